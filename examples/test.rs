@@ -10,7 +10,7 @@ fn main() {
     let path_in2 = path_in.clone();
     match YaffFont::from_path(path_in) {
         Ok(font) => {
-            let yaff_string = to_yaff_string(&font);
+            let yaff_string = to_yaff_string(&font).expect("Unable to encode font");
             std::fs::write(path_out, yaff_string).expect("Unable to write file");
         }
         Err(e) => {
@@ -0,0 +1,390 @@
+//! Bidirectional text support for the renderer.
+//!
+//! Embedding levels come from the Unicode Bidirectional Algorithm, and a font
+//! without a `direction` takes the paragraph direction from its text. Without
+//! the `bidi` feature, a built-in implementation resolves the implicit levels
+//! (rules P2-P3, W1-W7, N1-N2 and I1-I2) from the bidi classes of common
+//! scripts, ignoring explicit embedding and isolate controls; with it, the full
+//! algorithm and character database of the `unicode-bidi` crate are used.
+//! Either way, whitespace at the end of the text is reset to the paragraph level
+//! with rule L1, runs are reordered for display with rule L2 and characters at
+//! right-to-left levels are mirrored.
+
+use crate::models::*;
+use crate::segment::is_combining;
+
+/// Bidi character classes, named as in UAX #9, without the explicit formatting
+/// classes.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    L,
+    R,
+    AL,
+    EN,
+    ES,
+    ET,
+    AN,
+    CS,
+    NSM,
+    B,
+    S,
+    WS,
+    ON,
+}
+
+/// Bidi class of a character, for the scripts and punctuation in common use.
+fn class(c: char) -> Class {
+    use Class::*;
+    match c {
+        '\n' | '\r' | '\u{1C}'..='\u{1E}' | '\u{85}' | '\u{2029}' => B,
+        '\t' | '\u{B}' | '\u{1F}' => S,
+        '\u{C}'
+        | ' '
+        | '\u{1680}'
+        | '\u{2000}'..='\u{200A}'
+        | '\u{2028}'
+        | '\u{205F}'
+        | '\u{3000}' => WS,
+        '0'..='9'
+        | '²'
+        | '³'
+        | '¹'
+        | '\u{6F0}'..='\u{6F9}'
+        | '\u{2070}'
+        | '\u{2074}'..='\u{2079}'
+        | '\u{2080}'..='\u{2089}'
+        | '\u{FF10}'..='\u{FF19}' => EN,
+        '+' | '-' | '\u{207A}' | '\u{207B}' | '\u{208A}' | '\u{208B}' | '\u{2212}' | '\u{FB29}'
+        | '\u{FE62}' | '\u{FE63}' | '\u{FF0B}' | '\u{FF0D}' => ES,
+        '#'
+        | '$'
+        | '%'
+        | '¢'..='¥'
+        | '°'
+        | '±'
+        | '\u{609}'
+        | '\u{60A}'
+        | '\u{66A}'
+        | '\u{2030}'..='\u{2034}'
+        | '\u{20A0}'..='\u{20CF}'
+        | '\u{FF03}'..='\u{FF05}' => ET,
+        ',' | '.' | '/' | ':' | '\u{A0}' | '\u{60C}' | '\u{202F}' | '\u{2044}' | '\u{FF0C}'
+        | '\u{FF0E}' | '\u{FF0F}' | '\u{FF1A}' => CS,
+        '\u{600}'..='\u{605}' | '\u{660}'..='\u{669}' | '\u{66B}' | '\u{66C}' | '\u{6DD}' => AN,
+        '\u{200E}' => L,
+        '\u{200F}' => R,
+        _ if is_combining(c) => NSM,
+        '\u{600}'..='\u{7BF}'
+        | '\u{860}'..='\u{8FF}'
+        | '\u{FB50}'..='\u{FDFF}'
+        | '\u{FE70}'..='\u{FEFF}'
+        | '\u{1EE00}'..='\u{1EEFF}' => AL,
+        '\u{590}'..='\u{5FF}'
+        | '\u{7C0}'..='\u{85F}'
+        | '\u{FB1D}'..='\u{FB4F}'
+        | '\u{10800}'..='\u{10FFF}'
+        | '\u{1E800}'..='\u{1EDFF}'
+        | '\u{1EF00}'..='\u{1EFFF}' => R,
+        _ if c.is_alphanumeric() => L,
+        _ => ON,
+    }
+}
+
+/// Base embedding level of a paragraph for a font, `None` to detect it from the text.
+fn base_level(font: &YaffFont) -> Option<u8> {
+    match font.direction {
+        Some(WritingDirection::LeftToRight) => Some(0),
+        Some(WritingDirection::RightToLeft) => Some(1),
+        None => None,
+    }
+}
+
+/// Resets trailing whitespace, and whitespace before segment and paragraph
+/// separators, to the paragraph level by rule L1.
+fn reset_whitespace(classes: &[Class], levels: &mut [u8], paragraph: u8) {
+    let mut trailing = true;
+    for (class, level) in classes.iter().zip(levels).rev() {
+        match class {
+            Class::S | Class::B => {
+                *level = paragraph;
+                trailing = true;
+            }
+            Class::WS if trailing => *level = paragraph,
+            _ => trailing = false,
+        }
+    }
+}
+
+/// Embedding level of each character of a single paragraph, and the paragraph level.
+#[cfg(feature = "bidi")]
+pub(crate) fn levels(font: &YaffFont, text: &str) -> (Vec<u8>, u8) {
+    use unicode_bidi::{Level, ParagraphBidiInfo};
+
+    let base = base_level(font).map(|level| {
+        if level == 0 {
+            Level::ltr()
+        } else {
+            Level::rtl()
+        }
+    });
+    let info = ParagraphBidiInfo::new(text, base);
+    let mut levels: Vec<u8> = text
+        .char_indices()
+        .map(|(i, _)| info.levels[i].number())
+        .collect();
+    let paragraph = info.paragraph_level.number();
+    let classes: Vec<Class> = text.chars().map(class).collect();
+    reset_whitespace(&classes, &mut levels, paragraph);
+    (levels, paragraph)
+}
+
+/// Embedding level of each character of a single paragraph, and the paragraph level.
+#[cfg(not(feature = "bidi"))]
+pub(crate) fn levels(font: &YaffFont, text: &str) -> (Vec<u8>, u8) {
+    use Class::*;
+
+    let classes: Vec<Class> = text.chars().map(class).collect();
+    // P2, P3: the first strong character sets the paragraph direction
+    let paragraph = base_level(font).unwrap_or_else(|| {
+        match classes.iter().find(|&&c| matches!(c, L | R | AL)) {
+            Some(R | AL) => 1,
+            _ => 0,
+        }
+    });
+    // The text is one run at the paragraph level, so sos and eos are its direction
+    let embedding = if paragraph.is_multiple_of(2) { L } else { R };
+    let mut types = classes.clone();
+
+    // W1: marks take the class of the character before them
+    let mut previous = embedding;
+    for t in &mut types {
+        if *t == NSM {
+            *t = previous;
+        }
+        previous = *t;
+    }
+    // W2, W3: European digits after Arabic letters are Arabic digits
+    let mut strong = embedding;
+    for t in &mut types {
+        match *t {
+            L | R | AL => strong = *t,
+            EN if strong == AL => *t = AN,
+            _ => {}
+        }
+        if *t == AL {
+            *t = R;
+        }
+    }
+    // W4: a single separator between two numbers of the same kind joins them
+    for i in 1..types.len().saturating_sub(1) {
+        let (before, after) = (types[i - 1], types[i + 1]);
+        if before == after
+            && (before == EN && matches!(types[i], ES | CS) || before == AN && types[i] == CS)
+        {
+            types[i] = before;
+        }
+    }
+    // W5: terminators next to European digits are part of the number
+    let mut i = 0;
+    while i < types.len() {
+        let start = i;
+        while i < types.len() && types[i] == ET {
+            i += 1;
+        }
+        if i == start {
+            i += 1;
+        } else if (start > 0 && types[start - 1] == EN) || types.get(i) == Some(&EN) {
+            types[start..i].fill(EN);
+        }
+    }
+    // W6, W7: other separators are neutral, and European digits in left-to-right
+    // context are left-to-right
+    let mut strong = embedding;
+    for t in &mut types {
+        match *t {
+            ES | ET | CS => *t = ON,
+            L | R => strong = *t,
+            EN if strong == L => *t = L,
+            _ => {}
+        }
+    }
+    // N1, N2: neutrals between text of one direction take it, others the embedding's
+    let direction = |t: Class| if t == L { L } else { R };
+    let mut i = 0;
+    while i < types.len() {
+        if !matches!(types[i], B | S | WS | ON) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < types.len() && matches!(types[i], B | S | WS | ON) {
+            i += 1;
+        }
+        let before = start
+            .checked_sub(1)
+            .map_or(embedding, |j| direction(types[j]));
+        let after = types.get(i).map_or(embedding, |&t| direction(t));
+        types[start..i].fill(if before == after { before } else { embedding });
+    }
+    // I1, I2
+    let mut levels: Vec<u8> = types
+        .iter()
+        .map(|&t| match (paragraph % 2, t) {
+            (0, R) => paragraph + 1,
+            (0, AN | EN) => paragraph + 2,
+            (1, L | EN | AN) => paragraph + 1,
+            _ => paragraph,
+        })
+        .collect();
+    reset_whitespace(&classes, &mut levels, paragraph);
+    (levels, paragraph)
+}
+
+/// Visual order of items with the given embedding levels, by rule L2: from the
+/// highest level down to the lowest odd level, reverse every run at that level
+/// or higher.
+pub(crate) fn visual_order(levels: &[u8]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..levels.len()).collect();
+    let Some(&highest) = levels.iter().max() else {
+        return order;
+    };
+    let lowest_odd = levels.iter().map(|&l| l | 1).min().unwrap_or(1);
+    for level in (lowest_odd..=highest).rev() {
+        let mut i = 0;
+        while i < order.len() {
+            if levels[order[i]] >= level {
+                let start = i;
+                while i < order.len() && levels[order[i]] >= level {
+                    i += 1;
+                }
+                order[start..i].reverse();
+            } else {
+                i += 1;
+            }
+        }
+    }
+    order
+}
+
+/// Characters with the Bidi_Mirrored property and their mirror images.
+const MIRRORED_PAIRS: &[(char, char)] = &[
+    ('(', ')'),
+    ('<', '>'),
+    ('[', ']'),
+    ('{', '}'),
+    ('«', '»'),
+    ('‹', '›'),
+    ('⁅', '⁆'),
+    ('⁽', '⁾'),
+    ('₍', '₎'),
+    ('∈', '∋'),
+    ('∉', '∌'),
+    ('∊', '∍'),
+    ('∼', '∽'),
+    ('≃', '⋍'),
+    ('≤', '≥'),
+    ('≦', '≧'),
+    ('≪', '≫'),
+    ('≮', '≯'),
+    ('≰', '≱'),
+    ('≲', '≳'),
+    ('≺', '≻'),
+    ('⊂', '⊃'),
+    ('⊄', '⊅'),
+    ('⊆', '⊇'),
+    ('⊏', '⊐'),
+    ('⊑', '⊒'),
+    ('⊢', '⊣'),
+    ('⋐', '⋑'),
+    ('⌈', '⌉'),
+    ('⌊', '⌋'),
+    ('〈', '〉'),
+    ('❨', '❩'),
+    ('❪', '❫'),
+    ('❬', '❭'),
+    ('❮', '❯'),
+    ('❰', '❱'),
+    ('❲', '❳'),
+    ('❴', '❵'),
+    ('⟦', '⟧'),
+    ('⟨', '⟩'),
+    ('⟪', '⟫'),
+    ('⦃', '⦄'),
+    ('⦅', '⦆'),
+    ('〈', '〉'),
+    ('《', '》'),
+    ('「', '」'),
+    ('『', '』'),
+    ('【', '】'),
+    ('〔', '〕'),
+    ('〖', '〗'),
+    ('〘', '〙'),
+    ('〚', '〛'),
+    ('﹙', '﹚'),
+    ('﹛', '﹜'),
+    ('﹝', '﹞'),
+    ('﹤', '﹥'),
+    ('（', '）'),
+    ('＜', '＞'),
+    ('［', '］'),
+    ('｛', '｝'),
+    ('｟', '｠'),
+    ('｢', '｣'),
+];
+
+/// The mirror image of a character, if it has one.
+pub(crate) fn mirror(c: char) -> Option<char> {
+    MIRRORED_PAIRS.iter().find_map(|&(open, close)| {
+        if c == open {
+            Some(close)
+        } else if c == close {
+            Some(open)
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visual_order() {
+        assert_eq!(visual_order(&[0, 0, 0]), vec![0, 1, 2]);
+        assert_eq!(visual_order(&[1, 1, 1]), vec![2, 1, 0]);
+        // Left-to-right text embedded in a right-to-left paragraph
+        assert_eq!(visual_order(&[1, 2, 2, 1]), vec![3, 1, 2, 0]);
+        assert_eq!(visual_order(&[0, 1, 1, 0, 1]), vec![0, 2, 1, 3, 4]);
+        assert_eq!(mirror('('), Some(')'));
+        assert_eq!(mirror('»'), Some('«'));
+        assert_eq!(mirror('a'), None);
+    }
+
+    #[test]
+    fn test_bidi_levels() {
+        let font = YaffFont::new();
+        // Hebrew letters are right-to-left, digits and Latin left-to-right
+        assert_eq!(levels(&font, "ab אב"), (vec![0, 0, 0, 1, 1], 0));
+        assert_eq!(levels(&font, "אב ab"), (vec![1, 1, 1, 2, 2], 1));
+
+        let font = YaffFont {
+            direction: Some(WritingDirection::RightToLeft),
+            ..Default::default()
+        };
+        assert_eq!(levels(&font, "ab"), (vec![2, 2], 1));
+        // Digits and the space between them and Latin text stay left to right
+        assert_eq!(levels(&font, "abc 123"), (vec![2; 7], 1));
+        assert_eq!(levels(&font, "אב 12"), (vec![1, 1, 1, 2, 2], 1));
+        // Digits after Arabic letters are Arabic digits, which a separator joins
+        // but a percent sign doesn't
+        assert_eq!(levels(&font, "عد 1,5%"), (vec![1, 1, 1, 2, 2, 2, 1], 1));
+        // Rule L1 puts a tab and the whitespace before it at the paragraph level
+        let font = YaffFont::new();
+        assert_eq!(
+            levels(&font, "ab אב \tאב "),
+            (vec![0, 0, 0, 1, 1, 0, 0, 1, 1, 0], 0)
+        );
+    }
+}
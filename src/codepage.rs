@@ -0,0 +1,291 @@
+//! Codepage tables for converting between codepoint and Unicode labels.
+//!
+//! A [`Codepage`] maps the encoding-specific byte sequences used in
+//! `Label::Codepoint` to Unicode characters and back. Tables for common legacy
+//! encodings are built in, as are the double-byte CJK charsets Shift-JIS,
+//! GB2312, Big5 and EUC-KR (with the `cjk` feature); additional tables can be
+//! added at runtime with [`register_codepage`].
+
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+mod tables;
+
+/// A bidirectional mapping between encoding-specific codes and Unicode characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Codepage {
+    name: String,
+    mapping: Mapping,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mapping {
+    Table {
+        to_unicode: HashMap<Vec<u8>, char>,
+        from_unicode: HashMap<char, Vec<u8>>,
+    },
+    #[cfg(feature = "cjk")]
+    DoubleByte {
+        encoding: &'static encoding_rs::Encoding,
+        // Restrict double-byte codes to the EUC 0xA1-0xFE rows, excluding the
+        // vendor extensions of the superset codecs (GBK for GB2312, UHC for EUC-KR)
+        euc_only: bool,
+    },
+}
+
+impl Codepage {
+    /// Creates a codepage from `(bytes, char)` pairs.
+    ///
+    /// If several codes map to the same character, the shortest and then lowest
+    /// code is used when converting from Unicode.
+    pub fn new<I>(name: &str, mapping: I) -> Self
+    where
+        I: IntoIterator<Item = (Vec<u8>, char)>,
+    {
+        let mut to_unicode = HashMap::new();
+        let mut from_unicode: HashMap<char, Vec<u8>> = HashMap::new();
+        for (code, c) in mapping {
+            to_unicode.insert(code.clone(), c);
+            match from_unicode.get(&c) {
+                Some(existing) if (existing.len(), existing) <= (code.len(), &code) => {}
+                _ => {
+                    from_unicode.insert(c, code);
+                }
+            }
+        }
+        Codepage {
+            name: name.to_string(),
+            mapping: Mapping::Table {
+                to_unicode,
+                from_unicode,
+            },
+        }
+    }
+
+    /// Looks up a codepage by encoding name, e.g. `cp437`, `ISO-8859-1`, `mac-roman`
+    /// or `shift-jis`.
+    ///
+    /// Names are matched case-insensitively, ignoring punctuation. Codepages added
+    /// with [`register_codepage`] take precedence over the built-in tables.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let key = normalize_name(name);
+        if let Some(custom) = registry().read().unwrap().get(&key) {
+            return Some(custom.clone());
+        }
+        #[cfg(feature = "cjk")]
+        if let Some(codepage) = builtin_double_byte(&key) {
+            return Some(codepage);
+        }
+        builtin_table(&key).map(|(canonical, table)| Self::from_table(canonical, table))
+    }
+
+    fn from_table(name: &str, table: &[u32; 256]) -> Self {
+        Self::new(
+            name,
+            table.iter().enumerate().filter_map(|(code, &value)| {
+                if value == tables::NONE {
+                    None
+                } else {
+                    std::char::from_u32(value).map(|c| (vec![code as u8], c))
+                }
+            }),
+        )
+    }
+
+    /// The canonical name of the codepage, e.g. `cp437` for a lookup of `CP-437`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the Unicode character for an encoding-specific byte sequence.
+    pub fn decode(&self, bytes: &[u8]) -> Option<char> {
+        match &self.mapping {
+            Mapping::Table { to_unicode, .. } => to_unicode.get(bytes).copied(),
+            #[cfg(feature = "cjk")]
+            Mapping::DoubleByte { encoding, euc_only } => {
+                if bytes.is_empty() || bytes.len() > 2 || (*euc_only && !is_euc_code(bytes)) {
+                    return None;
+                }
+                let decoded =
+                    encoding.decode_without_bom_handling_and_without_replacement(bytes)?;
+                let mut chars = decoded.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if !c.is_control() => Some(c),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Returns the encoding-specific byte sequence for a Unicode character.
+    pub fn encode(&self, c: char) -> Option<Vec<u8>> {
+        match &self.mapping {
+            Mapping::Table { from_unicode, .. } => from_unicode.get(&c).cloned(),
+            #[cfg(feature = "cjk")]
+            Mapping::DoubleByte { encoding, .. } => {
+                let mut buffer = [0u8; 4];
+                let (bytes, _, had_errors) = encoding.encode(c.encode_utf8(&mut buffer));
+                if had_errors || self.decode(&bytes) != Some(c) {
+                    return None;
+                }
+                Some(bytes.into_owned())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "cjk")]
+fn is_euc_code(bytes: &[u8]) -> bool {
+    match bytes {
+        [b] => *b < 0x80,
+        [lead, trail] => (0xA1..=0xFE).contains(lead) && (0xA1..=0xFE).contains(trail),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "cjk")]
+fn builtin_double_byte(key: &str) -> Option<Codepage> {
+    let (name, encoding, euc_only) = match key {
+        "shiftjis" | "sjis" | "cp932" | "windows31j" | "mskanji" => {
+            ("shift-jis", encoding_rs::SHIFT_JIS, false)
+        }
+        "gb2312" | "euccn" | "gb231280" => ("gb2312", encoding_rs::GBK, true),
+        "gbk" | "cp936" => ("gbk", encoding_rs::GBK, false),
+        "big5" | "cp950" => ("big5", encoding_rs::BIG5, false),
+        "euckr" | "ksc5601" | "ksx1001" => ("euc-kr", encoding_rs::EUC_KR, true),
+        "cp949" | "uhc" | "windows949" => ("cp949", encoding_rs::EUC_KR, false),
+        _ => return None,
+    };
+    Some(Codepage {
+        name: name.to_string(),
+        mapping: Mapping::DoubleByte { encoding, euc_only },
+    })
+}
+
+/// Registers a custom codepage under its name, replacing any earlier
+/// registration or built-in table of the same name.
+pub fn register_codepage(codepage: Codepage) {
+    let key = normalize_name(&codepage.name);
+    registry().write().unwrap().insert(key, codepage);
+}
+
+static REGISTRY_LOCK: OnceLock<RwLock<HashMap<String, Codepage>>> = OnceLock::new();
+fn registry() -> &'static RwLock<HashMap<String, Codepage>> {
+    REGISTRY_LOCK.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Reduces an encoding name to lowercase alphanumerics, so that `ISO-8859-1`,
+/// `iso8859_1` and `iso 8859-1` all compare equal.
+pub(crate) fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Resolves a normalized name to the canonical name and table of a built-in codepage.
+fn builtin_table(key: &str) -> Option<(&'static str, &'static [u32; 256])> {
+    let entry = match key {
+        "ascii" | "usascii" | "iso646us" | "646" => ("ascii", &tables::ASCII),
+        "cp437" | "ibm437" | "437" | "oemus" => ("cp437", &tables::CP437),
+        "cp850" | "ibm850" | "850" => ("cp850", &tables::CP850),
+        "cp852" | "ibm852" | "852" => ("cp852", &tables::CP852),
+        "cp866" | "ibm866" | "866" => ("cp866", &tables::CP866),
+        "cp1250" | "windows1250" => ("windows-1250", &tables::CP1250),
+        "cp1251" | "windows1251" => ("windows-1251", &tables::CP1251),
+        "cp1252" | "windows1252" => ("windows-1252", &tables::CP1252),
+        "iso88591" | "latin1" | "l1" => ("iso-8859-1", &tables::ISO_8859_1),
+        "iso88592" | "latin2" | "l2" => ("iso-8859-2", &tables::ISO_8859_2),
+        "iso88593" | "latin3" | "l3" => ("iso-8859-3", &tables::ISO_8859_3),
+        "iso88594" | "latin4" | "l4" => ("iso-8859-4", &tables::ISO_8859_4),
+        "iso88595" | "cyrillic" => ("iso-8859-5", &tables::ISO_8859_5),
+        "iso88596" | "arabic" => ("iso-8859-6", &tables::ISO_8859_6),
+        "iso88597" | "greek" => ("iso-8859-7", &tables::ISO_8859_7),
+        "iso88598" | "hebrew" => ("iso-8859-8", &tables::ISO_8859_8),
+        "iso88599" | "latin5" | "l5" => ("iso-8859-9", &tables::ISO_8859_9),
+        "iso885910" | "latin6" | "l6" => ("iso-8859-10", &tables::ISO_8859_10),
+        "iso885911" | "thai" => ("iso-8859-11", &tables::ISO_8859_11),
+        "iso885913" | "latin7" | "l7" => ("iso-8859-13", &tables::ISO_8859_13),
+        "iso885914" | "latin8" | "l8" => ("iso-8859-14", &tables::ISO_8859_14),
+        "iso885915" | "latin9" | "l9" => ("iso-8859-15", &tables::ISO_8859_15),
+        "iso885916" | "latin10" | "l10" => ("iso-8859-16", &tables::ISO_8859_16),
+        "koi8r" => ("koi8-r", &tables::KOI8_R),
+        "koi8u" => ("koi8-u", &tables::KOI8_U),
+        "macroman" | "mac" | "macintosh" => ("mac-roman", &tables::MAC_ROMAN),
+        "atascii" | "atari" => ("atascii", &tables::ATASCII),
+        "petscii" | "cbm" => ("petscii", &tables::PETSCII),
+        _ => return None,
+    };
+    Some(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_lookup_and_aliases() {
+        let cp437 = Codepage::from_name("CP437").unwrap();
+        assert_eq!(cp437.decode(&[0x01]), Some('☺'));
+        assert_eq!(cp437.decode(&[0xDB]), Some('█'));
+        assert_eq!(cp437.encode('é'), Some(vec![0x82]));
+
+        let latin1 = Codepage::from_name("ISO-8859-1").unwrap();
+        assert_eq!(Codepage::from_name("latin-1"), Some(latin1.clone()));
+        assert_eq!(latin1.decode(&[0xE9]), Some('é'));
+        assert_eq!(latin1.decode(&[0x85]), None); // C1 control
+
+        assert_eq!(
+            Codepage::from_name("mac-roman").unwrap().decode(&[0xA5]),
+            Some('•')
+        );
+        assert_eq!(
+            Codepage::from_name("petscii").unwrap().decode(&[0x5C]),
+            Some('£')
+        );
+        assert!(Codepage::from_name("no-such-encoding").is_none());
+    }
+
+    #[test]
+    fn test_register_custom_codepage() {
+        let custom = Codepage::new(
+            "test-custom",
+            [
+                (vec![0x01], 'α'),
+                (vec![0x02], 'β'),
+                (vec![0x00, 0x01], 'α'),
+            ],
+        );
+        register_codepage(custom);
+
+        let found = Codepage::from_name("Test_Custom").unwrap();
+        assert_eq!(found.decode(&[0x02]), Some('β'));
+        assert_eq!(found.decode(&[0x00, 0x01]), Some('α'));
+        assert_eq!(found.encode('α'), Some(vec![0x01]));
+    }
+
+    #[cfg(feature = "cjk")]
+    #[test]
+    fn test_double_byte_codepages() {
+        let sjis = Codepage::from_name("Shift_JIS").unwrap();
+        assert_eq!(sjis.decode(&[0x82, 0xA0]), Some('あ'));
+        assert_eq!(sjis.decode(&[0xB1]), Some('ｱ')); // half-width katakana
+        assert_eq!(sjis.decode(&[0x41]), Some('A'));
+        assert_eq!(sjis.decode(&[0x82]), None); // lone lead byte
+        assert_eq!(sjis.encode('あ'), Some(vec![0x82, 0xA0]));
+
+        let gb2312 = Codepage::from_name("GB2312").unwrap();
+        assert_eq!(gb2312.decode(&[0xB0, 0xA1]), Some('啊'));
+        assert_eq!(gb2312.encode('啊'), Some(vec![0xB0, 0xA1]));
+        // GBK extension outside the GB2312 rows
+        assert_eq!(gb2312.decode(&[0x81, 0x40]), None);
+
+        let big5 = Codepage::from_name("big5").unwrap();
+        assert_eq!(big5.decode(&[0xA4, 0x40]), Some('一'));
+        assert_eq!(big5.encode('一'), Some(vec![0xA4, 0x40]));
+
+        let euc_kr = Codepage::from_name("EUC-KR").unwrap();
+        assert_eq!(euc_kr.decode(&[0xB0, 0xA1]), Some('가'));
+        assert_eq!(euc_kr.encode('가'), Some(vec![0xB0, 0xA1]));
+    }
+}
@@ -0,0 +1,161 @@
+//! Parser diagnostics with source locations and a human-readable renderer.
+//!
+//! A [`Diagnostic`] describes one problem found while parsing: where it is (line,
+//! column and byte span in the input), the offending source line, a stable
+//! machine-readable code and a message. [`Diagnostic::render`] formats it with
+//! the source snippet and a caret underline, in the style of compiler output.
+
+use std::fmt;
+use std::ops::Range;
+
+/// How serious a problem is. For the parser, whether it stopped the affected
+/// property or glyph from loading; for [`validate`](crate::validate), whether the
+/// data is unusable as-is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Category of a problem found while parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticKind {
+    /// Deprecated syntax such as unquoted tags or the `offset`, `tracking` and
+    /// `kern-to` glyph properties.
+    Deprecated,
+    /// A property key the parser doesn't know; its value is discarded.
+    UnknownProperty,
+    /// A known property the library doesn't support; its value is discarded.
+    UnsupportedProperty,
+    /// A bitmap row shorter than the first row, padded with paper.
+    ShortBitmapLine,
+    InvalidSyntax,
+    UnexpectedEndOfInput,
+    InvalidPropertyValue,
+    UnsupportedFeature,
+    SemanticError,
+    /// A bitmap row longer than the first row.
+    InconsistentGlyphLineLength,
+    InvalidGlyphCharacter,
+    Io,
+}
+
+impl DiagnosticKind {
+    /// Stable identifier for tools that filter or count diagnostics.
+    pub fn code(self) -> &'static str {
+        match self {
+            DiagnosticKind::Deprecated => "deprecated",
+            DiagnosticKind::UnknownProperty => "unknown-property",
+            DiagnosticKind::UnsupportedProperty => "unsupported-property",
+            DiagnosticKind::ShortBitmapLine => "short-bitmap-line",
+            DiagnosticKind::InvalidSyntax => "invalid-syntax",
+            DiagnosticKind::UnexpectedEndOfInput => "unexpected-end-of-input",
+            DiagnosticKind::InvalidPropertyValue => "invalid-property-value",
+            DiagnosticKind::UnsupportedFeature => "unsupported-feature",
+            DiagnosticKind::SemanticError => "semantic-error",
+            DiagnosticKind::InconsistentGlyphLineLength => "inconsistent-glyph-line-length",
+            DiagnosticKind::InvalidGlyphCharacter => "invalid-glyph-character",
+            DiagnosticKind::Io => "io",
+        }
+    }
+}
+
+/// A problem found while parsing.
+///
+/// `line` and `column` are 1-based, with the column counted in characters;
+/// `span` holds byte offsets into the whole input. A diagnostic whose source
+/// line is no longer available (or that has no line, like an I/O error) has
+/// an empty `source_line` and a zero column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    pub line: usize,
+    pub column: usize,
+    pub span: Range<usize>,
+    pub source_line: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic that has not been located in the source yet.
+    pub fn new(severity: Severity, kind: DiagnosticKind, line: usize, message: String) -> Self {
+        Diagnostic {
+            severity,
+            kind,
+            line,
+            column: 0,
+            span: 0..0,
+            source_line: String::new(),
+            message,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    /// Formats the diagnostic with its source line and a caret under the span:
+    ///
+    /// ```text
+    /// error[invalid-glyph-character]: Invalid glyph character 'x'
+    ///  --> font.yaff:5:5
+    ///   |
+    /// 5 |   @@x
+    ///   |     ^
+    /// ```
+    pub fn render(&self, source_name: Option<&str>) -> String {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let mut location = source_name.map(str::to_string).unwrap_or_default();
+        if self.line > 0 {
+            if !location.is_empty() {
+                location.push(':');
+            }
+            location.push_str(&self.line.to_string());
+            if self.column > 0 {
+                location.push_str(&format!(":{}", self.column));
+            }
+        }
+
+        let mut out = format!("{severity}[{}]: {}\n", self.code(), self.message);
+        let gutter = " ".repeat(self.line.to_string().len());
+        if !location.is_empty() {
+            out.push_str(&format!("{gutter}--> {location}\n"));
+        }
+        if self.column > 0 {
+            let source_line = self.source_line.replace('\t', " ");
+            let start = self.column - 1;
+            let spanned = &self.source_line[self.byte_column()..];
+            let width = spanned
+                .char_indices()
+                .take_while(|(i, _)| *i < self.span.len())
+                .count()
+                .max(1);
+            out.push_str(&format!("{gutter} |\n"));
+            out.push_str(&format!("{} | {source_line}\n", self.line));
+            out.push_str(&format!(
+                "{gutter} | {}{}\n",
+                " ".repeat(start),
+                "^".repeat(width)
+            ));
+        }
+        out
+    }
+
+    /// Byte offset of the span start within `source_line`.
+    fn byte_column(&self) -> usize {
+        self.source_line
+            .char_indices()
+            .nth(self.column - 1)
+            .map_or(self.source_line.len(), |(i, _)| i)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
@@ -1,346 +1,807 @@
 use crate::models::*;
 use std::collections::HashMap;
-use std::fmt::Write;
+use std::fmt::{self, Write};
+use std::io;
 
-const GLYPH_ITEM_INDENT: &str = "  ";
+/// How Unicode labels are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LabelStyle {
+    /// Quoted characters where possible, `u+XXXX` otherwise, e.g. `'A':`.
+    #[default]
+    Char,
+    /// Always `u+XXXX`, e.g. `u+0041:`.
+    Unicode,
+    /// Both forms, each on its own label line.
+    Both,
+}
+
+/// Order in which global and per-glyph properties are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PropertyOrder {
+    /// The order of the YAFF specification.
+    #[default]
+    Canonical,
+    /// Sorted by property key.
+    Alphabetical,
+}
+
+/// Options controlling the textual layout produced by the encoder.
+///
+/// The default options produce the same output as [`to_yaff_string`].
+/// Note that the parser only reads `@` and `.` in bitmaps, so changing
+/// `ink` or `paper` produces output meant for display rather than re-reading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Indentation of bitmap rows, glyph properties and multi-line values.
+    pub indent: String,
+    /// Character for set pixels.
+    pub ink: char,
+    /// Character for unset pixels.
+    pub paper: char,
+    /// Decimal places for kerning values; `None` writes the shortest exact form.
+    /// Rounded values don't read back unchanged.
+    pub kerning_precision: Option<usize>,
+    /// Decimal places for other fractional values such as `point-size`;
+    /// `None` writes the shortest exact form.
+    pub float_precision: Option<usize>,
+    pub label_style: LabelStyle,
+    /// Write printable non-ASCII characters as quoted labels instead of `u+XXXX`.
+    pub quote_non_ascii: bool,
+    pub property_order: PropertyOrder,
+    /// Leave out properties whose value equals the default a reader would assume,
+    /// such as `direction: left-to-right` or a glyph bearing equal to the global one.
+    pub omit_defaults: bool,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {
+            indent: "  ".to_string(),
+            ink: '@',
+            paper: '.',
+            kerning_precision: None,
+            float_precision: None,
+            label_style: LabelStyle::Char,
+            quote_non_ascii: false,
+            property_order: PropertyOrder::Canonical,
+            omit_defaults: false,
+        }
+    }
+}
+
+fn can_quote(c: char, options: &EncodeOptions) -> bool {
+    if c == '\'' || c.is_control() {
+        return false;
+    }
+    c.is_ascii() || (options.quote_non_ascii && !c.is_whitespace())
+}
+
+fn format_unicode_label(values: &[u32], as_chars: bool, options: &EncodeOptions) -> String {
+    if values.is_empty() {
+        return "''".to_string();
+    }
+    // The parser splits sequences at commas, so only a lone comma may be quoted
+    let is_sequence = values.len() > 1;
+    values
+        .iter()
+        .map(|&v| match std::char::from_u32(v) {
+            Some(c) if as_chars && can_quote(c, options) && !(is_sequence && c == ',') => {
+                format!("'{c}'")
+            }
+            _ => format!("u+{v:04X}"),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
 
-fn format_label_to_string(label: &Label) -> String {
+fn format_label_to_string(label: &Label, options: &EncodeOptions) -> String {
     match label {
         Label::Unicode(values) => {
-            if values.is_empty() {
-                "''".to_string()
-            } else {
-                values
-                    .iter()
-                    .map(|&v| match std::char::from_u32(v) {
-                        Some(c) if c.is_ascii() && !c.is_control() && c != '\'' => {
-                            format!("'{c}'")
-                        }
-                        _ => format!("u+{v:04X}"),
-                    })
-                    .collect::<Vec<String>>()
-                    .join(", ")
-            }
+            format_unicode_label(values, options.label_style != LabelStyle::Unicode, options)
         }
-        Label::Codepoint(values) => {
-            // Assuming u16 now
-            if values.is_empty() {
-                "".to_string()
-            } else {
-                values
-                    .iter()
-                    .map(|v| format!("0x{v:X}"))
-                    .collect::<Vec<String>>()
-                    .join(", ")
+        Label::Codepoint(bytes) => match bytes.as_slice() {
+            [] => "".to_string(),
+            // A leading zero byte would be lost in the single-integer form, and
+            // the parser reads at most two bytes from each element
+            [0, ..] | [_, _, _, ..] => bytes
+                .iter()
+                .map(|b| format!("0x{b:X}"))
+                .collect::<Vec<String>>()
+                .join(", "),
+            [first, rest @ ..] => {
+                let mut s = format!("0x{first:X}");
+                for b in rest {
+                    write!(s, "{b:02X}").unwrap();
+                }
+                s
             }
-        }
+        },
         Label::Tag(tag_str) => format!("\"{tag_str}\""),
         Label::Anonymous => "".to_string(),
     }
 }
 
-fn append_property<T, F>(buffer: &mut String, key: &str, value_opt: &Option<T>, formatter: F)
-where
-    F: Fn(&T) -> String,
-{
-    if let Some(value) = value_opt {
-        let formatted_value = formatter(value);
-        if formatted_value.contains('\n') {
-            writeln!(buffer, "{key}:").unwrap();
-            for line in formatted_value.lines() {
-                writeln!(buffer, "{GLYPH_ITEM_INDENT}{line}").unwrap();
+/// Label lines for a glyph label; more than one only for [`LabelStyle::Both`].
+fn format_label_lines(label: &Label, options: &EncodeOptions) -> Vec<String> {
+    match label {
+        Label::Unicode(values) if options.label_style == LabelStyle::Both => {
+            let as_chars = format_unicode_label(values, true, options);
+            let as_codes = format_unicode_label(values, false, options);
+            if as_chars == as_codes {
+                vec![as_chars]
+            } else {
+                vec![as_chars, as_codes]
             }
-        } else if formatted_value.is_empty() && key != "yaff" {
-            writeln!(buffer, "{key}:").unwrap();
-        } else if !formatted_value.is_empty() {
-            writeln!(buffer, "{key}: {formatted_value}").unwrap();
         }
+        _ => vec![format_label_to_string(label, options)],
     }
 }
 
-fn append_string(buffer: &mut String, key: &str, value_opt: &Option<String>) {
-    append_property(buffer, key, value_opt, |s| s.clone());
+/// Rejects labels that would be written as text the parser reads back differently.
+fn check_label(label: &Label, glyph: usize, is_kerning_key: bool) -> Result<(), EncodeError> {
+    let message = match label {
+        Label::Unicode(values) if values.iter().any(|&v| v > 0xFF_FFFF) => {
+            "Unicode values above u+FFFFFF cannot be written"
+        }
+        Label::Codepoint(bytes) if bytes.is_empty() => "codepoint label has no bytes",
+        Label::Tag(tag) if tag.contains('\n') => "tags cannot contain line breaks",
+        Label::Anonymous if is_kerning_key => "kerning pairs need a non-anonymous label",
+        _ => return Ok(()),
+    };
+    Err(EncodeError::InvalidLabel {
+        glyph,
+        label: label.clone(),
+        message: message.to_string(),
+    })
 }
 
-fn append_i32(buffer: &mut String, key: &str, value_opt: &Option<i32>) {
-    append_property(buffer, key, value_opt, |v| v.to_string());
+fn check_bitmap(bitmap: &Bitmap, glyph: usize) -> Result<(), EncodeError> {
+    if bitmap.is_empty() {
+        return Ok(());
+    }
+    let message = if bitmap.width == 0 || bitmap.height == 0 {
+        format!(
+            "bitmap is {}x{}, but only both or neither dimension may be zero",
+            bitmap.width, bitmap.height
+        )
+    } else if bitmap.pixels.len() != bitmap.height {
+        format!(
+            "bitmap height is {} but it has {} rows",
+            bitmap.height,
+            bitmap.pixels.len()
+        )
+    } else if let Some((row, pixels)) = bitmap
+        .pixels
+        .iter()
+        .enumerate()
+        .find(|(_, pixels)| pixels.len() != bitmap.width)
+    {
+        format!(
+            "bitmap width is {} but row {row} has {} pixels",
+            bitmap.width,
+            pixels.len()
+        )
+    } else {
+        return Ok(());
+    };
+    Err(EncodeError::InconsistentBitmap { glyph, message })
 }
 
-fn append_f32(buffer: &mut String, key: &str, value_opt: &Option<f32>) {
-    append_property(buffer, key, value_opt, |v| format!("{v}"));
+/// Whether a string value has to be written as an indented block to read back unchanged.
+fn needs_block(value: &str) -> bool {
+    value.contains('\n') || value.trim() != value || value.ends_with(':')
 }
 
-fn append_i32_tuple(buffer: &mut String, key: &str, value_opt: &Option<(i32, i32)>) {
-    append_property(buffer, key, value_opt, |(v1, v2)| format!("{v1} {v2}"));
+/// Quotes a line of a block value if the parser would otherwise trim it, strip its
+/// quotes, or mistake it for a label or bitmap.
+fn quote_block_line(line: &str) -> String {
+    let is_bitmap_like =
+        line == "-" || (!line.is_empty() && line.chars().all(|c| c == '.' || c == '@'));
+    let is_quoted = line.len() >= 2 && line.starts_with('"') && line.ends_with('"');
+    if line.is_empty() || line.trim() != line || line.ends_with(':') || is_bitmap_like || is_quoted
+    {
+        format!("\"{line}\"")
+    } else {
+        line.to_string()
+    }
 }
 
-fn append_u32_tuple(buffer: &mut String, key: &str, value_opt: &Option<(u32, u32)>) {
-    append_property(buffer, key, value_opt, |(v1, v2)| format!("{v1} {v2}"));
+fn format_float(value: f32, precision: Option<usize>) -> String {
+    match precision {
+        Some(precision) => format!("{value:.precision$}"),
+        None => format!("{value}"),
+    }
 }
 
-fn append_i32_quad_tuple(buffer: &mut String, key: &str, value_opt: &Option<(i32, i32, i32, i32)>) {
-    append_property(buffer, key, value_opt, |(v1, v2, v3, v4)| {
-        format!("{v1} {v2} {v3} {v4}")
-    });
+/// A formatted property waiting to be written.
+struct Property {
+    key: &'static str,
+    value: String,
+    /// Write the value as an indented block even if it fits on one line.
+    block: bool,
 }
 
-fn append_font_spacing(buffer: &mut String, key: &str, value_opt: &Option<FontSpacing>) {
-    append_property(buffer, key, value_opt, |v| match v {
-        FontSpacing::Proportional => "proportional".to_string(),
-        FontSpacing::Monospace => "monospace".to_string(),
-        FontSpacing::CharacterCell => "character-cell".to_string(),
-        FontSpacing::MultiCell => "multi-cell".to_string(),
-    });
+/// Collects formatted properties so they can be validated, filtered and reordered
+/// before anything is written.
+struct PropertyList<'a> {
+    options: &'a EncodeOptions,
+    glyph: Option<usize>,
+    properties: Vec<Property>,
+    error: Option<EncodeError>,
 }
 
-fn append_writing_direction(buffer: &mut String, key: &str, value_opt: &Option<WritingDirection>) {
-    append_property(buffer, key, value_opt, |v| match v {
-        WritingDirection::LeftToRight => "left-to-right".to_string(),
-        WritingDirection::RightToLeft => "right-to-left".to_string(),
-    });
-}
+impl<'a> PropertyList<'a> {
+    fn new(options: &'a EncodeOptions, glyph: Option<usize>) -> Self {
+        PropertyList {
+            options,
+            glyph,
+            properties: Vec::new(),
+            error: None,
+        }
+    }
 
-fn format_kerning_map_to_string(map: &HashMap<Label, f32>) -> String {
-    let mut s = String::new();
-    let mut sorted_pairs: Vec<(&Label, &f32)> = map.iter().collect();
-    sorted_pairs.sort_by(|a, b| {
-        // Sort by label string representation
-        let a_str = format_label_to_string(a.0);
-        let b_str = format_label_to_string(b.0);
-        a_str.cmp(&b_str)
-    });
+    /// Records the first problem found; it is reported by [`Self::validated`].
+    fn fail(&mut self, err: EncodeError) {
+        self.error.get_or_insert(err);
+    }
 
-    for (i, (label, value)) in sorted_pairs.iter().enumerate() {
-        if i > 0 {
-            s.push('\n');
+    fn check_finite(&mut self, key: &'static str, value: f32) {
+        if !value.is_finite() {
+            self.fail(EncodeError::NonFiniteValue {
+                glyph: self.glyph,
+                property_key: key.to_string(),
+                value,
+            });
         }
-        let label_str = format_label_to_string(label);
-        write!(s, "{GLYPH_ITEM_INDENT}{label_str} {value:.2}").unwrap();
     }
-    s
-}
-
-pub fn to_yaff_string(font: &YaffFont) -> String {
-    let mut buffer = String::new();
-    let mut has_written_any_global_prop = false;
 
-    let ensure_newline_for_global = |buf: &mut String, written_flag: &mut bool| {
-        if !*written_flag && !buf.is_empty() && !buf.ends_with('\n') {
-            buf.push('\n');
+    fn validated(mut self) -> Result<Self, EncodeError> {
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => Ok(self),
         }
-        *written_flag = true;
-    };
+    }
 
-    macro_rules! append_global {
-        ($key:expr, $field:expr, $writer:ident) => {
-            if $field.is_some() {
-                ensure_newline_for_global(&mut buffer, &mut has_written_any_global_prop);
-                $writer(&mut buffer, $key, $field);
-                has_written_any_global_prop = true;
+    fn add<T, F>(
+        &mut self,
+        key: &'static str,
+        value_opt: &Option<T>,
+        default: Option<T>,
+        formatter: F,
+    ) where
+        T: PartialEq,
+        F: Fn(&T) -> String,
+    {
+        if let Some(value) = value_opt {
+            if self.options.omit_defaults && default.as_ref() == Some(value) {
+                return;
             }
+            self.properties.push(Property {
+                key,
+                value: formatter(value),
+                block: false,
+            });
+        }
+    }
+
+    fn string(&mut self, key: &'static str, value_opt: &Option<String>) {
+        if value_opt.as_ref().is_some_and(|s| s.is_empty()) {
+            self.fail(EncodeError::EmptyValue {
+                glyph: self.glyph,
+                property_key: key.to_string(),
+            });
+        }
+        self.add(key, value_opt, None, |s| s.clone());
+    }
+
+    fn i32(&mut self, key: &'static str, value_opt: &Option<i32>) {
+        self.add(key, value_opt, None, |v| v.to_string());
+    }
+
+    fn f32(&mut self, key: &'static str, value_opt: &Option<f32>) {
+        if let Some(value) = *value_opt {
+            self.check_finite(key, value);
+        }
+        let precision = self.options.float_precision;
+        self.add(key, value_opt, None, |&v| format_float(v, precision));
+    }
+
+    fn i32_tuple(&mut self, key: &'static str, value_opt: &Option<(i32, i32)>) {
+        self.add(key, value_opt, None, |(v1, v2)| format!("{v1} {v2}"));
+    }
+
+    fn u32_tuple(&mut self, key: &'static str, value_opt: &Option<(u32, u32)>) {
+        self.add(key, value_opt, None, |(v1, v2)| format!("{v1} {v2}"));
+    }
+
+    fn i32_quad_tuple(&mut self, key: &'static str, value_opt: &Option<(i32, i32, i32, i32)>) {
+        self.add(key, value_opt, None, |(v1, v2, v3, v4)| {
+            format!("{v1} {v2} {v3} {v4}")
+        });
+    }
+
+    fn font_spacing(&mut self, key: &'static str, value_opt: &Option<FontSpacing>) {
+        self.add(key, value_opt, None, |v| match v {
+            FontSpacing::Proportional => "proportional".to_string(),
+            FontSpacing::Monospace => "monospace".to_string(),
+            FontSpacing::CharacterCell => "character-cell".to_string(),
+            FontSpacing::MultiCell => "multi-cell".to_string(),
+        });
+    }
+
+    fn writing_direction(&mut self, key: &'static str, value_opt: &Option<WritingDirection>) {
+        let default = Some(WritingDirection::LeftToRight);
+        self.add(key, value_opt, default, |v| match v {
+            WritingDirection::LeftToRight => "left-to-right".to_string(),
+            WritingDirection::RightToLeft => "right-to-left".to_string(),
+        });
+    }
+
+    fn kerning_map(&mut self, key: &'static str, map_opt: &Option<HashMap<Label, f32>>) {
+        let Some(map) = map_opt else {
+            return;
         };
-        ($key:expr, $field:expr, $writer:ident, $formatter:expr) => {
-            if $field.is_some() {
-                ensure_newline_for_global(&mut buffer, &mut has_written_any_global_prop);
-                append_property(&mut buffer, $key, $field, $formatter);
-                has_written_any_global_prop = true;
+        for (label, &value) in map {
+            self.check_finite(key, value);
+            if let Err(err) = check_label(label, self.glyph.unwrap_or_default(), true) {
+                self.fail(err);
             }
-        };
+        }
+        let mut sorted_pairs: Vec<(String, f32)> = map
+            .iter()
+            .filter(|&(_, &value)| !(self.options.omit_defaults && value == 0.0))
+            .map(|(label, &value)| (format_label_to_string(label, self.options), value))
+            .collect();
+        if sorted_pairs.is_empty() {
+            return;
+        }
+        // Sort by label string representation
+        sorted_pairs.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let precision = self.options.kerning_precision;
+        let value = sorted_pairs
+            .iter()
+            .map(|(label_str, value)| format!("{label_str} {}", format_float(*value, precision)))
+            .collect::<Vec<String>>()
+            .join("\n");
+        self.properties.push(Property {
+            key,
+            value,
+            block: true,
+        });
     }
 
-    // --- Encode Global Properties ---
-    append_global!("yaff", &font.yaff_version, append_string);
-    append_global!("name", &font.name, append_string);
-    append_global!("family", &font.family, append_string);
-    append_global!("subfamily", &font.subfamily, append_string);
-    append_global!("revision", &font.revision, append_string);
-    append_global!("point-size", &font.point_size, append_f32);
-    append_global!("line-height", &font.line_height, append_i32);
-    append_global!("style", &font.style, append_string);
-    append_global!("weight", &font.weight, append_string);
-    append_global!("slant", &font.slant, append_string);
-    append_global!("setwidth", &font.setwidth, append_string);
-    append_global!("decoration", &font.decoration, append_string);
-    append_global!("x-height", &font.x_height, append_i32);
-    append_global!("cap-height", &font.cap_height, append_i32);
-    append_global!("ascent", &font.ascent, append_i32);
-    append_global!("descent", &font.descent, append_i32);
-    append_global!("pixel-size", &font.pixel_size, append_i32);
-    append_global!("leading", &font.leading, append_i32);
-    append_global!("raster-bounds", &font.raster_bounds, append_i32_quad_tuple);
-    append_global!("ink-bounds", &font.ink_bounds, append_i32_quad_tuple);
-    append_global!("raster-size", &font.raster_size, append_u32_tuple);
-    append_global!("cell-size", &font.cell_size, append_u32_tuple);
-    append_global!("bounding-box", &font.bounding_box, append_u32_tuple);
-    append_global!("average-width", &font.average_width, append_f32);
-    append_global!("max-width", &font.max_width, append_i32);
-    append_global!("cap-width", &font.cap_width, append_i32);
-    append_global!("digit-width", &font.digit_width, append_i32);
-    append_global!("spacing", &font.spacing, append_font_spacing);
-    append_global!("direction", &font.direction, append_writing_direction);
-    append_global!("bold-smear", &font.bold_smear, append_i32);
-    append_global!("italic-pitch", &font.italic_pitch, append_i32_tuple);
-    append_global!("outline-thickness", &font.outline_thickness, append_i32);
-    append_global!("underline-thickness", &font.underline_thickness, append_i32);
-    append_global!("underline-descent", &font.underline_descent, append_i32);
-    append_global!(
-        "strikethrough-thickness",
-        &font.strikethrough_thickness,
-        append_i32
-    );
-    append_global!(
-        "strikethrough-ascent",
-        &font.strikethrough_ascent,
-        append_i32
+    fn write_to(mut self, out: &mut dyn Write) -> fmt::Result {
+        if self.options.property_order == PropertyOrder::Alphabetical {
+            self.properties.sort_by_key(|property| property.key);
+        }
+        let indent = &self.options.indent;
+        for Property { key, value, block } in &self.properties {
+            if *block || needs_block(value) {
+                writeln!(out, "{key}:")?;
+                for line in value.split('\n') {
+                    writeln!(out, "{indent}{}", quote_block_line(line))?;
+                }
+            } else {
+                writeln!(out, "{key}: {value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_global_properties(
+    out: &mut dyn Write,
+    font: &YaffFont,
+    options: &EncodeOptions,
+) -> Result<(), EncodeError> {
+    let mut props = PropertyList::new(options, None);
+    props.string("yaff", &font.yaff_version);
+    props.string("name", &font.name);
+    props.string("family", &font.family);
+    props.string("subfamily", &font.subfamily);
+    props.string("revision", &font.revision);
+    props.f32("point-size", &font.point_size);
+    props.i32("line-height", &font.line_height);
+    props.string("style", &font.style);
+    props.string("weight", &font.weight);
+    props.string("slant", &font.slant);
+    props.string("setwidth", &font.setwidth);
+    props.string("decoration", &font.decoration);
+    props.i32("x-height", &font.x_height);
+    props.i32("cap-height", &font.cap_height);
+    props.i32("ascent", &font.ascent);
+    props.i32("descent", &font.descent);
+    props.i32("pixel-size", &font.pixel_size);
+    props.i32("leading", &font.leading);
+    props.i32_quad_tuple("raster-bounds", &font.raster_bounds);
+    props.i32_quad_tuple("ink-bounds", &font.ink_bounds);
+    props.u32_tuple("raster-size", &font.raster_size);
+    props.u32_tuple("cell-size", &font.cell_size);
+    props.u32_tuple("bounding-box", &font.bounding_box);
+    props.f32("average-width", &font.average_width);
+    props.i32("max-width", &font.max_width);
+    props.i32("cap-width", &font.cap_width);
+    props.i32("digit-width", &font.digit_width);
+    props.font_spacing("spacing", &font.spacing);
+    props.writing_direction("direction", &font.direction);
+    props.add("bold-smear", &font.bold_smear, Some(1), |v| v.to_string());
+    props.i32_tuple("italic-pitch", &font.italic_pitch);
+    props.i32("outline-thickness", &font.outline_thickness);
+    props.i32("underline-thickness", &font.underline_thickness);
+    props.i32("underline-descent", &font.underline_descent);
+    props.i32("strikethrough-thickness", &font.strikethrough_thickness);
+    props.i32("strikethrough-ascent", &font.strikethrough_ascent);
+    props.i32("superscript-size", &font.superscript_size);
+    props.i32_tuple("superscript-offset", &font.superscript_offset);
+    props.i32("subscript-size", &font.subscript_size);
+    props.i32_tuple("subscript-offset", &font.subscript_offset);
+    props.i32("small-cap-size", &font.small_cap_size);
+    props.i32("word-space", &font.word_space);
+    props.i32("min-word-space", &font.min_word_space);
+    props.i32("max-word-space", &font.max_word_space);
+    props.i32("sentence-space", &font.sentence_space);
+    props.string("author", &font.author);
+    props.string("foundry", &font.foundry);
+    props.string("copyright", &font.copyright);
+    props.string("notice", &font.notice);
+    props.string("device", &font.device);
+    props.add(
+        "pixel-aspect",
+        &font.pixel_aspect,
+        Some((1, 1)),
+        |(v1, v2)| format!("{v1} {v2}"),
     );
-    append_global!("superscript-size", &font.superscript_size, append_i32);
-    append_global!(
-        "superscript-offset",
-        &font.superscript_offset,
-        append_i32_tuple
+    props.u32_tuple("dpi", &font.dpi);
+    props.string("converter", &font.converter);
+    props.string("source-name", &font.source_name);
+    props.string("source-format", &font.source_format);
+    props.string("history", &font.history);
+    props.string("encoding", &font.encoding);
+    props.string("default-char", &font.default_char_label_raw);
+    props.string("word-boundary", &font.word_boundary_label_raw);
+    props.add("left-bearing", &font.global_left_bearing, Some(0), |v| {
+        v.to_string()
+    });
+    props.add("right-bearing", &font.global_right_bearing, Some(0), |v| {
+        v.to_string()
+    });
+    props.i32("shift-up", &font.global_shift_up);
+    props.validated()?.write_to(out)?;
+    Ok(())
+}
+
+/// Global values a glyph inherits when it doesn't set them itself.
+#[derive(Debug, Default)]
+struct InheritedMetrics {
+    left_bearing: i32,
+    right_bearing: i32,
+    shift_up: i32,
+}
+
+impl InheritedMetrics {
+    fn from_font(font: &YaffFont) -> Self {
+        InheritedMetrics {
+            left_bearing: font.global_left_bearing.unwrap_or(0),
+            right_bearing: font.global_right_bearing.unwrap_or(0),
+            shift_up: font.global_shift_up.unwrap_or(0),
+        }
+    }
+}
+
+fn write_glyph(
+    out: &mut dyn Write,
+    glyph: &GlyphDefinition,
+    index: usize,
+    separate: bool,
+    inherited: &InheritedMetrics,
+    options: &EncodeOptions,
+) -> Result<(), EncodeError> {
+    let indent = options.indent.as_str();
+
+    for (i, label) in glyph.labels.iter().enumerate() {
+        check_label(label, index, false)?;
+        if glyph.labels[..i].contains(label) {
+            return Err(EncodeError::DuplicateLabel {
+                glyph: index,
+                label: label.clone(),
+            });
+        }
+    }
+    check_bitmap(&glyph.bitmap, index)?;
+
+    let mut props = PropertyList::new(options, Some(index));
+    props.add(
+        "left-bearing",
+        &glyph.left_bearing,
+        Some(inherited.left_bearing),
+        |v| v.to_string(),
     );
-    append_global!("subscript-size", &font.subscript_size, append_i32);
-    append_global!("subscript-offset", &font.subscript_offset, append_i32_tuple);
-    append_global!("small-cap-size", &font.small_cap_size, append_i32);
-    append_global!("word-space", &font.word_space, append_i32);
-    append_global!("min-word-space", &font.min_word_space, append_i32);
-    append_global!("max-word-space", &font.max_word_space, append_i32);
-    append_global!("sentence-space", &font.sentence_space, append_i32);
-    append_global!("author", &font.author, append_string);
-    append_global!("foundry", &font.foundry, append_string);
-    append_global!("copyright", &font.copyright, append_string);
-    append_global!("notice", &font.notice, append_string);
-    append_global!("device", &font.device, append_string);
-    append_global!("pixel-aspect", &font.pixel_aspect, append_u32_tuple);
-    append_global!("dpi", &font.dpi, append_u32_tuple);
-    append_global!("converter", &font.converter, append_string);
-    append_global!("source-name", &font.source_name, append_string);
-    append_global!("source-format", &font.source_format, append_string);
-    append_global!("history", &font.history, append_string);
-    append_global!("encoding", &font.encoding, append_string);
-    append_global!("default-char", &font.default_char_label_raw, append_string);
-    append_global!(
-        "word-boundary",
-        &font.word_boundary_label_raw,
-        append_string
+    props.add(
+        "right-bearing",
+        &glyph.right_bearing,
+        Some(inherited.right_bearing),
+        |v| v.to_string(),
     );
-    append_global!("left-bearing", &font.global_left_bearing, append_i32);
-    append_global!("right-bearing", &font.global_right_bearing, append_i32);
-    append_global!("shift-up", &font.global_shift_up, append_i32);
-
-    // --- Encode Glyphs ---
-    if !font.glyphs.is_empty() {
-        if has_written_any_global_prop {
-            while !buffer.ends_with("\n\n") {
-                buffer.push('\n');
+    props.add("shift-up", &glyph.shift_up, Some(inherited.shift_up), |v| {
+        v.to_string()
+    });
+    props.i32("top-bearing", &glyph.top_bearing);
+    props.i32("bottom-bearing", &glyph.bottom_bearing);
+    props.i32("shift-left", &glyph.shift_left);
+    props.f32("scalable-width", &glyph.scalable_width);
+    props.kerning_map("right-kerning", &glyph.right_kerning);
+    props.kerning_map("left-kerning", &glyph.left_kerning);
+    let props = props.validated()?;
+
+    if separate {
+        out.write_char('\n')?; // Blank line before each glyph
+    }
+
+    // Labels; a glyph without labels is written (and read back) as anonymous
+    if glyph.labels.is_empty() {
+        writeln!(out, ":")?;
+    } else {
+        for label in &glyph.labels {
+            for label_str in format_label_lines(label, options) {
+                writeln!(out, "{label_str}:")?;
             }
-        } else if !buffer.is_empty() && !buffer.ends_with('\n') {
-            buffer.push('\n');
         }
+    }
 
-        for (glyph_index, glyph) in font.glyphs.iter().enumerate() {
-            if glyph_index > 0 {
-                buffer.push('\n'); // Blank line between glyphs
+    // Bitmap
+    if glyph.bitmap.is_empty() {
+        writeln!(out, "{indent}-")?;
+    } else {
+        for row in &glyph.bitmap.pixels {
+            out.write_str(indent)?;
+            for &pixel_is_set in row {
+                out.write_char(if pixel_is_set {
+                    options.ink
+                } else {
+                    options.paper
+                })?;
             }
+            out.write_char('\n')?;
+        }
+    }
 
-            // Labels
-            if glyph.labels.is_empty() && !glyph.bitmap.is_empty() {
-                writeln!(buffer, ":").unwrap();
-            } else {
-                for label in &glyph.labels {
-                    let label_str = format_label_to_string(label);
-                    writeln!(buffer, "{label_str}:").unwrap();
-                }
-            }
+    // Per-Glyph Properties
+    if !props.properties.is_empty() {
+        out.write_char('\n')?;
+        props.write_to(&mut IndentedWriter::new(out, indent))?;
+    }
+    Ok(())
+}
 
-            // Bitmap
-            if glyph.bitmap.is_empty() {
-                writeln!(buffer, "{GLYPH_ITEM_INDENT}-").unwrap();
-            } else {
-                for row in &glyph.bitmap.pixels {
-                    write!(buffer, "{GLYPH_ITEM_INDENT}").unwrap();
-                    for &pixel_is_set in row {
-                        buffer.push(if pixel_is_set { '@' } else { '.' });
-                    }
-                    buffer.push('\n');
-                }
-            }
+/// Prefixes every line written through it with a fixed indent.
+struct IndentedWriter<'a> {
+    out: &'a mut dyn Write,
+    indent: &'a str,
+    at_line_start: bool,
+}
 
-            // Per-Glyph Properties
-            let mut per_glyph_props_s = String::new();
-            let mut has_any_per_glyph_prop = false;
+impl<'a> IndentedWriter<'a> {
+    fn new(out: &'a mut dyn Write, indent: &'a str) -> Self {
+        IndentedWriter {
+            out,
+            indent,
+            at_line_start: true,
+        }
+    }
+}
 
-            if glyph.left_bearing.is_some() {
-                append_i32(&mut per_glyph_props_s, "left-bearing", &glyph.left_bearing);
-                has_any_per_glyph_prop = true;
-            }
-            if glyph.right_bearing.is_some() {
-                append_i32(
-                    &mut per_glyph_props_s,
-                    "right-bearing",
-                    &glyph.right_bearing,
-                );
-                has_any_per_glyph_prop = true;
-            }
-            if glyph.shift_up.is_some() {
-                append_i32(&mut per_glyph_props_s, "shift-up", &glyph.shift_up);
-                has_any_per_glyph_prop = true;
-            }
-            if glyph.top_bearing.is_some() {
-                append_i32(&mut per_glyph_props_s, "top-bearing", &glyph.top_bearing);
-                has_any_per_glyph_prop = true;
-            }
-            if glyph.bottom_bearing.is_some() {
-                append_i32(
-                    &mut per_glyph_props_s,
-                    "bottom-bearing",
-                    &glyph.bottom_bearing,
-                );
-                has_any_per_glyph_prop = true;
-            }
-            if glyph.shift_left.is_some() {
-                append_i32(&mut per_glyph_props_s, "shift-left", &glyph.shift_left);
-                has_any_per_glyph_prop = true;
-            }
-            if glyph.scalable_width.is_some() {
-                append_f32(
-                    &mut per_glyph_props_s,
-                    "scalable-width",
-                    &glyph.scalable_width,
-                );
-                has_any_per_glyph_prop = true;
+impl Write for IndentedWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for line in s.split_inclusive('\n') {
+            if self.at_line_start {
+                self.out.write_str(self.indent)?;
             }
+            self.out.write_str(line)?;
+            self.at_line_start = line.ends_with('\n');
+        }
+        Ok(())
+    }
+}
 
-            if let Some(map) = &glyph.right_kerning {
-                if !map.is_empty() {
-                    let formatted_map = format_kerning_map_to_string(map);
-                    if !formatted_map.is_empty() {
-                        writeln!(per_glyph_props_s, "right-kerning:\n{formatted_map}").unwrap();
-                        has_any_per_glyph_prop = true;
-                    }
-                }
-            }
-            if let Some(map) = &glyph.left_kerning {
-                if !map.is_empty() {
-                    let formatted_map = format_kerning_map_to_string(map);
-                    if !formatted_map.is_empty() {
-                        writeln!(per_glyph_props_s, "left-kerning:\n{formatted_map}").unwrap();
-                        has_any_per_glyph_prop = true;
-                    }
-                }
-            }
+/// Records whether anything was written, so blank separator lines are only
+/// emitted between non-empty sections.
+struct TrackingWriter<'a> {
+    out: &'a mut dyn Write,
+    written: bool,
+}
 
-            if has_any_per_glyph_prop {
-                while !buffer.ends_with("\n\n") {
-                    buffer.push('\n');
-                }
-                for line in per_glyph_props_s.trim_end_matches('\n').lines() {
-                    writeln!(buffer, "{GLYPH_ITEM_INDENT}{line}").unwrap();
-                }
-            }
+impl Write for TrackingWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.written |= !s.is_empty();
+        self.out.write_str(s)
+    }
+}
+
+/// Layout state shared by [`YaffWriter`] and [`write_yaff`]: a blank line
+/// separates the global properties from the first glyph and each glyph from the next.
+#[derive(Debug, Default)]
+struct Emitter {
+    options: EncodeOptions,
+    inherited: InheritedMetrics,
+    wrote_anything: bool,
+    glyph_count: usize,
+}
+
+impl Emitter {
+    fn new(options: EncodeOptions) -> Self {
+        Emitter {
+            options,
+            ..Default::default()
+        }
+    }
+
+    fn header(&mut self, out: &mut dyn Write, font: &YaffFont) -> Result<(), EncodeError> {
+        let mut tracking = TrackingWriter {
+            out,
+            written: false,
+        };
+        write_global_properties(&mut tracking, font, &self.options)?;
+        self.inherited = InheritedMetrics::from_font(font);
+        self.wrote_anything |= tracking.written;
+        Ok(())
+    }
+
+    fn glyph(&mut self, out: &mut dyn Write, glyph: &GlyphDefinition) -> Result<(), EncodeError> {
+        write_glyph(
+            out,
+            glyph,
+            self.glyph_count,
+            self.wrote_anything,
+            &self.inherited,
+            &self.options,
+        )?;
+        self.wrote_anything = true;
+        self.glyph_count += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write) -> Result<(), EncodeError> {
+        if !self.wrote_anything {
+            // Ensure an empty font is still a single line
+            out.write_char('\n')?;
+            self.wrote_anything = true;
         }
+        Ok(())
+    }
+}
+
+/// Forwards formatted output to an `io::Write`, keeping the underlying I/O error.
+struct IoAdapter<'a, W: io::Write> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
     }
-    buffer.trim_end_matches('\n').to_string() + "\n" // Ensure single trailing newline
+}
+
+/// Incremental YAFF encoder writing to any `io::Write`.
+///
+/// Write the global properties with [`write_header`](Self::write_header), then each
+/// glyph with [`write_glyph`](Self::write_glyph), and call [`finish`](Self::finish)
+/// to flush. Glyphs are written as they are passed in, so the whole font never
+/// needs to be held in memory. Each call validates its input first, so a rejected
+/// header or glyph leaves no partial output behind.
+///
+/// ```rust
+/// use libyaff::{GlyphDefinition, Label, YaffFont, YaffWriter};
+///
+/// let mut writer = YaffWriter::new(Vec::new());
+/// writer.write_header(&YaffFont { name: Some("Demo".to_string()), ..Default::default() })?;
+/// writer.write_glyph(&GlyphDefinition {
+///     labels: vec![Label::Unicode(vec![0x20])],
+///     ..Default::default()
+/// })?;
+/// let bytes = writer.finish()?;
+/// assert_eq!(String::from_utf8(bytes).unwrap(), "name: Demo\n\n' ':\n  -\n");
+/// # Ok::<(), libyaff::EncodeError>(())
+/// ```
+#[derive(Debug)]
+pub struct YaffWriter<W: io::Write> {
+    writer: W,
+    emitter: Emitter,
+}
+
+impl<W: io::Write> YaffWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, EncodeOptions::default())
+    }
+
+    pub fn with_options(writer: W, options: EncodeOptions) -> Self {
+        YaffWriter {
+            writer,
+            emitter: Emitter::new(options),
+        }
+    }
+
+    /// Writes the global properties of `font`. Its `glyphs` are ignored.
+    ///
+    /// Must be called before the first glyph is written.
+    pub fn write_header(&mut self, font: &YaffFont) -> Result<(), EncodeError> {
+        if self.emitter.glyph_count > 0 {
+            return Err(EncodeError::HeaderAfterGlyphs);
+        }
+        self.with_adapter(|emitter, out| emitter.header(out, font))
+    }
+
+    pub fn write_glyph(&mut self, glyph: &GlyphDefinition) -> Result<(), EncodeError> {
+        self.with_adapter(|emitter, out| emitter.glyph(out, glyph))
+    }
+
+    /// Writes the global properties followed by all glyphs of `font`.
+    pub fn write_font(&mut self, font: &YaffFont) -> Result<(), EncodeError> {
+        self.write_header(font)?;
+        for glyph in &font.glyphs {
+            self.write_glyph(glyph)?;
+        }
+        Ok(())
+    }
+
+    /// Completes the output, flushes the writer and returns it.
+    pub fn finish(mut self) -> Result<W, EncodeError> {
+        self.with_adapter(|emitter, out| emitter.finish(out))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn with_adapter<F>(&mut self, f: F) -> Result<(), EncodeError>
+    where
+        F: FnOnce(&mut Emitter, &mut dyn Write) -> Result<(), EncodeError>,
+    {
+        let mut adapter = IoAdapter {
+            inner: &mut self.writer,
+            error: None,
+        };
+        f(&mut self.emitter, &mut adapter).map_err(|err| match (err, adapter.error) {
+            (EncodeError::Format(_), Some(io_err)) => EncodeError::Io(io_err),
+            (err, _) => err,
+        })
+    }
+}
+
+/// Writes a complete font to any `fmt::Write`, such as a `String`.
+///
+/// Fails with an [`EncodeError`] if the font holds data that would not read back
+/// unchanged, such as empty strings, non-finite numbers or duplicate labels.
+pub fn write_yaff<W: Write>(out: &mut W, font: &YaffFont) -> Result<(), EncodeError> {
+    write_yaff_with_options(out, font, &EncodeOptions::default())
+}
+
+pub fn write_yaff_with_options<W: Write>(
+    out: &mut W,
+    font: &YaffFont,
+    options: &EncodeOptions,
+) -> Result<(), EncodeError> {
+    let mut emitter = Emitter::new(options.clone());
+    emitter.header(out, font)?;
+    for glyph in &font.glyphs {
+        emitter.glyph(out, glyph)?;
+    }
+    emitter.finish(out)
+}
+
+pub fn to_yaff_string(font: &YaffFont) -> Result<String, EncodeError> {
+    to_yaff_string_with_options(font, &EncodeOptions::default())
+}
+
+pub fn to_yaff_string_with_options(
+    font: &YaffFont,
+    options: &EncodeOptions,
+) -> Result<String, EncodeError> {
+    let mut buffer = String::new();
+    write_yaff_with_options(&mut buffer, font, options)?;
+    Ok(buffer)
 }
 
 #[cfg(test)]
@@ -353,8 +814,508 @@ mod tests {
         font.name = Some("Basic Font".to_string());
         font.ascent = Some(8);
 
-        let yaff_output = to_yaff_string(&font);
+        let yaff_output = to_yaff_string(&font).unwrap();
         assert!(yaff_output.contains("name: Basic Font"));
         assert!(yaff_output.contains("ascent: 8"));
     }
+
+    #[test]
+    fn test_yaff_writer_matches_to_yaff_string() {
+        let mut font = YaffFont::new();
+        font.name = Some("Stream".to_string());
+        font.notice = Some("first\nsecond".to_string());
+        font.glyphs = vec![
+            GlyphDefinition {
+                labels: vec![Label::Unicode(vec![0x41]), Label::Codepoint(vec![0x41])],
+                bitmap: Bitmap {
+                    pixels: vec![vec![false, true], vec![true, true]],
+                    width: 2,
+                    height: 2,
+                },
+                left_bearing: Some(1),
+                right_kerning: Some(HashMap::from([(Label::Unicode(vec![0x42]), -1.0)])),
+                ..Default::default()
+            },
+            GlyphDefinition {
+                labels: vec![Label::Tag("space".to_string())],
+                ..Default::default()
+            },
+        ];
+
+        let mut writer = YaffWriter::new(Vec::new());
+        writer.write_header(&font).unwrap();
+        for glyph in &font.glyphs {
+            writer.write_glyph(glyph).unwrap();
+        }
+        let streamed = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(streamed, to_yaff_string(&font).unwrap());
+        assert_eq!(
+            streamed,
+            "name: Stream\nnotice:\n  first\n  second\n\n'A':\n0x41:\n  .@\n  @@\n\n  \
+             left-bearing: 1\n  right-kerning:\n    'B' -1\n\n\"space\":\n  -\n"
+        );
+        assert_eq!(to_yaff_string(&YaffFont::new()).unwrap(), "\n");
+    }
+
+    #[test]
+    fn test_yaff_writer_propagates_io_errors() {
+        struct FailingWriter;
+        impl io::Write for FailingWriter {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut font = YaffFont::new();
+        font.name = Some("Broken".to_string());
+        let mut writer = YaffWriter::new(FailingWriter);
+        match writer.write_header(&font) {
+            Err(EncodeError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::BrokenPipe),
+            other => panic!("Expected an I/O error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_encoder_multi_byte_codepoint_labels() {
+        assert_eq!(
+            format_label_to_string(&Label::Codepoint(vec![0x41]), &EncodeOptions::default()),
+            "0x41"
+        );
+        assert_eq!(
+            format_label_to_string(
+                &Label::Codepoint(vec![0x81, 0x40]),
+                &EncodeOptions::default()
+            ),
+            "0x8140"
+        );
+        assert_eq!(
+            format_label_to_string(
+                &Label::Codepoint(vec![0x81, 0x05]),
+                &EncodeOptions::default()
+            ),
+            "0x8105"
+        );
+        assert_eq!(
+            format_label_to_string(
+                &Label::Codepoint(vec![0x00, 0x41]),
+                &EncodeOptions::default()
+            ),
+            "0x0, 0x41"
+        );
+        assert_eq!(
+            format_label_to_string(
+                &Label::Codepoint(vec![0x12, 0x34, 0x56]),
+                &EncodeOptions::default()
+            ),
+            "0x12, 0x34, 0x56"
+        );
+    }
+
+    #[test]
+    fn test_encode_options() {
+        let mut font = YaffFont::new();
+        font.name = Some("Options".to_string());
+        font.point_size = Some(10.5);
+        font.direction = Some(WritingDirection::LeftToRight);
+        font.global_left_bearing = Some(1);
+        font.glyphs = vec![GlyphDefinition {
+            labels: vec![Label::Unicode(vec![0xE9])],
+            bitmap: Bitmap {
+                pixels: vec![vec![true, false]],
+                width: 2,
+                height: 1,
+            },
+            left_bearing: Some(1),
+            right_bearing: Some(2),
+            right_kerning: Some(HashMap::from([
+                (Label::Unicode(vec![0x41]), 0.125),
+                (Label::Unicode(vec![0x42]), 0.0),
+            ])),
+            ..Default::default()
+        }];
+
+        let options = EncodeOptions {
+            indent: "\t".to_string(),
+            ink: '#',
+            paper: '-',
+            kerning_precision: None,
+            float_precision: Some(1),
+            label_style: LabelStyle::Both,
+            quote_non_ascii: true,
+            property_order: PropertyOrder::Alphabetical,
+            omit_defaults: true,
+        };
+        assert_eq!(
+            to_yaff_string_with_options(&font, &options).unwrap(),
+            "left-bearing: 1\nname: Options\npoint-size: 10.5\n\n'é':\nu+00E9:\n\t#-\n\n\t\
+             right-bearing: 2\n\tright-kerning:\n\t\t'A' 0.125\n"
+        );
+
+        let unicode_only = EncodeOptions {
+            label_style: LabelStyle::Unicode,
+            ..Default::default()
+        };
+        let output = to_yaff_string_with_options(&font, &unicode_only).unwrap();
+        assert!(output.starts_with("name: Options\npoint-size: 10.5\ndirection: left-to-right\n"));
+        assert!(output.contains("\nu+00E9:\n  @.\n"));
+        assert!(output.contains("    u+0041 0.125\n    u+0042 0\n"));
+        assert_eq!(
+            to_yaff_string_with_options(&font, &EncodeOptions::default()).unwrap(),
+            to_yaff_string(&font).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "parsing")]
+    fn test_encoder_escapes_values_and_labels() {
+        let mut font = YaffFont::new();
+        font.name = Some("  padded  ".to_string());
+        font.family = Some("\"quoted\"".to_string());
+        font.notice = Some("ends with:\n\n@@..\n-\n\"x\"\n trailing ".to_string());
+        font.copyright = Some("colon:".to_string());
+        font.glyphs = vec![GlyphDefinition {
+            labels: vec![
+                Label::Unicode(vec![0x2C, 0x41]),
+                Label::Codepoint(vec![0x81, 0x40, 0x41]),
+                Label::Tag("a\": tag:".to_string()),
+            ],
+            right_kerning: Some(HashMap::from([(Label::Unicode(vec![0x2C]), 1.5)])),
+            ..Default::default()
+        }];
+
+        let output = to_yaff_string(&font).unwrap();
+        assert!(output.contains("name:\n  \"  padded  \"\n"));
+        assert!(output.contains("family: \"quoted\"\n"));
+        assert!(output.contains(
+            "notice:\n  \"ends with:\"\n  \"\"\n  \"@@..\"\n  \"-\"\n  \"\"x\"\"\n  \" trailing \"\n"
+        ));
+        assert!(output.contains("copyright:\n  \"colon:\"\n"));
+        assert!(output.contains("u+002C, 'A':\n0x81, 0x40, 0x41:\n\"a\": tag:\":\n"));
+        assert!(output.contains("    ',' 1.5\n"));
+        assert_eq!(crate::parser::from_str(&output).unwrap(), font);
+    }
+
+    #[test]
+    fn test_encoder_rejects_unrepresentable_data() {
+        let glyph = GlyphDefinition {
+            labels: vec![Label::Unicode(vec![0x41])],
+            ..Default::default()
+        };
+        let font_with = |glyph: GlyphDefinition| YaffFont {
+            glyphs: vec![glyph],
+            ..Default::default()
+        };
+
+        let empty_name = YaffFont {
+            name: Some(String::new()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            to_yaff_string(&empty_name),
+            Err(EncodeError::EmptyValue { glyph: None, .. })
+        ));
+
+        let nan_size = YaffFont {
+            point_size: Some(f32::NAN),
+            ..Default::default()
+        };
+        assert!(matches!(
+            to_yaff_string(&nan_size),
+            Err(EncodeError::NonFiniteValue { glyph: None, .. })
+        ));
+
+        let duplicate = GlyphDefinition {
+            labels: vec![Label::Unicode(vec![0x41]), Label::Unicode(vec![0x41])],
+            ..glyph.clone()
+        };
+        assert!(matches!(
+            to_yaff_string(&font_with(duplicate)),
+            Err(EncodeError::DuplicateLabel { glyph: 0, .. })
+        ));
+
+        for label in [
+            Label::Unicode(vec![0x100_0000]),
+            Label::Codepoint(vec![]),
+            Label::Tag("two\nlines".to_string()),
+        ] {
+            let bad = GlyphDefinition {
+                labels: vec![label],
+                ..glyph.clone()
+            };
+            assert!(matches!(
+                to_yaff_string(&font_with(bad)),
+                Err(EncodeError::InvalidLabel { glyph: 0, .. })
+            ));
+        }
+
+        let anonymous_kerning = GlyphDefinition {
+            right_kerning: Some(HashMap::from([(Label::Anonymous, 1.0)])),
+            ..glyph.clone()
+        };
+        assert!(matches!(
+            to_yaff_string(&font_with(anonymous_kerning)),
+            Err(EncodeError::InvalidLabel { glyph: 0, .. })
+        ));
+
+        let ragged = GlyphDefinition {
+            bitmap: Bitmap {
+                pixels: vec![vec![true, true], vec![true]],
+                width: 2,
+                height: 2,
+            },
+            ..glyph.clone()
+        };
+        assert!(matches!(
+            to_yaff_string(&font_with(ragged)),
+            Err(EncodeError::InconsistentBitmap { glyph: 0, .. })
+        ));
+
+        // A rejected glyph leaves no partial output in a stream
+        let mut writer = YaffWriter::new(Vec::new());
+        writer.write_glyph(&glyph).unwrap();
+        let infinite = GlyphDefinition {
+            labels: vec![Label::Unicode(vec![0x42])],
+            scalable_width: Some(f32::INFINITY),
+            ..Default::default()
+        };
+        assert!(writer.write_glyph(&infinite).is_err());
+        assert!(matches!(
+            writer.write_header(&YaffFont::new()),
+            Err(EncodeError::HeaderAfterGlyphs)
+        ));
+        let bytes = writer.finish().unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), "'A':\n  -\n");
+    }
+
+    #[cfg(feature = "parsing")]
+    mod round_trip {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn text() -> impl Strategy<Value = String> {
+            any::<String>().prop_filter("values cannot be empty", |s| !s.is_empty())
+        }
+
+        fn label() -> impl Strategy<Value = Label> {
+            prop_oneof![
+                prop::collection::vec(0u32..=0xFF_FFFF, 0..4).prop_map(Label::Unicode),
+                prop::collection::vec(any::<char>().prop_map(|c| c as u32), 1..4)
+                    .prop_map(Label::Unicode),
+                prop::collection::vec(any::<u8>(), 1..4).prop_map(Label::Codepoint),
+                "[^\n]*".prop_map(Label::Tag),
+            ]
+        }
+
+        fn finite() -> impl Strategy<Value = f32> {
+            any::<f32>().prop_filter("finite", |v| v.is_finite())
+        }
+
+        fn kerning() -> impl Strategy<Value = Option<HashMap<Label, f32>>> {
+            prop::option::of(prop::collection::hash_map(label(), finite(), 1..4))
+        }
+
+        fn bitmap() -> impl Strategy<Value = Bitmap> {
+            prop_oneof![
+                Just(Bitmap::default()),
+                (1usize..6, 1usize..6).prop_flat_map(|(width, height)| {
+                    prop::collection::vec(prop::collection::vec(any::<bool>(), width), height)
+                        .prop_map(move |pixels| Bitmap {
+                            pixels,
+                            width,
+                            height,
+                        })
+                }),
+            ]
+        }
+
+        fn glyph() -> impl Strategy<Value = GlyphDefinition> {
+            let labels = prop_oneof![
+                prop::collection::hash_set(label(), 1..4)
+                    .prop_map(|labels| labels.into_iter().collect::<Vec<_>>()),
+                Just(vec![Label::Anonymous]),
+            ];
+            let metrics = (
+                prop::option::of(any::<i32>()),
+                prop::option::of(any::<i32>()),
+                prop::option::of(any::<i32>()),
+                prop::option::of(any::<i32>()),
+                prop::option::of(any::<i32>()),
+                prop::option::of(any::<i32>()),
+                prop::option::of(finite()),
+            );
+            (labels, bitmap(), metrics, kerning(), kerning()).prop_map(
+                |(labels, bitmap, metrics, right_kerning, left_kerning)| GlyphDefinition {
+                    labels,
+                    bitmap,
+                    left_bearing: metrics.0,
+                    right_bearing: metrics.1,
+                    shift_up: metrics.2,
+                    top_bearing: metrics.3,
+                    bottom_bearing: metrics.4,
+                    shift_left: metrics.5,
+                    scalable_width: metrics.6,
+                    right_kerning,
+                    left_kerning,
+                },
+            )
+        }
+
+        fn font() -> impl Strategy<Value = YaffFont> {
+            let string = || prop::option::of(text());
+            let int = || prop::option::of(any::<i32>());
+            let pair = || prop::option::of(any::<(i32, i32)>());
+            let size = || prop::option::of(any::<(u32, u32)>());
+            let names = (
+                (string(), string(), string(), string(), string()),
+                (string(), string(), string(), string(), string()),
+            );
+            let sources = (
+                (string(), string(), string(), string(), string()),
+                (string(), string(), string(), string(), string()),
+                (string(), string()),
+            );
+            let sizes = (
+                prop::option::of(finite()),
+                (int(), int(), int(), int(), int(), int(), int()),
+                prop::option::of(any::<(i32, i32, i32, i32)>()),
+                prop::option::of(any::<(i32, i32, i32, i32)>()),
+                (size(), size(), size(), size(), size()),
+            );
+            let widths = (
+                prop::option::of(finite()),
+                (int(), int(), int()),
+                (int(), int(), int(), int()),
+                (int(), int(), int()),
+            );
+            let decorations = (
+                (int(), pair(), int(), int(), int(), int(), int()),
+                (int(), pair(), int(), pair(), int()),
+            );
+            let enums = (
+                prop::option::of(prop_oneof![
+                    Just(FontSpacing::Proportional),
+                    Just(FontSpacing::Monospace),
+                    Just(FontSpacing::CharacterCell),
+                    Just(FontSpacing::MultiCell),
+                ]),
+                prop::option::of(prop_oneof![
+                    Just(WritingDirection::LeftToRight),
+                    Just(WritingDirection::RightToLeft),
+                ]),
+            );
+            let glyphs = prop::collection::vec(glyph(), 0..4);
+            (names, sources, sizes, widths, decorations, enums, glyphs).prop_map(
+                |(names, sources, sizes, widths, decorations, enums, glyphs)| {
+                    let ((yaff, name, family, subfamily, revision), more) = names;
+                    let (style, weight, slant, setwidth, decoration) = more;
+                    let ((author, foundry, copyright, notice, device), more, special) = sources;
+                    let (converter, source_name, source_format, history, encoding) = more;
+                    let (default_char, word_boundary) = special;
+                    let (point_size, heights, raster_bounds, ink_bounds, cells) = sizes;
+                    let (x_height, cap_height, ascent, descent, pixel_size, leading, line) =
+                        heights;
+                    let (raster_size, cell_size, bounding_box, pixel_aspect, dpi) = cells;
+                    let (average_width, (max_width, cap_width, digit_width), spaces, globals) =
+                        widths;
+                    let (word_space, min_word_space, max_word_space, sentence_space) = spaces;
+                    let (global_left_bearing, global_right_bearing, global_shift_up) = globals;
+                    let (styles, scripts) = decorations;
+                    let (
+                        bold_smear,
+                        italic_pitch,
+                        outline,
+                        underline,
+                        under_descent,
+                        strike,
+                        strike_ascent,
+                    ) = styles;
+                    let (
+                        superscript_size,
+                        superscript_offset,
+                        subscript_size,
+                        subscript_offset,
+                        small_cap,
+                    ) = scripts;
+                    YaffFont {
+                        yaff_version: yaff,
+                        name,
+                        family,
+                        subfamily,
+                        revision,
+                        point_size,
+                        line_height: line,
+                        style,
+                        weight,
+                        slant,
+                        setwidth,
+                        decoration,
+                        x_height,
+                        cap_height,
+                        ascent,
+                        descent,
+                        pixel_size,
+                        leading,
+                        raster_bounds,
+                        ink_bounds,
+                        raster_size,
+                        cell_size,
+                        bounding_box,
+                        average_width,
+                        max_width,
+                        cap_width,
+                        digit_width,
+                        spacing: enums.0,
+                        direction: enums.1,
+                        bold_smear,
+                        italic_pitch,
+                        outline_thickness: outline,
+                        underline_thickness: underline,
+                        underline_descent: under_descent,
+                        strikethrough_thickness: strike,
+                        strikethrough_ascent: strike_ascent,
+                        superscript_size,
+                        superscript_offset,
+                        subscript_size,
+                        subscript_offset,
+                        small_cap_size: small_cap,
+                        word_space,
+                        min_word_space,
+                        max_word_space,
+                        sentence_space,
+                        author,
+                        foundry,
+                        copyright,
+                        notice,
+                        device,
+                        pixel_aspect,
+                        dpi,
+                        converter,
+                        source_name,
+                        source_format,
+                        history,
+                        encoding,
+                        default_char_label_raw: default_char,
+                        word_boundary_label_raw: word_boundary,
+                        global_left_bearing,
+                        global_right_bearing,
+                        global_shift_up,
+                        glyphs,
+                    }
+                },
+            )
+        }
+
+        proptest! {
+            #[test]
+            fn encoded_fonts_parse_back_unchanged(font in font()) {
+                let encoded = to_yaff_string(&font).unwrap();
+                let parsed = crate::parser::from_str(&encoded)
+                    .map_err(|err| TestCaseError::fail(format!("{err}\n{encoded}")))?;
+                prop_assert_eq!(parsed, font, "{}", encoded);
+            }
+        }
+    }
 }
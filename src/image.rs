@@ -0,0 +1,389 @@
+//! Drawing into caller-supplied pixel buffers.
+//!
+//! An [`ImageBuffer`] wraps a mutable byte slice in one of the [`PixelFormat`]s,
+//! so glyphs and text can be drawn straight into a frame buffer, a texture or an
+//! image from any imaging library. Bitmaps are drawn with foreground and optional
+//! background colours, blended over the buffer by their alpha, enlarged by an
+//! integer scale, stretched to correct the font's `pixel-aspect`, and clipped to
+//! the buffer.
+
+use crate::models::*;
+use crate::render::Renderer;
+
+/// Layout of the pixels of an [`ImageBuffer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// Red, green, blue and alpha bytes.
+    Rgba8,
+    /// One luminance byte.
+    Gray8,
+    /// 16-bit little-endian words with 5 bits red, 6 bits green and 5 bits blue.
+    Rgb565,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8 => 4,
+            PixelFormat::Gray8 => 1,
+            PixelFormat::Rgb565 => 2,
+        }
+    }
+}
+
+/// A colour with straight (not premultiplied) alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color { r, g, b, a }
+    }
+
+    /// Luminance by the Rec. 601 weights.
+    fn luma(self) -> u8 {
+        ((self.r as u32 * 299 + self.g as u32 * 587 + self.b as u32 * 114 + 500) / 1000) as u8
+    }
+
+    /// This colour composited over `below` with the Porter-Duff "over" operator,
+    /// both in straight (not premultiplied) alpha.
+    fn over(self, below: Color) -> Color {
+        let alpha = self.a as u32;
+        // Weights of the two colours and the resulting alpha, in units of 1/255²
+        let top_weight = alpha * 255;
+        let bottom_weight = below.a as u32 * (255 - alpha);
+        let total = top_weight + bottom_weight;
+        if total == 0 {
+            return Color::TRANSPARENT;
+        }
+        let mix = |top: u8, bottom: u8| {
+            ((top as u32 * top_weight + bottom as u32 * bottom_weight + total / 2) / total) as u8
+        };
+        Color {
+            r: mix(self.r, below.r),
+            g: mix(self.g, below.g),
+            b: mix(self.b, below.b),
+            a: ((total + 127) / 255) as u8,
+        }
+    }
+}
+
+/// How a bitmap is drawn into an [`ImageBuffer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawOptions {
+    /// Colour of set pixels.
+    pub foreground: Color,
+    /// Colour of unset pixels; `None` leaves the buffer showing through.
+    pub background: Option<Color>,
+    /// Size of a font pixel in buffer pixels, before aspect correction.
+    pub scale: u32,
+    /// Width to height ratio of a font pixel, as in the `pixel-aspect` property.
+    /// Font pixels are stretched so they keep this shape on square buffer pixels.
+    pub pixel_aspect: (u32, u32),
+}
+
+impl Default for DrawOptions {
+    fn default() -> Self {
+        DrawOptions {
+            foreground: Color::BLACK,
+            background: None,
+            scale: 1,
+            pixel_aspect: (1, 1),
+        }
+    }
+}
+
+impl DrawOptions {
+    /// Default options with the `pixel-aspect` of a font.
+    pub fn for_font(font: &YaffFont) -> Self {
+        DrawOptions {
+            pixel_aspect: font.pixel_aspect.unwrap_or((1, 1)),
+            ..Default::default()
+        }
+    }
+
+    /// Width and height of a font pixel in buffer pixels: the scale times the
+    /// pixel aspect in lowest terms.
+    pub fn pixel_size(&self) -> (usize, usize) {
+        let (x, y) = self.pixel_aspect;
+        let divisor = gcd(x, y).max(1);
+        let scale = self.scale as usize;
+        (
+            scale * (x / divisor).max(1) as usize,
+            scale * (y / divisor).max(1) as usize,
+        )
+    }
+
+    /// Size a bitmap takes up in buffer pixels.
+    pub fn scaled_size(&self, bitmap: &Bitmap) -> (usize, usize) {
+        let (width, height) = self.pixel_size();
+        (bitmap.width * width, bitmap.height * height)
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// A caller-supplied pixel buffer to draw into.
+#[derive(Debug)]
+pub struct ImageBuffer<'b> {
+    data: &'b mut [u8],
+    format: PixelFormat,
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+impl<'b> ImageBuffer<'b> {
+    /// Wraps a buffer of tightly packed rows.
+    pub fn new(
+        data: &'b mut [u8],
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+    ) -> Result<Self, ImageError> {
+        Self::with_stride(
+            data,
+            width,
+            height,
+            width * format.bytes_per_pixel(),
+            format,
+        )
+    }
+
+    /// Wraps a buffer whose rows start `stride` bytes apart.
+    pub fn with_stride(
+        data: &'b mut [u8],
+        width: usize,
+        height: usize,
+        stride: usize,
+        format: PixelFormat,
+    ) -> Result<Self, ImageError> {
+        let row = width * format.bytes_per_pixel();
+        if stride < row {
+            return Err(ImageError::StrideTooSmall { stride, row });
+        }
+        let needed = if height == 0 {
+            0
+        } else {
+            stride * (height - 1) + row
+        };
+        if data.len() < needed {
+            return Err(ImageError::BufferTooSmall {
+                needed,
+                actual: data.len(),
+            });
+        }
+        Ok(ImageBuffer {
+            data,
+            format,
+            width,
+            height,
+            stride,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    fn offset(&self, x: usize, y: usize) -> usize {
+        y * self.stride + x * self.format.bytes_per_pixel()
+    }
+
+    /// Colour of a pixel, `None` outside the buffer.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let at = self.offset(x, y);
+        let bytes = &self.data[at..at + self.format.bytes_per_pixel()];
+        Some(match self.format {
+            PixelFormat::Rgba8 => Color::rgba(bytes[0], bytes[1], bytes[2], bytes[3]),
+            PixelFormat::Gray8 => Color::rgb(bytes[0], bytes[0], bytes[0]),
+            PixelFormat::Rgb565 => {
+                let word = u16::from_le_bytes([bytes[0], bytes[1]]);
+                let r = (word >> 11) as u8;
+                let g = ((word >> 5) & 0x3F) as u8;
+                let b = (word & 0x1F) as u8;
+                Color::rgb(r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2)
+            }
+        })
+    }
+
+    /// Sets a pixel to a colour, blended over it by the colour's alpha. Pixels
+    /// outside the buffer are ignored.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        let Some(below) = self.pixel(x, y) else {
+            return;
+        };
+        let color = match color.a {
+            255 => color,
+            0 => return,
+            _ => color.over(below),
+        };
+        let at = self.offset(x, y);
+        match self.format {
+            PixelFormat::Rgba8 => {
+                self.data[at..at + 4].copy_from_slice(&[color.r, color.g, color.b, color.a])
+            }
+            PixelFormat::Gray8 => self.data[at] = color.luma(),
+            PixelFormat::Rgb565 => {
+                let word =
+                    (color.r as u16 >> 3) << 11 | (color.g as u16 >> 2) << 5 | color.b as u16 >> 3;
+                self.data[at..at + 2].copy_from_slice(&word.to_le_bytes());
+            }
+        }
+    }
+
+    /// Fills a rectangle, clipped to the buffer.
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: usize, height: usize, color: Color) {
+        let clip = |start: i32, length: usize, limit: usize| {
+            let end = (start as i64 + length as i64).clamp(0, limit as i64) as usize;
+            (start.max(0) as usize).min(end)..end
+        };
+        for row in clip(y, height, self.height) {
+            for column in clip(x, width, self.width) {
+                self.set_pixel(column, row, color);
+            }
+        }
+    }
+
+    /// Fills the whole buffer.
+    pub fn fill(&mut self, color: Color) {
+        self.fill_rect(0, 0, self.width, self.height, color);
+    }
+
+    /// Draws a bitmap with its top left corner at `(x, y)`, clipped to the buffer.
+    pub fn draw_bitmap(&mut self, bitmap: &Bitmap, x: i32, y: i32, options: &DrawOptions) {
+        let (width, height) = options.pixel_size();
+        for (row_index, row) in bitmap.pixels.iter().enumerate() {
+            for (column, &set) in row.iter().enumerate() {
+                let color = if set {
+                    options.foreground
+                } else if let Some(background) = options.background {
+                    background
+                } else {
+                    continue;
+                };
+                self.fill_rect(
+                    x + (column * width) as i32,
+                    y + (row_index * height) as i32,
+                    width,
+                    height,
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Draws a line of text with its top left corner at `(x, y)`, and returns
+    /// the size it takes up. See [`Renderer::render`].
+    pub fn draw_text(
+        &mut self,
+        renderer: &Renderer,
+        text: &str,
+        x: i32,
+        y: i32,
+        options: &DrawOptions,
+    ) -> (usize, usize) {
+        let bitmap = renderer.render(text);
+        self.draw_bitmap(&bitmap, x, y, options);
+        options.scaled_size(&bitmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::bitmap;
+
+    #[test]
+    fn test_buffer_size_is_checked() {
+        let mut data = vec![0; 7];
+        assert_eq!(
+            ImageBuffer::new(&mut data, 2, 1, PixelFormat::Rgba8).unwrap_err(),
+            ImageError::BufferTooSmall {
+                needed: 8,
+                actual: 7
+            }
+        );
+        assert_eq!(
+            ImageBuffer::with_stride(&mut data, 2, 2, 3, PixelFormat::Rgb565).unwrap_err(),
+            ImageError::StrideTooSmall { stride: 3, row: 4 }
+        );
+        // The last row needn't be padded to the stride
+        assert!(ImageBuffer::with_stride(&mut data, 2, 2, 5, PixelFormat::Gray8).is_ok());
+    }
+
+    #[test]
+    fn test_draw_scaled_with_aspect_and_clipping() {
+        let mut data = vec![0; 5 * 4];
+        let mut buffer = ImageBuffer::new(&mut data, 5, 4, PixelFormat::Gray8).unwrap();
+        let options = DrawOptions {
+            foreground: Color::WHITE,
+            background: Some(Color::rgb(100, 100, 100)),
+            scale: 1,
+            pixel_aspect: (4, 2),
+        };
+        assert_eq!(options.pixel_size(), (2, 1));
+        buffer.draw_bitmap(&bitmap(&["@.", ".@", "@@"]), -1, 2, &options);
+        assert_eq!(
+            data,
+            vec![
+                0, 0, 0, 0, 0, //
+                0, 0, 0, 0, 0, //
+                255, 100, 100, 0, 0, //
+                100, 255, 255, 0, 0, //
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pixel_formats_and_blending() {
+        let mut data = vec![0; 4];
+        let mut buffer = ImageBuffer::new(&mut data, 2, 1, PixelFormat::Rgb565).unwrap();
+        buffer.set_pixel(0, 0, Color::rgb(255, 0, 255));
+        buffer.set_pixel(1, 0, Color::WHITE);
+        buffer.set_pixel(1, 0, Color::rgba(0, 0, 0, 0));
+        assert_eq!(buffer.pixel(0, 0), Some(Color::rgb(255, 0, 255)));
+        assert_eq!(data, vec![0x1F, 0xF8, 0xFF, 0xFF]);
+
+        let mut data = vec![0; 4];
+        let mut buffer = ImageBuffer::new(&mut data, 1, 1, PixelFormat::Rgba8).unwrap();
+        buffer.fill(Color::WHITE);
+        buffer.set_pixel(0, 0, Color::rgba(0, 0, 0, 128));
+        assert_eq!(buffer.pixel(0, 0), Some(Color::rgba(127, 127, 127, 255)));
+
+        // Over a transparent buffer the colour is kept and only the alpha mixes
+        let mut data = vec![0; 8];
+        let mut buffer = ImageBuffer::new(&mut data, 2, 1, PixelFormat::Rgba8).unwrap();
+        buffer.set_pixel(0, 0, Color::rgba(255, 0, 0, 128));
+        buffer.set_pixel(1, 0, Color::rgba(255, 0, 0, 128));
+        buffer.set_pixel(1, 0, Color::rgba(0, 0, 255, 128));
+        assert_eq!(buffer.pixel(0, 0), Some(Color::rgba(255, 0, 0, 128)));
+        assert_eq!(buffer.pixel(1, 0), Some(Color::rgba(85, 0, 170, 192)));
+    }
+}
@@ -0,0 +1,220 @@
+//! Constant-time glyph lookup.
+//!
+//! A [`GlyphIndex`] maps every label of a font to its glyph, so finding a glyph
+//! doesn't scan `font.glyphs`. Where several glyphs share a label, the first one
+//! wins, as it does when the font is read. Characters are also found by their
+//! codepoint label in the font's `encoding`, for fonts labelled only by code.
+
+use std::collections::HashMap;
+
+use crate::codepage::Codepage;
+use crate::models::*;
+
+/// Lookup tables from labels to glyph indices, built once from a font.
+///
+/// The index borrows the font and holds no interior mutability, so it can be
+/// shared between threads for read-only use.
+#[derive(Debug, Clone)]
+pub struct GlyphIndex<'a> {
+    font: &'a YaffFont,
+    unicode: HashMap<&'a [u32], usize>,
+    codepoints: HashMap<&'a [u8], usize>,
+    tags: HashMap<&'a str, usize>,
+    /// Codepage named by the font's `encoding`, if known.
+    codepage: Option<Codepage>,
+    longest_sequence: usize,
+    /// Kerning between pairs of glyphs, from the right kerning of the left glyph
+    /// plus the left kerning of the right one.
+    kerning: HashMap<(usize, usize), f32>,
+}
+
+impl<'a> GlyphIndex<'a> {
+    pub fn new(font: &'a YaffFont) -> Self {
+        let mut index = GlyphIndex {
+            font,
+            unicode: HashMap::new(),
+            codepoints: HashMap::new(),
+            tags: HashMap::new(),
+            codepage: font.encoding.as_deref().and_then(Codepage::from_name),
+            longest_sequence: 0,
+            kerning: HashMap::new(),
+        };
+        for (i, glyph) in font.glyphs.iter().enumerate() {
+            for label in &glyph.labels {
+                match label {
+                    Label::Unicode(values) if !values.is_empty() => {
+                        index.unicode.entry(values.as_slice()).or_insert(i);
+                        index.longest_sequence = index.longest_sequence.max(values.len());
+                    }
+                    Label::Codepoint(bytes) if !bytes.is_empty() => {
+                        index.codepoints.entry(bytes.as_slice()).or_insert(i);
+                    }
+                    Label::Tag(tag) => {
+                        index.tags.entry(tag.as_str()).or_insert(i);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Kerning keys that don't name a glyph of the font are dropped
+        let mut kerning = HashMap::new();
+        for (i, glyph) in font.glyphs.iter().enumerate() {
+            for (label, &amount) in glyph.right_kerning.iter().flatten() {
+                if let Some(right) = index.get(label) {
+                    *kerning.entry((i, right)).or_insert(0.0) += amount;
+                }
+            }
+            for (label, &amount) in glyph.left_kerning.iter().flatten() {
+                if let Some(left) = index.get(label) {
+                    *kerning.entry((left, i)).or_insert(0.0) += amount;
+                }
+            }
+        }
+        index.kerning = kerning;
+        index
+    }
+
+    pub fn font(&self) -> &'a YaffFont {
+        self.font
+    }
+
+    /// Returns the glyph with a label.
+    pub fn get(&self, label: &Label) -> Option<usize> {
+        match label {
+            Label::Unicode(values) => self.unicode(values),
+            Label::Codepoint(bytes) => self.codepoint(bytes),
+            Label::Tag(tag) => self.tag(tag),
+            Label::Anonymous => None,
+        }
+    }
+
+    /// Returns the glyph labelled with a single character, or else with the code
+    /// of the character in the font's encoding.
+    pub fn char(&self, c: char) -> Option<usize> {
+        self.unicode(&[c as u32]).or_else(|| {
+            let bytes = self.codepage.as_ref()?.encode(c)?;
+            self.codepoint(&bytes)
+        })
+    }
+
+    /// The codepage of the font's `encoding`, if it names a known one.
+    pub fn codepage(&self) -> Option<&Codepage> {
+        self.codepage.as_ref()
+    }
+
+    /// Returns the glyph labelled with a Unicode sequence, such as a ligature.
+    pub fn unicode(&self, values: &[u32]) -> Option<usize> {
+        self.unicode.get(values).copied()
+    }
+
+    /// Returns the glyph labelled with an encoded byte sequence.
+    pub fn codepoint(&self, bytes: &[u8]) -> Option<usize> {
+        self.codepoints.get(bytes).copied()
+    }
+
+    /// Returns the glyph with a tag.
+    pub fn tag(&self, tag: &str) -> Option<usize> {
+        self.tags.get(tag).copied()
+    }
+
+    /// Length of the longest Unicode label, in characters.
+    pub fn longest_sequence(&self) -> usize {
+        self.longest_sequence
+    }
+
+    /// Every kerned pair of glyphs as `(left, right)` with its kerning, in no
+    /// particular order.
+    pub fn kerning_pairs(&self) -> impl Iterator<Item = ((usize, usize), f32)> + '_ {
+        self.kerning.iter().map(|(&pair, &amount)| (pair, amount))
+    }
+
+    /// Kerning between two neighbouring glyphs, 0 if the font doesn't kern them.
+    pub fn kerning(&self, left: usize, right: usize) -> f32 {
+        self.kerning.get(&(left, right)).copied().unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::labelled;
+
+    #[test]
+    fn test_lookup_by_label() {
+        let font = YaffFont {
+            glyphs: vec![
+                labelled(vec![
+                    Label::Unicode(vec![0x41]),
+                    Label::Codepoint(vec![0x41]),
+                ]),
+                labelled(vec![
+                    Label::Unicode(vec![0x66, 0x69]),
+                    Label::Tag("fi".into()),
+                ]),
+                labelled(vec![
+                    Label::Unicode(vec![0x41]),
+                    Label::Codepoint(vec![0x81, 0x40]),
+                ]),
+                labelled(vec![Label::Anonymous]),
+            ],
+            ..Default::default()
+        };
+        let index = GlyphIndex::new(&font);
+        assert_eq!(index.char('A'), Some(0));
+        assert_eq!(index.char('B'), None);
+        assert_eq!(index.unicode(&[0x66, 0x69]), Some(1));
+        assert_eq!(index.tag("fi"), Some(1));
+        assert_eq!(index.codepoint(&[0x81, 0x40]), Some(2));
+        assert_eq!(index.get(&Label::Codepoint(vec![0x41])), Some(0));
+        assert_eq!(index.get(&Label::Anonymous), None);
+        assert_eq!(index.longest_sequence(), 2);
+    }
+
+    #[test]
+    fn test_characters_found_by_encoded_codepoint() {
+        let font = YaffFont {
+            encoding: Some("cp437".into()),
+            glyphs: vec![labelled(vec![Label::Codepoint(vec![0x82])])],
+            ..Default::default()
+        };
+        let index = GlyphIndex::new(&font);
+        assert_eq!(index.char('é'), Some(0));
+        assert_eq!(index.char('\u{82}'), None);
+        assert_eq!(index.codepage().map(Codepage::name), Some("cp437"));
+    }
+
+    #[test]
+    fn test_kerning_is_resolved_to_glyphs() {
+        let mut a = labelled(vec![Label::Unicode(vec![0x41])]);
+        a.right_kerning = Some(HashMap::from([
+            (Label::Tag("vee".into()), -1.0),
+            (Label::Tag("missing".into()), 2.0),
+        ]));
+        let mut v = labelled(vec![Label::Unicode(vec![0x56]), Label::Tag("vee".into())]);
+        v.left_kerning = Some(HashMap::from([(Label::Unicode(vec![0x41]), -0.5)]));
+        let font = YaffFont {
+            glyphs: vec![a, v],
+            ..Default::default()
+        };
+        let index = GlyphIndex::new(&font);
+        assert_eq!(index.kerning(0, 1), -1.5);
+        assert_eq!(index.kerning(1, 0), 0.0);
+    }
+
+    #[test]
+    fn test_index_is_shareable_between_threads() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let font = YaffFont {
+            glyphs: vec![labelled(vec![Label::Unicode(vec![0x41])])],
+            ..Default::default()
+        };
+        let index = GlyphIndex::new(&font);
+        assert_send_sync(&index);
+        std::thread::scope(|scope| {
+            scope.spawn(|| assert_eq!(index.char('A'), Some(0)));
+            scope.spawn(|| assert_eq!(index.char('A'), Some(0)));
+        });
+    }
+}
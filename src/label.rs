@@ -0,0 +1,184 @@
+//! Reading labels from their YAFF syntax.
+//!
+//! Label syntax is simple enough to read without regular expressions, so this
+//! works without the `parsing` feature. That lets the `default-char` and
+//! `word-boundary` properties, which hold labels as text, be resolved in any build.
+
+use crate::models::{Label, LineType};
+
+/// Text between a pair of `quote` characters that enclose all of `s`.
+fn quoted(s: &str, quote: char) -> Option<&str> {
+    s.strip_prefix(quote)?
+        .strip_suffix(quote)
+        .filter(|inner| !inner.contains('\n'))
+}
+
+/// The hex digits of a `u+XXXX` element.
+fn uplus_digits(element: &str) -> Option<&str> {
+    element
+        .strip_prefix(['u', 'U'])?
+        .strip_prefix('+')
+        .filter(|digits| (1..=6).contains(&digits.len()))
+        .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn is_unicode_element(element: &str) -> bool {
+    uplus_digits(element).is_some() || quoted(element, '\'').is_some()
+}
+
+/// The value of a `0x..`, `0o..` or decimal element.
+fn codepoint_value(element: &str) -> Option<u32> {
+    let (digits, radix) = match element.get(..2) {
+        Some("0x" | "0X") => (&element[2..], 16),
+        Some("0o" | "0O") => (&element[2..], 8),
+        _ => (element, 10),
+    };
+    let valid = !digits.is_empty() && digits.chars().all(|c| c.is_digit(radix));
+    valid
+        .then(|| u32::from_str_radix(digits, radix).ok())
+        .flatten()
+}
+
+/// Deprecated unquoted tags start with a letter or underscore.
+fn is_unquoted_tag(element: &str) -> bool {
+    let mut chars = element.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || "_./ ()-".contains(c))
+}
+
+// Helper to parse a single Unicode element string (e.g., "u+0041" or "'A'")
+fn parse_unicode_element(element_str: &str) -> Option<Vec<u32>> {
+    if let Some(digits) = uplus_digits(element_str) {
+        u32::from_str_radix(digits, 16).ok().map(|val| vec![val])
+    } else {
+        quoted(element_str, '\'').map(|inner| inner.chars().map(|c| c as u32).collect())
+    }
+}
+
+// Helper to parse a sequence of Unicode elements (comma-separated)
+fn parse_unicode_label_sequence(elements: &[&str]) -> Option<Label> {
+    let mut unicode_values = Vec::new();
+    for el_str in elements {
+        if let Some(parsed_values) = parse_unicode_element(el_str) {
+            unicode_values.extend(parsed_values);
+        } else {
+            return None; // Mixed types not allowed
+        }
+    }
+    Some(Label::Unicode(unicode_values))
+}
+
+// Helper to parse a single Codepoint element string (e.g., "65", "0x41", "0o101")
+// into its big-endian byte sequence, so "0x8140" becomes [0x81, 0x40].
+fn parse_codepoint_element(element_str: &str) -> Option<Vec<u8>> {
+    match codepoint_value(element_str) {
+        Some(v) if v <= 0xFF => Some(vec![v as u8]),
+        Some(v) if v <= 0xFFFF => Some(vec![(v >> 8) as u8, v as u8]),
+        _ => None, // Parse failed or value out of double-byte range
+    }
+}
+
+// Helper to parse a sequence of Codepoint elements (comma-separated) into one byte sequence
+fn parse_codepoint_label_sequence(elements: &[&str]) -> Option<Label> {
+    let mut codepoint_bytes = Vec::new();
+    for el_str in elements {
+        if let Some(bytes) = parse_codepoint_element(el_str) {
+            codepoint_bytes.extend(bytes);
+        } else {
+            return None; // Mixed types not allowed
+        }
+    }
+    Some(Label::Codepoint(codepoint_bytes))
+}
+
+// Helper to handle deprecated unquoted tag format
+fn handle_deprecated_unquoted_tag(
+    single_element: &str,
+    next_line_type: &LineType,
+) -> Option<Label> {
+    let should_treat_as_tag = matches!(
+        next_line_type,
+        LineType::BitmapLine { .. }
+            | LineType::KeyValue {
+                value_on_line: None,
+                ..
+            }
+    );
+
+    if should_treat_as_tag {
+        Some(Label::Tag(single_element.to_string()))
+    } else {
+        None
+    }
+}
+
+/// Parses a key string (content before the final ':' of a label line)
+/// into an `Option<Label>`.
+/// Returns `None` if the string does not conform to any known label syntax.
+pub fn parse_key_as_label(key_str: &str, next_line_type: &LineType) -> Option<Label> {
+    let trimmed_key = key_str.trim();
+
+    // Special case for a quoted comma so it doesn't get recognized as a sequence
+    if trimmed_key == "','" {
+        return Some(Label::Unicode(vec![0x2c]));
+    }
+
+    // Handle Anonymous Label (original line was just ":")
+    if trimmed_key.is_empty() {
+        return Some(Label::Anonymous);
+    }
+
+    // Handle double-quoted strings
+    if let Some(inner_content) = quoted(trimmed_key, '"') {
+        return Some(Label::Tag(inner_content.to_string()));
+    }
+
+    // Handle potential multi-element labels (Unicode or Codepoint)
+    let elements: Vec<&str> = trimmed_key.split(',').map(str::trim).collect();
+    if elements.is_empty() || elements.iter().any(|e| e.is_empty()) {
+        // This can happen if key_str was just "," or "elem1, ,elem2"
+        return None;
+    }
+
+    // Determine label type from first element and parse accordingly
+    let first_element = elements[0];
+
+    // Try Unicode (Character) Label first
+    if is_unicode_element(first_element) {
+        return parse_unicode_label_sequence(&elements);
+    }
+
+    // Try Codepoint Label
+    if codepoint_value(first_element).is_some() {
+        return parse_codepoint_label_sequence(&elements);
+    }
+
+    // Handle deprecated unquoted tags (single element only)
+    if elements.len() == 1 && is_unquoted_tag(first_element) {
+        return handle_deprecated_unquoted_tag(first_element, next_line_type);
+    }
+
+    None // Does not match any known label format (including previously matched unquoted tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_element_syntax_is_exact() {
+        let label = |key| parse_key_as_label(key, &LineType::Empty);
+        assert_eq!(label("0x+41"), None);
+        assert_eq!(label("+65"), None);
+        assert_eq!(label("u++41"), None);
+        assert_eq!(label("u+1234567"), None);
+        assert_eq!(label("0x"), None);
+        assert_eq!(label("'"), None);
+        assert_eq!(label("\""), None);
+        assert_eq!(label("0X4a"), Some(Label::Codepoint(vec![0x4a])));
+        assert_eq!(label("0O17"), Some(Label::Codepoint(vec![0o17])));
+        assert_eq!(label("U+10FFFF"), Some(Label::Unicode(vec![0x10ffff])));
+    }
+}
@@ -0,0 +1,385 @@
+//! Multi-line text layout.
+//!
+//! Text is split into paragraphs at newlines and into words at the font's
+//! `word-boundary` character, which is the space if the font doesn't name one.
+//! The gap between words is the `word-space` (or the advance of the boundary glyph),
+//! or `sentence-space` after a word ending in `.`, `!` or `?`. Lines are filled
+//! greedily, letting gaps shrink to `min-word-space` to fit one more word, and
+//! justified lines stretch their gaps up to `max-word-space`. Lines are
+//! `line-height` apart, or the pixel size plus `leading`. In right-to-left and
+//! mixed-direction text, the words of each line are reordered for display.
+
+use crate::bidi;
+use crate::label::parse_key_as_label;
+use crate::models::*;
+use crate::render::{GlyphRun, PlacedGlyph, Renderer};
+
+/// Horizontal alignment of the lines of a [`Layout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Stretch the gaps of every line but the last of each paragraph to the width.
+    /// Last lines are set flush with the side the paragraph starts on.
+    Justify,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LayoutOptions {
+    /// Width to break lines at and to align within. Without a width, only
+    /// newlines break lines and alignment is relative to the longest line.
+    pub width: Option<i32>,
+    pub align: Align,
+}
+
+/// A positioned line of a [`Layout`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutLine {
+    /// Glyphs with positions relative to the start of the line.
+    pub run: GlyphRun,
+    /// Offset of the start of the line from the left edge of the layout.
+    pub x: i32,
+    /// Row of the baseline, counted down from the top of the layout.
+    pub baseline: i32,
+}
+
+/// Text broken into lines and positioned.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Layout {
+    pub lines: Vec<LayoutLine>,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Word spacing of a font in pixels.
+struct Spacing {
+    boundary: char,
+    word: i32,
+    min_word: i32,
+    max_word: Option<i32>,
+    sentence: i32,
+}
+
+impl Spacing {
+    fn new(renderer: &Renderer) -> Self {
+        let font = renderer.font();
+        let boundary = font
+            .word_boundary_label_raw
+            .as_deref()
+            .and_then(|raw| parse_key_as_label(raw, &LineType::Empty))
+            .and_then(|label| match label {
+                Label::Unicode(values) => match values.as_slice() {
+                    [value] => char::from_u32(*value),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .unwrap_or(' ');
+        let word = font.word_space.unwrap_or_else(|| {
+            renderer
+                .glyph_for(boundary)
+                .map_or(0, |glyph| renderer.advance(glyph))
+        });
+        Spacing {
+            boundary,
+            word,
+            min_word: font.min_word_space.unwrap_or(word).min(word),
+            max_word: font.max_word_space,
+            sentence: font.sentence_space.unwrap_or(word),
+        }
+    }
+
+    /// Natural gap after `word`.
+    fn after(&self, word: &str) -> i32 {
+        if word.ends_with(['.', '!', '?']) {
+            self.sentence
+        } else {
+            self.word
+        }
+    }
+}
+
+/// A word being set on a line.
+struct Word {
+    run: GlyphRun,
+    /// Natural gap before the word; unused for the first word of a line.
+    gap: i32,
+    /// Embedding level of the first character of the word.
+    level: u8,
+}
+
+/// Moves `gaps` towards `limits` one pixel at a time, round robin, until `amount`
+/// pixels have been moved or every gap is at its limit.
+fn distribute(gaps: &mut [i32], limits: &[i32], mut amount: i32) {
+    let step = if amount < 0 { -1 } else { 1 };
+    while amount != 0 {
+        let mut moved = false;
+        for (gap, &limit) in gaps.iter_mut().zip(limits) {
+            if amount != 0 && (limit - *gap) * step > 0 {
+                *gap += step;
+                amount -= step;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+}
+
+/// Sets the words of one line in visual order. Gaps shrink towards `min-word-space`
+/// if the line is wider than `width`, and grow towards `max-word-space` to fill it
+/// if justifying.
+fn set_line(words: Vec<Word>, spacing: &Spacing, width: Option<i32>, justify: bool) -> GlyphRun {
+    let levels: Vec<u8> = words.iter().map(|w| w.level).collect();
+    let order = bidi::visual_order(&levels);
+    // The gap between two words is the one before whichever comes later in the text
+    let mut gaps: Vec<i32> = order
+        .windows(2)
+        .map(|pair| words[pair[0].max(pair[1])].gap)
+        .collect();
+    if let Some(width) = width {
+        let natural_width: i32 =
+            words.iter().map(|w| w.run.advance).sum::<i32>() + gaps.iter().sum::<i32>();
+        if natural_width > width {
+            let limits: Vec<i32> = gaps.iter().map(|&g| g.min(spacing.min_word)).collect();
+            distribute(&mut gaps, &limits, width - natural_width);
+        } else if justify {
+            let limits: Vec<i32> = gaps
+                .iter()
+                .map(|&g| spacing.max_word.map_or(i32::MAX, |max| max.max(g)))
+                .collect();
+            distribute(&mut gaps, &limits, width - natural_width);
+        }
+    }
+
+    let mut run = GlyphRun::default();
+    let mut x = 0;
+    for (i, &index) in order.iter().enumerate() {
+        let word = &words[index];
+        if i > 0 {
+            x += gaps[i - 1];
+        }
+        run.glyphs
+            .extend(word.run.glyphs.iter().map(|placed| PlacedGlyph {
+                glyph: placed.glyph,
+                x: x + placed.x,
+            }));
+        x += word.run.advance;
+    }
+    run.advance = x;
+    run
+}
+
+/// Breaks text into lines and positions them.
+pub fn layout(renderer: &Renderer, text: &str, options: &LayoutOptions) -> Layout {
+    let font = renderer.font();
+    let spacing = Spacing::new(renderer);
+    let pixel_size = renderer.ascent() + renderer.descent();
+    let pitch = font
+        .line_height
+        .unwrap_or(pixel_size + font.leading.unwrap_or(0));
+
+    let justify = options.align == Align::Justify;
+    // Lines, each with whether its paragraph runs right to left
+    let mut lines: Vec<(GlyphRun, bool)> = Vec::new();
+    for paragraph in text.split('\n') {
+        let (levels, paragraph_level) = bidi::levels(font, paragraph);
+        let rtl = paragraph_level % 2 == 1;
+        let mut words: Vec<Word> = Vec::new();
+        // Width of the current line with minimum gaps
+        let mut min_width = 0;
+        let mut previous_word = "";
+        let mut start = 0;
+        for word in paragraph.split(spacing.boundary) {
+            let level = levels.get(start).copied().unwrap_or(paragraph_level);
+            start += word.chars().count() + 1;
+            if word.is_empty() {
+                continue;
+            }
+            let run = renderer.place(word);
+            let gap = spacing.after(previous_word);
+            previous_word = word;
+            if let Some(width) = options.width
+                && !words.is_empty()
+                && min_width + gap.min(spacing.min_word) + run.advance > width
+            {
+                let line = set_line(std::mem::take(&mut words), &spacing, Some(width), justify);
+                lines.push((line, rtl));
+                min_width = 0;
+            }
+            if !words.is_empty() {
+                min_width += gap.min(spacing.min_word);
+            }
+            min_width += run.advance;
+            words.push(Word { run, gap, level });
+        }
+        // The last line of a paragraph isn't justified
+        lines.push((set_line(words, &spacing, options.width, false), rtl));
+    }
+
+    let width = options
+        .width
+        .unwrap_or_else(|| lines.iter().map(|(run, _)| run.advance).max().unwrap_or(0));
+    let lines: Vec<LayoutLine> = lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, (run, rtl))| {
+            let slack = (width - run.advance).max(0);
+            let x = match options.align {
+                Align::Left => 0,
+                Align::Center => slack / 2,
+                Align::Right => slack,
+                // Lines that aren't stretched start at the paragraph's reading edge
+                Align::Justify if rtl => slack,
+                Align::Justify => 0,
+            };
+            LayoutLine {
+                run,
+                x,
+                baseline: renderer.ascent() + i as i32 * pitch,
+            }
+        })
+        .collect();
+    let height = match lines.len() {
+        0 => 0,
+        n => (n as i32 - 1) * pitch + pixel_size,
+    };
+    Layout {
+        lines,
+        width,
+        height,
+    }
+}
+
+impl Layout {
+    /// Draws the layout onto a bitmap of its size, clipping ink outside it.
+    pub fn render(&self, renderer: &Renderer) -> Bitmap {
+        let width = self.width.max(0) as usize;
+        let height = self.height.max(0) as usize;
+        let mut bitmap = Bitmap {
+            pixels: vec![vec![false; width]; height],
+            width,
+            height,
+        };
+        for line in &self.lines {
+            for placed in &line.run.glyphs {
+                renderer.draw_glyph(&mut bitmap, placed.glyph, line.x + placed.x, line.baseline);
+            }
+        }
+        bitmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A font where every letter is a 2x2 block with 1 pixel of right bearing.
+    fn font() -> YaffFont {
+        let mut yaff = String::from("ascent: 2\ndescent: 1\nword-space: 2\n");
+        for c in 'a'..='e' {
+            yaff.push_str(&format!("\n'{c}':\n  @@\n  @@\n  right-bearing: 1\n"));
+        }
+        yaff.push_str("\n'.':\n  .\n  @\n  right-bearing: 1\n");
+        yaff.parse().unwrap()
+    }
+
+    fn lines(layout: &Layout) -> Vec<(i32, i32, i32)> {
+        layout
+            .lines
+            .iter()
+            .map(|line| (line.x, line.baseline, line.run.advance))
+            .collect()
+    }
+
+    #[test]
+    fn test_wraps_at_word_boundaries() {
+        let font = font();
+        let renderer = Renderer::new(&font);
+        let options = LayoutOptions {
+            width: Some(14),
+            align: Align::Left,
+        };
+        // Words are 6 pixels wide ("ab" is 2 x 3) with 2 pixel gaps
+        let result = layout(&renderer, "ab cd ea\nb", &options);
+        assert_eq!(lines(&result), vec![(0, 2, 14), (0, 5, 6), (0, 8, 3)]);
+        assert_eq!((result.width, result.height), (14, 9));
+
+        let bitmap = result.render(&renderer);
+        assert!(bitmap.pixels[0][8]);
+        assert!(!bitmap.pixels[2][0]);
+        assert!(bitmap.pixels[3][0]);
+    }
+
+    #[test]
+    fn test_alignment_and_spacing() {
+        let mut font = font();
+        font.sentence_space = Some(4);
+        font.max_word_space = Some(3);
+        font.min_word_space = Some(1);
+        font.leading = Some(1);
+        let renderer = Renderer::new(&font);
+        let text = "ab cd ea b";
+        let mut options = LayoutOptions {
+            width: Some(20),
+            align: Align::Right,
+        };
+        let result = layout(&renderer, text, &options);
+        // Three words fit with minimum gaps of 1
+        assert_eq!(lines(&result), vec![(0, 2, 20), (17, 6, 3)]);
+
+        options.align = Align::Center;
+        assert_eq!(lines(&layout(&renderer, "a b", &options))[0], (6, 2, 8));
+
+        // Justified gaps stop growing at max-word-space
+        options.align = Align::Justify;
+        let result = layout(&renderer, "ab cd abcd", &options);
+        assert_eq!(lines(&result), vec![(0, 2, 15), (0, 6, 12)]);
+        font.max_word_space = None;
+        let renderer = Renderer::new(&font);
+        let result = layout(&renderer, "ab cd abcd", &options);
+        assert_eq!(lines(&result)[0], (0, 2, 20));
+
+        // Sentence space after a full stop
+        options.align = Align::Left;
+        options.width = None;
+        let result = layout(&renderer, "a. b", &options);
+        assert_eq!(result.lines[0].run.glyphs[2].x, 9);
+        assert_eq!(result.width, 12);
+    }
+
+    #[test]
+    fn test_right_to_left_lines() {
+        let yaff = "\
+direction: right-to-left
+word-space: 1
+
+u+05D0:
+  @
+  right-bearing: 1
+
+u+05D1:
+  @@
+  right-bearing: 1
+";
+        let font: YaffFont = yaff.parse().unwrap();
+        let renderer = Renderer::new(&font);
+        let options = LayoutOptions {
+            width: Some(9),
+            align: Align::Justify,
+        };
+        // The second word is set first, at the left; the short last line ends up right
+        let result = layout(&renderer, "אא ב א", &options);
+        let first_line: Vec<(usize, i32)> = result.lines[0]
+            .run
+            .glyphs
+            .iter()
+            .map(|p| (p.glyph, p.x))
+            .collect();
+        assert_eq!(first_line, vec![(1, 0), (0, 5), (0, 7)]);
+        assert_eq!(lines(&result)[1], (7, 2, 2));
+    }
+}
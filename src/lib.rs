@@ -7,7 +7,22 @@
 //! - **Complete YAFF format support**: Parse and generate YAFF 1.0.x format files
 //! - **Unicode and legacy encoding**: Support for Unicode, codepoint, and tag-based glyph labeling
 //! - **Advanced typography**: Kerning, bearing adjustments, and font metrics
+//! - **Fast lookup**: [`GlyphIndex`] finds glyphs by character, label or tag and resolves kerning
+//!   pairs in constant time
 //! - **Robust parsing**: Handles format variations and provides detailed error messages
+//! - **Derived metrics**: [`calculate_metrics`] computes bounds, sizes, widths and spacing from the glyphs,
+//!   and [`glyph_metrics`] resolves each glyph's position from its bearings and the font defaults
+//! - **Text rendering**: [`render`] draws a line of text to a [`Bitmap`] and [`layout`] wraps and
+//!   aligns paragraphs, and [`FontStack`] falls back through several fonts (feature `rendering`)
+//! - **Image buffers**: [`ImageBuffer`] draws glyphs and text into RGBA8, Gray8 or RGB565 pixel
+//!   buffers with colours, scaling and pixel aspect correction (feature `rendering`)
+//! - **Terminal preview**: [`preview`] shows bitmaps as half-blocks, braille or sixel graphics
+//!   (feature `rendering`)
+//! - **Specimens**: [`specimen`] produces PNG, SVG or HTML sheets with metadata, a character chart,
+//!   samples and kerning pairs (feature `rendering`)
+//! - **Merging**: [`merge`] combines the glyphs of two fonts with a choice of conflict policy
+//! - **Subsetting**: [`subset`] keeps only the glyphs in a [`CharSet`] of ranges, tags or codepages
+//! - **Validation**: [`validate`] lints a font for semantic inconsistencies
 //! - **Memory efficient**: Optimized for embedded and resource-constrained environments
 //!
 //! ## Quick Start
@@ -28,7 +43,7 @@
 //! }
 //!
 //! // Convert back to YAFF format
-//! let yaff_content = to_yaff_string(&font);
+//! let yaff_content = to_yaff_string(&font)?;
 //! std::fs::write("output.yaff", yaff_content)?;
 //! # }
 //! # Ok(())
@@ -45,24 +60,88 @@
 //! ## Error Handling
 //!
 //! All parsing operations return `Result<T, ParseError>` with detailed error information
-//! including line numbers and context for debugging malformed YAFF files.
+//! including line numbers and context for debugging malformed YAFF files. Problems that
+//! don't stop the parse, such as deprecated syntax or unknown properties, are returned as
+//! [`Diagnostic`]s by `YaffFont::from_str_with_options`; [`ParseMode::Strict`] turns
+//! deprecations and unknown properties into errors. When a document has several
+//! malformed glyphs or properties, parsing continues past each one and all of them are
+//! returned together as `ParseError::Multiple`. With [`Recovery::Discard`] or
+//! [`Recovery::Repair`] the parse succeeds anyway, skipping or fixing what is broken and
+//! recording each problem as a [`Diagnostic`]. Each [`Diagnostic`] carries a column, byte
+//! span and the source line, and [`Diagnostic::render`] shows it with a caret. Encoding
+//! returns `Result<T, EncodeError>` for font data that cannot be written as YAFF text
+//! that reads back unchanged.
 
+#[cfg(feature = "rendering")]
+mod bidi;
+mod codepage;
+mod diagnostic;
 #[cfg(feature = "encoding")]
 mod encoder;
+#[cfg(feature = "rendering")]
+mod image;
+mod index;
+mod label;
+#[cfg(feature = "rendering")]
+mod layout;
+mod merge;
+mod metrics;
 mod models;
 #[cfg(feature = "parsing")]
 mod parser;
+#[cfg(feature = "rendering")]
+mod preview;
+#[cfg(feature = "rendering")]
+mod render;
+#[cfg(feature = "rendering")]
+mod segment;
+#[cfg(feature = "rendering")]
+mod specimen;
+#[cfg(feature = "rendering")]
+mod stack;
+mod subset;
+#[cfg(test)]
+mod test_util;
 mod utils;
+mod validate;
 
+pub use crate::codepage::{Codepage, register_codepage};
+pub use crate::diagnostic::{Diagnostic, DiagnosticKind, Severity};
 #[cfg(feature = "encoding")]
-pub use crate::encoder::to_yaff_string;
+pub use crate::encoder::{
+    EncodeOptions, LabelStyle, PropertyOrder, YaffWriter, to_yaff_string,
+    to_yaff_string_with_options, write_yaff, write_yaff_with_options,
+};
+#[cfg(feature = "rendering")]
+pub use crate::image::{Color, DrawOptions, ImageBuffer, PixelFormat};
+pub use crate::index::GlyphIndex;
+pub use crate::label::parse_key_as_label;
+#[cfg(feature = "rendering")]
+pub use crate::layout::{Align, Layout, LayoutLine, LayoutOptions, layout};
+pub use crate::merge::{ConflictPolicy, MergeOptions, MetadataPrecedence, VerticalAlign, merge};
+pub use crate::metrics::{
+    FontMetrics, GlyphMetrics, calculate_metrics, fill_missing_metrics, glyph_metrics,
+    glyph_metrics_all, set_metrics, update_metrics,
+};
 pub use crate::models::*;
 #[cfg(feature = "parsing")]
-pub use crate::parser::{classify_line, parse_key_as_label};
+pub use crate::parser::{ParseMode, ParseOptions, Recovery, YaffReader, classify_line};
+#[cfg(feature = "rendering")]
+pub use crate::preview::{PreviewOptions, PreviewStyle, preview, preview_text};
+#[cfg(feature = "rendering")]
+pub use crate::render::{GlyphRun, PlacedGlyph, Renderer, render};
+#[cfg(feature = "rendering")]
+pub use crate::specimen::{PANGRAMS, SpecimenFormat, SpecimenOptions, specimen};
+#[cfg(feature = "rendering")]
+pub use crate::stack::{FontStack, StackRun, StackedGlyph};
+pub use crate::subset::{CharSet, subset};
 pub use crate::utils::{
-    calculate_ascent, convert_codepoint_to_unicode_labels, minimize_all_bounding_boxes,
+    calculate_ascent, convert_bearings_to_shift_up, convert_codepoint_to_unicode_labels,
+    convert_shift_up_to_bearings, convert_unicode_to_codepoint_labels, expand_global_metrics,
+    expand_to_character_cell, factor_global_metrics, minimize_all_bounding_boxes,
     minimize_glyph_bounding_box, set_ascent,
 };
+pub use crate::validate::{Issue, IssueKind, validate};
 #[cfg(feature = "parsing")]
 use std::fs::File;
 #[cfg(feature = "parsing")]
@@ -90,17 +169,29 @@ impl FromStr for YaffFont {
 
 #[cfg(feature = "parsing")]
 impl YaffFont {
-
-    pub fn from_reader<R: std::io::Read>(mut reader: R) -> Result<YaffFont, ParseError> {
-        let mut buffer = String::new();
-        reader.read_to_string(&mut buffer)?;
-        Self::from_str(&buffer)
+    pub fn from_reader<R: std::io::Read>(reader: R) -> Result<YaffFont, ParseError> {
+        parser::from_reader(BufReader::new(reader))
     }
 
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ParseError> {
         let file = File::open(path).map_err(ParseError::Io)?;
-        let reader = BufReader::new(file);
-        Self::from_reader(reader)
+        Self::from_reader(file)
+    }
+
+    /// Parses a font with the given options, returning it together with the
+    /// problems that were recorded instead of failing the parse.
+    pub fn from_str_with_options(
+        s: &str,
+        options: &ParseOptions,
+    ) -> Result<(YaffFont, Vec<Diagnostic>), ParseError> {
+        parser::from_str_with_options(s, options)
+    }
+
+    pub fn from_reader_with_options<R: std::io::Read>(
+        reader: R,
+        options: &ParseOptions,
+    ) -> Result<(YaffFont, Vec<Diagnostic>), ParseError> {
+        parser::from_reader_with_options(BufReader::new(reader), options)
     }
 }
 
@@ -0,0 +1,471 @@
+//! Combining the glyphs of two fonts.
+//!
+//! [`merge`] adds the glyphs of an incoming font to a base font, matching glyphs
+//! by label. Incoming glyphs keep their place relative to the baseline, or to the
+//! ascent line if chosen, whatever global bearings, shift and ascent either font
+//! has. Descriptive properties such as the name and copyright are combined by a
+//! precedence rule. The result keeps the global bearings, shift, ascent, descent
+//! and encoding of the base font, while the derived metrics the base font stores,
+//! such as `spacing` and `cell-size`, are recalculated from the merged glyphs.
+
+use crate::codepage::Codepage;
+use crate::metrics::{GlyphMetrics, bearing_anchors, glyph_metrics_all, update_metrics};
+use crate::models::*;
+use crate::utils::pin_bearing_anchors;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// What happens to an incoming glyph that has a label a base glyph also has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// The base glyph stays and the incoming glyph is left out.
+    #[default]
+    KeepFirst,
+    /// The incoming glyph takes the place of the base glyph, and gets the labels
+    /// of the base glyph it replaces.
+    Replace,
+    /// Merging fails with [`MergeError::Conflict`].
+    Error,
+}
+
+/// Which line of the incoming font is matched to the base font.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VerticalAlign {
+    /// Glyphs keep their height above the baseline.
+    #[default]
+    Baseline,
+    /// Glyphs keep their depth below the ascent line.
+    Ascent,
+}
+
+/// Which font's descriptive properties win when both fonts have them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetadataPrecedence {
+    /// The base font's, filled in from the incoming font where the base has none.
+    #[default]
+    Base,
+    /// The incoming font's, where it has them.
+    Incoming,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MergeOptions {
+    pub conflict: ConflictPolicy,
+    pub align: VerticalAlign,
+    pub metadata: MetadataPrecedence,
+}
+
+/// Adds kerning entries of `other` that `glyph` doesn't have.
+fn merge_kerning(glyph: &mut GlyphDefinition, other: &GlyphDefinition) {
+    for (kerning, other) in [
+        (&mut glyph.right_kerning, &other.right_kerning),
+        (&mut glyph.left_kerning, &other.left_kerning),
+    ] {
+        for (label, &amount) in other.iter().flatten() {
+            kerning
+                .get_or_insert_with(HashMap::new)
+                .entry(label.clone())
+                .or_insert(amount);
+        }
+    }
+}
+
+/// Takes descriptive properties from `incoming` by the precedence rule.
+fn merge_metadata(base: &mut YaffFont, incoming: &YaffFont, precedence: MetadataPrecedence) {
+    let prefer_incoming = precedence == MetadataPrecedence::Incoming;
+    let pick = |field: &mut Option<String>, other: &Option<String>| {
+        if other.is_some() && (prefer_incoming || field.is_none()) {
+            field.clone_from(other);
+        }
+    };
+    pick(&mut base.name, &incoming.name);
+    pick(&mut base.family, &incoming.family);
+    pick(&mut base.subfamily, &incoming.subfamily);
+    pick(&mut base.revision, &incoming.revision);
+    pick(&mut base.style, &incoming.style);
+    pick(&mut base.weight, &incoming.weight);
+    pick(&mut base.slant, &incoming.slant);
+    pick(&mut base.setwidth, &incoming.setwidth);
+    pick(&mut base.decoration, &incoming.decoration);
+    pick(&mut base.author, &incoming.author);
+    pick(&mut base.foundry, &incoming.foundry);
+    pick(&mut base.copyright, &incoming.copyright);
+    pick(&mut base.notice, &incoming.notice);
+    pick(&mut base.device, &incoming.device);
+    pick(&mut base.converter, &incoming.converter);
+    pick(&mut base.source_name, &incoming.source_name);
+    pick(&mut base.source_format, &incoming.source_format);
+    pick(&mut base.history, &incoming.history);
+    pick(
+        &mut base.default_char_label_raw,
+        &incoming.default_char_label_raw,
+    );
+    pick(
+        &mut base.word_boundary_label_raw,
+        &incoming.word_boundary_label_raw,
+    );
+}
+
+/// Relabels the codepoints of `incoming` in the encoding of `base`.
+///
+/// A codepoint whose character the base encoding has no code for becomes a
+/// label for the character. Nothing changes unless both fonts name an encoding.
+fn recode_codepoints<'a>(
+    base: &YaffFont,
+    incoming: &'a YaffFont,
+) -> Result<Cow<'a, YaffFont>, MergeError> {
+    let (Some(to), Some(from)) = (&base.encoding, &incoming.encoding) else {
+        return Ok(Cow::Borrowed(incoming));
+    };
+    let has_codepoints = incoming
+        .glyphs
+        .iter()
+        .flat_map(|glyph| &glyph.labels)
+        .any(|label| matches!(label, Label::Codepoint(_)));
+    if !has_codepoints {
+        return Ok(Cow::Borrowed(incoming));
+    }
+    let Some((to_codepage, from_codepage)) = Codepage::from_name(to).zip(Codepage::from_name(from))
+    else {
+        return Err(MergeError::Encoding {
+            base: to.clone(),
+            incoming: from.clone(),
+        });
+    };
+    if to_codepage.name() == from_codepage.name() {
+        return Ok(Cow::Borrowed(incoming));
+    }
+
+    let recode = |label: &Label| match label {
+        Label::Codepoint(bytes) => match from_codepage.decode(bytes) {
+            Some(c) => to_codepage
+                .encode(c)
+                .map_or_else(|| Label::Unicode(vec![c as u32]), Label::Codepoint),
+            None => label.clone(),
+        },
+        _ => label.clone(),
+    };
+    let mut recoded = incoming.clone();
+    for glyph in &mut recoded.glyphs {
+        let mut labels: Vec<Label> = Vec::with_capacity(glyph.labels.len());
+        for label in glyph.labels.iter().map(recode) {
+            if !labels.contains(&label) {
+                labels.push(label);
+            }
+        }
+        glyph.labels = labels;
+        for map in [&mut glyph.right_kerning, &mut glyph.left_kerning]
+            .into_iter()
+            .flatten()
+        {
+            *map = map
+                .iter()
+                .map(|(label, &amount)| (recode(label), amount))
+                .collect();
+        }
+    }
+    Ok(Cow::Owned(recoded))
+}
+
+/// Merge the glyphs of `incoming` into `base`
+///
+/// Glyphs of `incoming` whose labels no base glyph has are appended. Glyphs that
+/// share a label with a base glyph are resolved by `options.conflict`, and the
+/// kerning of the glyph that stays is completed with that of the one that goes.
+/// Codepoint labels of `incoming` are converted to the encoding of `base` when
+/// the two differ, failing with [`MergeError::Encoding`] if either is unknown.
+/// With [`ConflictPolicy::Error`], `base` is unchanged if merging fails.
+pub fn merge(
+    base: &mut YaffFont,
+    incoming: &YaffFont,
+    options: &MergeOptions,
+) -> Result<(), MergeError> {
+    let incoming = recode_codepoints(base, incoming)?;
+    let incoming = incoming.as_ref();
+    let mut owners: HashMap<Label, usize> = HashMap::new();
+    for (index, glyph) in base.glyphs.iter().enumerate() {
+        for label in &glyph.labels {
+            if *label != Label::Anonymous {
+                owners.entry(label.clone()).or_insert(index);
+            }
+        }
+    }
+    if options.conflict == ConflictPolicy::Error
+        && let Some(label) = incoming
+            .glyphs
+            .iter()
+            .flat_map(|glyph| &glyph.labels)
+            .find(|label| owners.contains_key(label))
+    {
+        return Err(MergeError::Conflict {
+            label: label.clone(),
+        });
+    }
+
+    // Give incoming glyphs their own bearings and shift, so they sit in the same
+    // place under the base font's global values
+    pin_bearing_anchors(base, false);
+    let shift = match options.align {
+        VerticalAlign::Baseline => 0,
+        VerticalAlign::Ascent => bearing_anchors(base).0 - bearing_anchors(incoming).0,
+    };
+    let own_value =
+        |value: i32, global: Option<i32>| (value != global.unwrap_or(0)).then_some(value);
+    let adopt = |glyph: &GlyphDefinition, metrics: &GlyphMetrics| GlyphDefinition {
+        left_bearing: own_value(metrics.left_bearing, base.global_left_bearing),
+        right_bearing: own_value(metrics.right_bearing, base.global_right_bearing),
+        shift_up: own_value(metrics.shift_up + shift, base.global_shift_up),
+        top_bearing: None,
+        bottom_bearing: None,
+        ..glyph.clone()
+    };
+    let adopted: Vec<GlyphDefinition> = incoming
+        .glyphs
+        .iter()
+        .zip(glyph_metrics_all(incoming))
+        .map(|(glyph, metrics)| adopt(glyph, &metrics))
+        .collect();
+
+    let original_count = base.glyphs.len();
+    let mut emptied = vec![false; original_count];
+    for mut glyph in adopted {
+        let mut clashes: Vec<usize> = glyph
+            .labels
+            .iter()
+            .filter_map(|label| owners.get(label).copied())
+            .collect();
+        clashes.sort_unstable();
+        clashes.dedup();
+        let Some((&first, others)) = clashes.split_first() else {
+            base.glyphs.push(glyph);
+            continue;
+        };
+        match options.conflict {
+            ConflictPolicy::KeepFirst | ConflictPolicy::Error => {
+                merge_kerning(&mut base.glyphs[first], &glyph);
+            }
+            ConflictPolicy::Replace => {
+                for &index in others {
+                    let labels = &mut base.glyphs[index].labels;
+                    labels.retain(|label| !glyph.labels.contains(label));
+                    emptied[index] = labels.is_empty();
+                }
+                let replaced = &base.glyphs[first];
+                for label in &replaced.labels {
+                    if !glyph.labels.contains(label) {
+                        glyph.labels.push(label.clone());
+                    }
+                }
+                merge_kerning(&mut glyph, replaced);
+                base.glyphs[first] = glyph;
+            }
+        }
+    }
+    let mut index = 0;
+    base.glyphs.retain(|_| {
+        index += 1;
+        index > original_count || !emptied[index - 1]
+    });
+
+    merge_metadata(base, incoming, options.metadata);
+    update_metrics(base);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{bitmap, char, glyph};
+
+    #[test]
+    fn test_conflict_policies() {
+        let base = YaffFont {
+            glyphs: vec![
+                glyph(
+                    vec![char('A'), Label::Codepoint(vec![0x41])],
+                    bitmap(&["@"]),
+                ),
+                glyph(vec![Label::Tag("a".into())], bitmap(&["@"])),
+            ],
+            ..Default::default()
+        };
+        let incoming = YaffFont {
+            glyphs: vec![
+                glyph(vec![char('A'), Label::Tag("a".into())], bitmap(&["."])),
+                glyph(vec![char('B')], bitmap(&["."])),
+            ],
+            ..Default::default()
+        };
+
+        let mut kept = base.clone();
+        merge(&mut kept, &incoming, &MergeOptions::default()).unwrap();
+        assert_eq!(kept.glyphs.len(), 3);
+        assert_eq!(kept.glyphs[0], base.glyphs[0]);
+        assert_eq!(kept.glyphs[2].labels, vec![char('B')]);
+
+        let mut replaced = base.clone();
+        let options = MergeOptions {
+            conflict: ConflictPolicy::Replace,
+            ..Default::default()
+        };
+        merge(&mut replaced, &incoming, &options).unwrap();
+        // The glyph tagged "a" lost its only label to the incoming glyph
+        assert_eq!(replaced.glyphs.len(), 2);
+        assert_eq!(
+            replaced.glyphs[0].labels,
+            vec![
+                char('A'),
+                Label::Tag("a".into()),
+                Label::Codepoint(vec![0x41])
+            ]
+        );
+        assert_eq!(replaced.glyphs[0].bitmap, incoming.glyphs[0].bitmap);
+
+        let mut failed = base.clone();
+        let options = MergeOptions {
+            conflict: ConflictPolicy::Error,
+            ..Default::default()
+        };
+        assert_eq!(
+            merge(&mut failed, &incoming, &options),
+            Err(MergeError::Conflict { label: char('A') })
+        );
+        assert_eq!(failed, base);
+    }
+
+    #[test]
+    fn test_incoming_glyphs_keep_their_position() {
+        let mut base = YaffFont {
+            ascent: Some(4),
+            descent: Some(1),
+            global_left_bearing: Some(1),
+            glyphs: vec![glyph(vec![char('A')], bitmap(&["@"]))],
+            ..Default::default()
+        };
+        let mut x = glyph(vec![char('x')], bitmap(&["@"]));
+        x.top_bearing = Some(1);
+        let incoming = YaffFont {
+            ascent: Some(3),
+            descent: Some(1),
+            global_shift_up: Some(-1),
+            glyphs: vec![glyph(vec![char('g')], bitmap(&["@"])), x],
+            ..Default::default()
+        };
+        let before = glyph_metrics_all(&incoming);
+
+        let mut aligned = base.clone();
+        merge(&mut aligned, &incoming, &MergeOptions::default()).unwrap();
+        let after = glyph_metrics_all(&aligned);
+        // Top bearings count from each font's ascent, the rest from the baseline
+        for (after, before) in after[1..].iter().zip(&before) {
+            assert_eq!(after.raster_bounds, before.raster_bounds);
+            assert_eq!(after.advance_width, before.advance_width);
+            assert_eq!(after.top_bearing, before.top_bearing + 1);
+        }
+        assert_eq!(aligned.glyphs[1].left_bearing, Some(0));
+
+        let options = MergeOptions {
+            align: VerticalAlign::Ascent,
+            ..Default::default()
+        };
+        merge(&mut base, &incoming, &options).unwrap();
+        let after = glyph_metrics_all(&base);
+        assert_eq!(after[1].shift_up, before[0].shift_up + 1);
+        assert_eq!(after[2].top_bearing, 1);
+    }
+
+    #[test]
+    fn test_kerning_and_metadata() {
+        let mut a = glyph(vec![char('A')], bitmap(&["@"]));
+        a.right_kerning = Some(HashMap::from([(char('V'), -1.0)]));
+        let mut base = YaffFont {
+            name: Some("Base".into()),
+            max_width: Some(1),
+            glyphs: vec![a],
+            ..Default::default()
+        };
+        let mut incoming_a = glyph(vec![char('A')], bitmap(&["@"]));
+        incoming_a.right_kerning = Some(HashMap::from([(char('V'), -2.0), (char('W'), -1.0)]));
+        let incoming = YaffFont {
+            name: Some("Incoming".into()),
+            author: Some("Someone".into()),
+            glyphs: vec![incoming_a, glyph(vec![char('W')], bitmap(&["@@@"]))],
+            ..Default::default()
+        };
+
+        merge(&mut base, &incoming, &MergeOptions::default()).unwrap();
+        assert_eq!(
+            base.glyphs[0].right_kerning,
+            Some(HashMap::from([(char('V'), -1.0), (char('W'), -1.0)]))
+        );
+        assert_eq!(base.name.as_deref(), Some("Base"));
+        assert_eq!(base.author.as_deref(), Some("Someone"));
+        // Derived metrics the base stores are brought up to date
+        assert_eq!(base.max_width, Some(3));
+        assert_eq!(base.raster_bounds, None);
+    }
+
+    #[test]
+    fn test_proportional_glyphs_end_character_cell_metrics() {
+        let mut base = YaffFont {
+            glyphs: vec![glyph(vec![char('i')], bitmap(&["@.", "@."]))],
+            ..Default::default()
+        };
+        crate::metrics::set_metrics(&mut base);
+        assert_eq!(base.spacing, Some(FontSpacing::CharacterCell));
+        assert_eq!(base.cell_size, Some((2, 2)));
+
+        let incoming = YaffFont {
+            glyphs: vec![
+                glyph(vec![char('m')], bitmap(&["@@@@", "@@@@"])),
+                glyph(vec![char('w')], bitmap(&["@@@", "@@@"])),
+            ],
+            ..Default::default()
+        };
+        merge(&mut base, &incoming, &MergeOptions::default()).unwrap();
+        assert_eq!(base.spacing, Some(FontSpacing::Proportional));
+        assert_eq!(base.cell_size, None);
+        assert_eq!(base.max_width, Some(4));
+    }
+
+    #[test]
+    fn test_codepoints_converted_to_base_encoding() {
+        let mut base = YaffFont {
+            encoding: Some("cp437".into()),
+            glyphs: vec![glyph(vec![Label::Codepoint(vec![0x41])], bitmap(&["@"]))],
+            ..Default::default()
+        };
+        let mut e_acute = glyph(vec![Label::Codepoint(vec![0xe9])], bitmap(&["@"]));
+        e_acute.right_kerning = Some(HashMap::from([(Label::Codepoint(vec![0x41]), -1.0)]));
+        let incoming = YaffFont {
+            encoding: Some("latin-1".into()),
+            glyphs: vec![
+                e_acute,
+                glyph(vec![Label::Codepoint(vec![0xbe])], bitmap(&["@"])),
+            ],
+            ..Default::default()
+        };
+
+        merge(&mut base, &incoming, &MergeOptions::default()).unwrap();
+        assert_eq!(base.encoding.as_deref(), Some("cp437"));
+        // é is 0x82 in cp437, which has no ¾
+        assert_eq!(base.glyphs[1].labels, vec![Label::Codepoint(vec![0x82])]);
+        assert_eq!(
+            base.glyphs[1].right_kerning,
+            Some(HashMap::from([(Label::Codepoint(vec![0x41]), -1.0)]))
+        );
+        assert_eq!(base.glyphs[2].labels, vec![char('¾')]);
+
+        let unknown = YaffFont {
+            encoding: Some("no-such-encoding".into()),
+            ..incoming
+        };
+        assert_eq!(
+            merge(&mut base, &unknown, &MergeOptions::default()),
+            Err(MergeError::Encoding {
+                base: "cp437".into(),
+                incoming: "no-such-encoding".into()
+            })
+        );
+    }
+}
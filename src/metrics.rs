@@ -0,0 +1,440 @@
+//! Font-wide metrics derived from the glyphs.
+//!
+//! Coordinates follow monobit: x grows to the right from the glyph origin and y grows
+//! upwards from the baseline. Bounds are `(left, bottom, right, top)` with exclusive
+//! right and top edges, so a 5x8 glyph sitting on the baseline has raster bounds
+//! `(0, 0, 5, 8)`. Glyph positions are resolved by [`glyph_metrics`].
+
+use crate::models::*;
+use crate::utils::calculate_ascent;
+
+/// Metrics computed from a font's glyphs by [`calculate_metrics`].
+///
+/// The width metrics for specific characters are `None` if the font has no glyph
+/// labelled with that Unicode character.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontMetrics {
+    /// Top of the raster above the baseline.
+    pub ascent: i32,
+    /// Bottom of the raster below the baseline, as a positive number.
+    pub descent: i32,
+    /// `ascent + descent`.
+    pub pixel_size: i32,
+    /// Union of all glyph rasters.
+    pub raster_bounds: (i32, i32, i32, i32),
+    /// Union of the inked pixels of all glyphs; all zero if there is no ink.
+    pub ink_bounds: (i32, i32, i32, i32),
+    /// Width and height of `raster_bounds`.
+    pub raster_size: (u32, u32),
+    /// Width of a single cell and the pixel size, for character-cell and
+    /// multi-cell fonts only.
+    pub cell_size: Option<(u32, u32)>,
+    /// Width and height of `ink_bounds`.
+    pub bounding_box: (u32, u32),
+    /// Mean advance width over all glyphs.
+    pub average_width: f32,
+    /// Largest advance width.
+    pub max_width: i32,
+    /// Advance width of `X`.
+    pub cap_width: Option<i32>,
+    /// Advance width of `0`.
+    pub digit_width: Option<i32>,
+    /// Top of the ink of `x` above the baseline.
+    pub x_height: Option<i32>,
+    /// Top of the ink of `X` above the baseline.
+    pub cap_height: Option<i32>,
+    pub spacing: FontSpacing,
+}
+
+/// Metrics of one glyph with the font-wide defaults and YAFF precedence applied.
+///
+/// The vertical position of the raster is taken from the first of these that is set:
+/// the glyph's `shift-up`; its `top-bearing`, measured down from the ascent line;
+/// its `bottom-bearing`, measured up from the descent line; the global `shift-up`.
+/// Otherwise the raster sits on the baseline. Bearings fall back to the global
+/// bearings and then to 0. Where the font has no `ascent` or `descent`, they are
+/// estimated from the glyphs' `shift-up` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphMetrics {
+    pub advance_width: i32,
+    pub left_bearing: i32,
+    pub right_bearing: i32,
+    /// Height of the bottom of the raster above the baseline.
+    pub shift_up: i32,
+    /// Distance from the top of the raster down to the ascent line; negative if
+    /// the raster rises above the ascent.
+    pub top_bearing: i32,
+    /// Distance from the descent line up to the bottom of the raster.
+    pub bottom_bearing: i32,
+    /// The raster relative to the origin, as `(left, bottom, right, top)`.
+    pub raster_bounds: (i32, i32, i32, i32),
+    /// The inked pixels relative to the origin; `None` for a blank glyph.
+    pub ink_bounds: Option<(i32, i32, i32, i32)>,
+}
+
+/// Font-wide values that glyph metrics fall back on.
+struct Defaults {
+    left_bearing: i32,
+    right_bearing: i32,
+    shift_up: Option<i32>,
+    ascent: i32,
+    descent: i32,
+}
+
+impl Defaults {
+    fn from_font(font: &YaffFont) -> Self {
+        let explicit_shifts = font
+            .glyphs
+            .iter()
+            .filter_map(|g| g.shift_up.or(font.global_shift_up));
+        Defaults {
+            left_bearing: font.global_left_bearing.unwrap_or(0),
+            right_bearing: font.global_right_bearing.unwrap_or(0),
+            shift_up: font.global_shift_up,
+            ascent: font.ascent.unwrap_or_else(|| calculate_ascent(font)),
+            descent: font
+                .descent
+                .unwrap_or_else(|| -explicit_shifts.min().unwrap_or(0).min(0)),
+        }
+    }
+
+    fn resolve(&self, glyph: &GlyphDefinition) -> GlyphMetrics {
+        let left_bearing = glyph.left_bearing.unwrap_or(self.left_bearing);
+        let right_bearing = glyph.right_bearing.unwrap_or(self.right_bearing);
+        let width = glyph.bitmap.width as i32;
+        let height = glyph.bitmap.height as i32;
+        let shift_up = glyph
+            .shift_up
+            .or(glyph.top_bearing.map(|tb| self.ascent - tb - height))
+            .or(glyph.bottom_bearing.map(|bb| bb - self.descent))
+            .or(self.shift_up)
+            .unwrap_or(0);
+        let top = shift_up + height;
+
+        let mut ink: Option<(i32, i32, i32, i32)> = None;
+        for (y, row) in glyph.bitmap.pixels.iter().enumerate() {
+            for (x, _) in row.iter().enumerate().filter(|(_, set)| **set) {
+                let (x, y) = (left_bearing + x as i32, top - y as i32);
+                ink = Some(match ink {
+                    None => (x, y - 1, x + 1, y),
+                    Some(b) => (b.0.min(x), b.1.min(y - 1), b.2.max(x + 1), b.3.max(y)),
+                });
+            }
+        }
+
+        GlyphMetrics {
+            advance_width: left_bearing + width + right_bearing,
+            left_bearing,
+            right_bearing,
+            shift_up,
+            top_bearing: self.ascent - top,
+            bottom_bearing: shift_up + self.descent,
+            raster_bounds: (left_bearing, shift_up, left_bearing + width, top),
+            ink_bounds: ink,
+        }
+    }
+}
+
+impl GlyphMetrics {
+    /// Whether the raster stays within the horizontal extent of its advance.
+    fn within_advance(&self) -> bool {
+        self.raster_bounds.0 >= 0 && self.raster_bounds.2 <= self.advance_width
+    }
+}
+
+/// The ascent and descent that `top-bearing` and `bottom-bearing` are measured
+/// from, including the estimates used when the font doesn't set them.
+pub(crate) fn bearing_anchors(font: &YaffFont) -> (i32, i32) {
+    let defaults = Defaults::from_font(font);
+    (defaults.ascent, defaults.descent)
+}
+
+/// Resolves the effective metrics of a glyph of `font`.
+///
+/// To resolve every glyph of a font, [`glyph_metrics_all`] avoids recomputing the
+/// font-wide defaults for each one.
+pub fn glyph_metrics(font: &YaffFont, glyph: &GlyphDefinition) -> GlyphMetrics {
+    Defaults::from_font(font).resolve(glyph)
+}
+
+/// Resolves the effective metrics of all glyphs of `font`, in glyph order.
+pub fn glyph_metrics_all(font: &YaffFont) -> Vec<GlyphMetrics> {
+    let defaults = Defaults::from_font(font);
+    font.glyphs.iter().map(|g| defaults.resolve(g)).collect()
+}
+
+fn union(a: (i32, i32, i32, i32), b: (i32, i32, i32, i32)) -> (i32, i32, i32, i32) {
+    (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3))
+}
+
+fn size(bounds: (i32, i32, i32, i32)) -> (u32, u32) {
+    ((bounds.2 - bounds.0) as u32, (bounds.3 - bounds.1) as u32)
+}
+
+/// Detects the spacing the way monobit does: glyphs of one advance that stay inside
+/// their cell make a character-cell font, or a monospace one if some overhang; one
+/// and two cell widths make a multi-cell font.
+fn detect_spacing(glyphs: &[GlyphMetrics]) -> FontSpacing {
+    let mut advances: Vec<i32> = glyphs.iter().map(|p| p.advance_width).collect();
+    advances.sort_unstable();
+    advances.dedup();
+    let in_cells = glyphs.iter().all(GlyphMetrics::within_advance);
+    match advances.as_slice() {
+        [_] if in_cells => FontSpacing::CharacterCell,
+        [_] => FontSpacing::Monospace,
+        [narrow, wide] if *narrow > 0 && *wide == 2 * narrow && in_cells => FontSpacing::MultiCell,
+        _ => FontSpacing::Proportional,
+    }
+}
+
+fn find_char<'a>(
+    font: &YaffFont,
+    metrics: &'a [GlyphMetrics],
+    c: char,
+) -> Option<&'a GlyphMetrics> {
+    let label = Label::Unicode(vec![c as u32]);
+    font.glyphs
+        .iter()
+        .position(|glyph| glyph.labels.contains(&label))
+        .map(|index| &metrics[index])
+}
+
+/// Computes the derived metrics of a font from its glyphs and bearings.
+pub fn calculate_metrics(font: &YaffFont) -> FontMetrics {
+    let glyphs = glyph_metrics_all(font);
+
+    let raster_bounds = glyphs
+        .iter()
+        .map(|p| p.raster_bounds)
+        .reduce(union)
+        .unwrap_or_default();
+    let ink_bounds = glyphs
+        .iter()
+        .filter_map(|p| p.ink_bounds)
+        .reduce(union)
+        .unwrap_or_default();
+    let ascent = raster_bounds.3;
+    let descent = -raster_bounds.1;
+    let pixel_size = ascent + descent;
+
+    let spacing = detect_spacing(&glyphs);
+    let cell_size = match spacing {
+        FontSpacing::CharacterCell | FontSpacing::MultiCell => {
+            let cell_width = glyphs.iter().map(|p| p.advance_width).min().unwrap_or(0);
+            Some((cell_width as u32, pixel_size as u32))
+        }
+        _ => None,
+    };
+
+    let average_width = if glyphs.is_empty() {
+        0.0
+    } else {
+        glyphs.iter().map(|p| p.advance_width as f32).sum::<f32>() / glyphs.len() as f32
+    };
+    let ink_top = |c| find_char(font, &glyphs, c).and_then(|p| p.ink_bounds.map(|ink| ink.3));
+
+    FontMetrics {
+        ascent,
+        descent,
+        pixel_size,
+        raster_bounds,
+        ink_bounds,
+        raster_size: size(raster_bounds),
+        cell_size,
+        bounding_box: size(ink_bounds),
+        average_width,
+        max_width: glyphs.iter().map(|p| p.advance_width).max().unwrap_or(0),
+        cap_width: find_char(font, &glyphs, 'X').map(|p| p.advance_width),
+        digit_width: find_char(font, &glyphs, '0').map(|p| p.advance_width),
+        x_height: ink_top('x'),
+        cap_height: ink_top('X'),
+        spacing,
+    }
+}
+
+/// Which derived metrics of a font to set.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Apply {
+    All,
+    Missing,
+    Present,
+}
+
+/// Sets all derived metrics of a font, replacing the values it has.
+///
+/// Metrics the glyphs don't define, such as `cap-width` without an `X`, are
+/// removed.
+pub fn set_metrics(font: &mut YaffFont) {
+    apply_metrics(font, Apply::All);
+}
+
+/// Sets the derived metrics the font doesn't have yet.
+pub fn fill_missing_metrics(font: &mut YaffFont) {
+    apply_metrics(font, Apply::Missing);
+}
+
+/// Recalculates the derived metrics the font has, after its glyphs changed.
+///
+/// `ascent`, `descent` and `pixel-size` are kept, as glyphs positioned with
+/// `top-bearing` or `bottom-bearing` hang from them. A metric the glyphs no longer
+/// define, such as the `cell-size` of a font that stopped being a character-cell
+/// font, is removed.
+pub fn update_metrics(font: &mut YaffFont) {
+    apply_metrics(font, Apply::Present);
+}
+
+fn apply_metrics(font: &mut YaffFont, mode: Apply) {
+    fn apply<T>(field: &mut Option<T>, value: Option<T>, mode: Apply) {
+        let replace = match mode {
+            Apply::All => true,
+            Apply::Missing => field.is_none(),
+            Apply::Present => field.is_some(),
+        };
+        if replace {
+            *field = value;
+        }
+    }
+
+    let metrics = calculate_metrics(font);
+    if mode != Apply::Present {
+        apply(&mut font.ascent, Some(metrics.ascent), mode);
+        apply(&mut font.descent, Some(metrics.descent), mode);
+        apply(&mut font.pixel_size, Some(metrics.pixel_size), mode);
+    }
+    apply(&mut font.raster_bounds, Some(metrics.raster_bounds), mode);
+    apply(&mut font.ink_bounds, Some(metrics.ink_bounds), mode);
+    apply(&mut font.raster_size, Some(metrics.raster_size), mode);
+    apply(&mut font.cell_size, metrics.cell_size, mode);
+    apply(&mut font.bounding_box, Some(metrics.bounding_box), mode);
+    apply(&mut font.average_width, Some(metrics.average_width), mode);
+    apply(&mut font.max_width, Some(metrics.max_width), mode);
+    apply(&mut font.cap_width, metrics.cap_width, mode);
+    apply(&mut font.digit_width, metrics.digit_width, mode);
+    apply(&mut font.x_height, metrics.x_height, mode);
+    apply(&mut font.cap_height, metrics.cap_height, mode);
+    apply(&mut font.spacing, Some(metrics.spacing), mode);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{bitmap, char, glyph};
+
+    #[test]
+    fn test_character_cell_metrics() {
+        let mut font = YaffFont::new();
+        font.global_shift_up = Some(-1);
+        let x = glyph(vec![char('X')], bitmap(&["@..@", ".@@.", "@..@", "...."]));
+        let mut small_x = glyph(vec![char('x')], bitmap(&["....", "@..@", ".@@.", "@..."]));
+        small_x.shift_up = Some(-1);
+        font.glyphs = vec![x, small_x];
+
+        let metrics = calculate_metrics(&font);
+        assert_eq!(metrics.ascent, 3);
+        assert_eq!(metrics.descent, 1);
+        assert_eq!(metrics.pixel_size, 4);
+        assert_eq!(metrics.raster_bounds, (0, -1, 4, 3));
+        assert_eq!(metrics.ink_bounds, (0, -1, 4, 3));
+        assert_eq!(metrics.raster_size, (4, 4));
+        assert_eq!(metrics.bounding_box, (4, 4));
+        assert_eq!(metrics.spacing, FontSpacing::CharacterCell);
+        assert_eq!(metrics.cell_size, Some((4, 4)));
+        assert_eq!(metrics.cap_height, Some(3));
+        assert_eq!(metrics.x_height, Some(2));
+        assert_eq!(metrics.cap_width, Some(4));
+        assert_eq!(metrics.digit_width, None);
+    }
+
+    #[test]
+    fn test_spacing_detection() {
+        let mut font = YaffFont::new();
+        let mut narrow = glyph(vec![char('i')], bitmap(&["@"]));
+        narrow.right_bearing = Some(1);
+        let wide = glyph(vec![char('m')], bitmap(&["@@@@"]));
+        font.glyphs = vec![narrow.clone(), wide];
+        assert_eq!(calculate_metrics(&font).spacing, FontSpacing::MultiCell);
+
+        font.glyphs.push(glyph(vec![char('w')], bitmap(&["@@@"])));
+        let metrics = calculate_metrics(&font);
+        assert_eq!(metrics.spacing, FontSpacing::Proportional);
+        assert_eq!(metrics.cell_size, None);
+        assert_eq!(metrics.max_width, 4);
+        assert_eq!(metrics.average_width, 3.0);
+
+        let mut overhang = glyph(vec![char('j')], bitmap(&["@@"]));
+        overhang.left_bearing = Some(-1);
+        overhang.right_bearing = Some(1);
+        font.glyphs = vec![narrow, overhang];
+        assert_eq!(calculate_metrics(&font).spacing, FontSpacing::Monospace);
+    }
+
+    #[test]
+    fn test_fill_missing_metrics_keeps_existing_values() {
+        let mut font = YaffFont::new();
+        font.glyphs = vec![glyph(vec![char('0')], bitmap(&["@@", "@@"]))];
+        font.ascent = Some(5);
+        fill_missing_metrics(&mut font);
+        assert_eq!(font.ascent, Some(5));
+        assert_eq!(font.descent, Some(0));
+        assert_eq!(font.digit_width, Some(2));
+        assert_eq!(font.cap_width, None);
+
+        font.cap_width = Some(9);
+        font.x_height = Some(9);
+        font.cell_size = Some((9, 9));
+        font.glyphs
+            .push(glyph(vec![char('1')], bitmap(&["@@@", "@@@"])));
+        set_metrics(&mut font);
+        assert_eq!(font.ascent, Some(2));
+        // Stale values the glyphs no longer define are cleared
+        assert_eq!(font.cap_width, None);
+        assert_eq!(font.x_height, None);
+        assert_eq!(font.cell_size, None);
+        assert_eq!(font.digit_width, Some(2));
+    }
+
+    #[test]
+    fn test_glyph_metrics_precedence() {
+        let mut font = YaffFont::new();
+        font.ascent = Some(7);
+        font.descent = Some(2);
+        font.global_shift_up = Some(-1);
+        font.global_left_bearing = Some(1);
+        let base = glyph(vec![char('A')], bitmap(&["@@", "@@", "@@"]));
+
+        let metrics = glyph_metrics(&font, &base);
+        assert_eq!(metrics.shift_up, -1);
+        assert_eq!(metrics.left_bearing, 1);
+        assert_eq!(metrics.advance_width, 3);
+        assert_eq!(metrics.raster_bounds, (1, -1, 3, 2));
+        assert_eq!(metrics.ink_bounds, Some((1, -1, 3, 2)));
+        assert_eq!(metrics.top_bearing, 5);
+        assert_eq!(metrics.bottom_bearing, 1);
+
+        let mut from_bottom = base.clone();
+        from_bottom.bottom_bearing = Some(4);
+        assert_eq!(glyph_metrics(&font, &from_bottom).shift_up, 2);
+
+        let mut from_top = from_bottom.clone();
+        from_top.top_bearing = Some(1);
+        assert_eq!(glyph_metrics(&font, &from_top).shift_up, 3);
+        assert_eq!(glyph_metrics(&font, &from_top).top_bearing, 1);
+
+        let mut explicit = from_top.clone();
+        explicit.shift_up = Some(0);
+        explicit.left_bearing = Some(0);
+        explicit.right_bearing = Some(-1);
+        let metrics = glyph_metrics(&font, &explicit);
+        assert_eq!(metrics.shift_up, 0);
+        assert_eq!(metrics.advance_width, 1);
+
+        font.glyphs = vec![base, explicit];
+        assert_eq!(
+            glyph_metrics_all(&font),
+            vec![
+                glyph_metrics(&font, &font.glyphs[0]),
+                glyph_metrics(&font, &font.glyphs[1])
+            ]
+        );
+    }
+}
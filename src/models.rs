@@ -1,3 +1,4 @@
+use crate::diagnostic::Diagnostic;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -90,14 +91,35 @@ pub struct GlyphDefinition {
 ///
 /// YAFF supports multiple labeling schemes to accommodate different font encodings
 /// and use cases, from modern Unicode fonts to legacy 8-bit character sets.
+///
+/// A `Codepoint` label holds the byte sequence of a single encoded character, so
+/// double-byte charsets like Shift-JIS store e.g. `[0x81, 0x40]` for code `0x8140`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Label {
-    Unicode(Vec<u32>),   // Unicode code
-    Codepoint(Vec<u16>), // encoding-specfic code
-    Tag(String),         // tag like 'acircumflex'
+    Unicode(Vec<u32>),  // Unicode code
+    Codepoint(Vec<u8>), // encoding-specific byte sequence
+    Tag(String),        // tag like 'acircumflex'
     Anonymous,
 }
 
+/// Short description of a label for messages, in YAFF syntax.
+impl std::fmt::Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Label::Unicode(values) => {
+                let values: Vec<String> = values.iter().map(|v| format!("u+{v:04X}")).collect();
+                write!(f, "{}", values.join(", "))
+            }
+            Label::Codepoint(bytes) => {
+                let hex: String = bytes.iter().map(|b| format!("{b:02X}")).collect();
+                write!(f, "0x{hex}")
+            }
+            Label::Tag(tag) => write!(f, "\"{tag}\""),
+            Label::Anonymous => write!(f, "(anonymous)"),
+        }
+    }
+}
+
 /// Bitmap representation of a glyph as a 2D boolean array.
 ///
 /// Pixels are stored row-by-row, with `true` representing foreground pixels
@@ -159,6 +181,10 @@ pub enum ParseError {
         line: usize,
         char_found: char,
     },
+    /// A problem that strict parsing refuses instead of recording it.
+    Strict(Diagnostic),
+    /// Every error found when the parser recovered from more than one.
+    Multiple(Vec<Diagnostic>),
 }
 
 impl std::fmt::Display for ParseError {
@@ -197,11 +223,22 @@ impl std::fmt::Display for ParseError {
                 write!(f, "Inconsistent glyph line length at line {line}")
             }
             ParseError::InvalidGlyphCharacter { line, char_found } => {
+                write!(f, "Invalid glyph character '{char_found}' at line {line}")
+            }
+            ParseError::Strict(diagnostic) => {
                 write!(
                     f,
-                    "Invalid glyph character '{char_found}' at line {line}"
+                    "Rejected in strict mode at line {}: {}",
+                    diagnostic.line, diagnostic.message
                 )
             }
+            ParseError::Multiple(diagnostics) => {
+                write!(f, "{} errors:", diagnostics.len())?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n{diagnostic}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -221,6 +258,188 @@ impl From<std::io::Error> for ParseError {
     }
 }
 
+/// Errors for font data that cannot be written as YAFF text that reads back unchanged.
+///
+/// `glyph` is the zero-based position of the offending glyph in the output.
+#[derive(Debug)]
+pub enum EncodeError {
+    Io(std::io::Error),
+    Format(std::fmt::Error),
+    EmptyValue {
+        glyph: Option<usize>,
+        property_key: String,
+    },
+    NonFiniteValue {
+        glyph: Option<usize>,
+        property_key: String,
+        value: f32,
+    },
+    InvalidLabel {
+        glyph: usize,
+        label: Label,
+        message: String,
+    },
+    DuplicateLabel {
+        glyph: usize,
+        label: Label,
+    },
+    InconsistentBitmap {
+        glyph: usize,
+        message: String,
+    },
+    HeaderAfterGlyphs,
+}
+
+impl std::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::Io(err) => write!(f, "IO error: {err}"),
+            EncodeError::Format(err) => write!(f, "Formatting error: {err}"),
+            EncodeError::EmptyValue {
+                glyph: None,
+                property_key,
+            } => write!(f, "Empty value for property '{property_key}'"),
+            EncodeError::EmptyValue {
+                glyph: Some(glyph),
+                property_key,
+            } => write!(
+                f,
+                "Empty value for property '{property_key}' in glyph {glyph}"
+            ),
+            EncodeError::NonFiniteValue {
+                glyph: None,
+                property_key,
+                value,
+            } => write!(f, "Non-finite value {value} for property '{property_key}'"),
+            EncodeError::NonFiniteValue {
+                glyph: Some(glyph),
+                property_key,
+                value,
+            } => write!(
+                f,
+                "Non-finite value {value} for property '{property_key}' in glyph {glyph}"
+            ),
+            EncodeError::InvalidLabel {
+                glyph,
+                label,
+                message,
+            } => write!(f, "Invalid label {label:?} in glyph {glyph}: {message}"),
+            EncodeError::DuplicateLabel { glyph, label } => {
+                write!(f, "Duplicate label {label:?} in glyph {glyph}")
+            }
+            EncodeError::InconsistentBitmap { glyph, message } => {
+                write!(f, "Inconsistent bitmap in glyph {glyph}: {message}")
+            }
+            EncodeError::HeaderAfterGlyphs => {
+                write!(
+                    f,
+                    "Global properties must be written before the first glyph"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EncodeError::Io(err) => Some(err),
+            EncodeError::Format(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for EncodeError {
+    fn from(err: std::io::Error) -> Self {
+        EncodeError::Io(err)
+    }
+}
+
+impl From<std::fmt::Error> for EncodeError {
+    fn from(err: std::fmt::Error) -> Self {
+        EncodeError::Format(err)
+    }
+}
+
+/// Errors for fonts that cannot be turned into a strict character-cell font.
+///
+/// `glyph` is the zero-based position of the offending glyph in `font.glyphs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellError {
+    /// The glyph advances are not all one cell wide, or one and two cells wide.
+    Proportional,
+    /// Ink of the glyph lies to the left or right of its advance.
+    InkOutsideCell { glyph: usize },
+}
+
+impl std::fmt::Display for CellError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CellError::Proportional => {
+                write!(f, "Font is proportional and has no common character cell")
+            }
+            CellError::InkOutsideCell { glyph } => {
+                write!(f, "Ink of glyph {glyph} extends outside its character cell")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CellError {}
+
+/// Errors for pixel buffers that cannot hold the image they are said to contain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// The buffer is shorter than its rows need, in bytes.
+    BufferTooSmall { needed: usize, actual: usize },
+    /// The distance between rows is less than the length of a row, in bytes.
+    StrideTooSmall { stride: usize, row: usize },
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::BufferTooSmall { needed, actual } => {
+                write!(f, "Image buffer has {actual} bytes but needs {needed}")
+            }
+            ImageError::StrideTooSmall { stride, row } => {
+                write!(
+                    f,
+                    "Image stride of {stride} bytes is shorter than a row of {row}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+/// Errors for fonts that cannot be merged under the chosen conflict policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    /// Both fonts have a glyph with this label.
+    Conflict { label: Label },
+    /// The incoming font has codepoint labels in an encoding that can't be
+    /// converted to the encoding of the base font.
+    Encoding { base: String, incoming: String },
+}
+
+impl std::fmt::Display for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::Conflict { label } => {
+                write!(f, "Both fonts have a glyph labelled {label}")
+            }
+            MergeError::Encoding { base, incoming } => {
+                write!(f, "Cannot convert codepoints from {incoming} to {base}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
 pub enum LineType {
     KeyValue {
@@ -1,13 +1,108 @@
+use crate::diagnostic::{Diagnostic, DiagnosticKind, Severity};
+use crate::label::parse_key_as_label;
 use crate::models::*;
-use regex::Regex;
-use std::collections::HashMap;
-use std::sync::OnceLock;
+use std::collections::{HashMap, VecDeque};
+use std::io::BufRead;
+use std::ops::Range;
+
+/// How the parser treats deprecated syntax and unknown properties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Accept them and record a [`Diagnostic`] for each.
+    #[default]
+    Lenient,
+    /// Fail with [`ParseError::Strict`] on deprecated syntax and unknown properties.
+    /// Other problems are still only recorded.
+    Strict,
+}
+
+/// What the parser does when a glyph or property is malformed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Recovery {
+    /// Fail the parse. Whole-document parses still report every problem, through
+    /// [`ParseError::Multiple`] when there is more than one.
+    #[default]
+    Fail,
+    /// Drop the broken glyph or property, record an error [`Diagnostic`] and carry
+    /// on from the next top-level line.
+    Discard,
+    /// Like `Discard`, but first truncate bitmap lines that are longer than the
+    /// first line of their glyph, keeping the glyph with a warning.
+    Repair,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ParseOptions {
+    pub mode: ParseMode,
+    pub recovery: Recovery,
+}
 
 pub fn from_str(s: &str) -> Result<YaffFont, ParseError> {
-    let decoder = Decoder::new(s);
+    from_str_with_options(s, &ParseOptions::default()).map(|(font, _)| font)
+}
+
+pub fn from_reader<R: BufRead>(reader: R) -> Result<YaffFont, ParseError> {
+    from_reader_with_options(reader, &ParseOptions::default()).map(|(font, _)| font)
+}
+
+pub fn from_str_with_options(
+    s: &str,
+    options: &ParseOptions,
+) -> Result<(YaffFont, Vec<Diagnostic>), ParseError> {
+    from_reader_with_options(s.as_bytes(), options)
+}
+
+pub fn from_reader_with_options<R: BufRead>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<(YaffFont, Vec<Diagnostic>), ParseError> {
+    let decoder = Decoder::new(reader, options);
     decoder.parse_internal()
 }
 
+/// Streaming YAFF parser that reads glyphs one at a time from any `BufRead`.
+///
+/// The global properties are parsed when the reader is created and are available
+/// through [`YaffReader::properties`]; iterating then yields each glyph as soon as
+/// it is complete, so only one glyph is held in memory at a time. Errors carry the
+/// same line information as [`YaffFont::from_str`](std::str::FromStr::from_str),
+/// and iteration stops after the first error unless [`ParseOptions::recovery`] says
+/// to skip broken glyphs.
+pub struct YaffReader<R: BufRead> {
+    decoder: Decoder<R>,
+}
+
+impl<R: BufRead> YaffReader<R> {
+    /// Creates a reader and parses the global properties up to the first glyph.
+    pub fn new(reader: R) -> Result<Self, ParseError> {
+        Self::with_options(reader, &ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: &ParseOptions) -> Result<Self, ParseError> {
+        let mut decoder = Decoder::new(reader, options);
+        decoder.parse_global_properties()?;
+        Ok(YaffReader { decoder })
+    }
+
+    /// Returns the global font properties. The `glyphs` vector is always empty.
+    pub fn properties(&self) -> &YaffFont {
+        &self.decoder.font
+    }
+
+    /// Returns the problems recorded so far, in input order.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.decoder.reporter.diagnostics
+    }
+}
+
+impl<R: BufRead> Iterator for YaffReader<R> {
+    type Item = Result<GlyphDefinition, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.decoder.next_glyph().transpose()
+    }
+}
+
 /// Classifies a single line from a YAFF file.
 /// Returns a tuple of (line_type, indentation_level).
 pub fn classify_line(line_str: &str) -> (LineType, usize) {
@@ -101,188 +196,6 @@ pub fn classify_line(line_str: &str) -> (LineType, usize) {
     }
 }
 
-// --- Regex definitions for individual label elements using std::sync::OnceLock ---
-// For U+HEX or u+HEX
-static RE_UNICODE_UPLUS_ELEMENT_LOCK: OnceLock<Regex> = OnceLock::new();
-fn get_re_unicode_uplus_element() -> &'static Regex {
-    RE_UNICODE_UPLUS_ELEMENT_LOCK
-        .get_or_init(|| Regex::new(r"^(?:u|U)\+([0-9a-fA-F]{1,6})$").unwrap())
-}
-
-// For 'char sequence'
-static RE_UNICODE_CHAR_SEQ_ELEMENT_LOCK: OnceLock<Regex> = OnceLock::new();
-fn get_re_unicode_char_seq_element() -> &'static Regex {
-    RE_UNICODE_CHAR_SEQ_ELEMENT_LOCK.get_or_init(|| Regex::new(r"^'(.*?)'$").unwrap())
-}
-
-// For "tag string" - this is for the whole key_str if it's a tag
-static RE_TAG_LABEL_LOCK: OnceLock<Regex> = OnceLock::new();
-fn get_re_tag_label() -> &'static Regex {
-    RE_TAG_LABEL_LOCK.get_or_init(|| Regex::new(r#"^"(.*?)"$"#).unwrap())
-}
-
-// For 0xHEX or 0XHEX
-static RE_CODEPOINT_HEX_ELEMENT_LOCK: OnceLock<Regex> = OnceLock::new();
-fn get_re_codepoint_hex_element() -> &'static Regex {
-    RE_CODEPOINT_HEX_ELEMENT_LOCK.get_or_init(|| Regex::new(r"^0[xX]([0-9a-fA-F]+)$").unwrap())
-}
-
-// For 0oOCT or 0OOCT
-static RE_CODEPOINT_OCT_ELEMENT_LOCK: OnceLock<Regex> = OnceLock::new();
-fn get_re_codepoint_oct_element() -> &'static Regex {
-    RE_CODEPOINT_OCT_ELEMENT_LOCK.get_or_init(|| Regex::new(r"^0[oO]([0-7]+)$").unwrap())
-}
-
-// For DECIMAL
-static RE_CODEPOINT_DEC_ELEMENT_LOCK: OnceLock<Regex> = OnceLock::new();
-fn get_re_codepoint_dec_element() -> &'static Regex {
-    RE_CODEPOINT_DEC_ELEMENT_LOCK.get_or_init(|| Regex::new(r"^([0-9]+)$").unwrap())
-}
-
-// Regex for deprecated unquoted tags (allows spaces, parentheses, and various characters, must start with letter or underscore)
-static RE_UNQUOTED_TAG_LOCK: OnceLock<Regex> = OnceLock::new();
-fn get_re_unquoted_tag() -> &'static Regex {
-    RE_UNQUOTED_TAG_LOCK.get_or_init(|| Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_./ ()-]*$").unwrap())
-}
-
-// Helper to parse a single Unicode element string (e.g., "u+0041" or "'A'")
-fn parse_unicode_element(element_str: &str) -> Option<Vec<u32>> {
-    if let Some(caps) = get_re_unicode_uplus_element().captures(element_str) {
-        let hex_val = caps.get(1).unwrap().as_str();
-        u32::from_str_radix(hex_val, 16).ok().map(|val| vec![val])
-    } else if let Some(caps) = get_re_unicode_char_seq_element().captures(element_str) {
-        let inner_str = caps.get(1).unwrap().as_str();
-        Some(inner_str.chars().map(|c| c as u32).collect())
-    } else {
-        None
-    }
-}
-
-// Helper to parse a sequence of Unicode elements (comma-separated)
-fn parse_unicode_label_sequence(elements: &[&str]) -> Option<Label> {
-    let mut unicode_values = Vec::new();
-    for el_str in elements {
-        if let Some(parsed_values) = parse_unicode_element(el_str) {
-            unicode_values.extend(parsed_values);
-        } else {
-            return None; // Mixed types not allowed
-        }
-    }
-    Some(Label::Unicode(unicode_values))
-}
-
-// Helper to parse a single Codepoint element string (e.g., "65", "0x41", "0o101")
-fn parse_codepoint_element(element_str: &str) -> Option<u16> {
-    let val_u32 = if let Some(caps) = get_re_codepoint_hex_element().captures(element_str) {
-        u32::from_str_radix(caps.get(1).unwrap().as_str(), 16).ok()
-    } else if let Some(caps) = get_re_codepoint_oct_element().captures(element_str) {
-        u32::from_str_radix(caps.get(1).unwrap().as_str(), 8).ok()
-    } else if get_re_codepoint_dec_element().is_match(element_str) {
-        // Check is_match first for plain decimal
-        element_str.parse::<u32>().ok()
-    } else {
-        None
-    };
-
-    match val_u32 {
-        Some(v) if v <= 65535 => Some(v as u16),
-        _ => None, // Parse failed or value out of u8 range
-    }
-}
-
-// Helper to parse a sequence of Codepoint elements (comma-separated)
-fn parse_codepoint_label_sequence(elements: &[&str]) -> Option<Label> {
-    let mut codepoint_bytes = Vec::new();
-    for el_str in elements {
-        if let Some(byte_val) = parse_codepoint_element(el_str) {
-            codepoint_bytes.push(byte_val);
-        } else {
-            return None; // Mixed types not allowed
-        }
-    }
-    Some(Label::Codepoint(codepoint_bytes))
-}
-
-// Helper to handle deprecated unquoted tag format
-fn handle_deprecated_unquoted_tag(
-    single_element: &str,
-    next_line_type: &LineType,
-) -> Option<Label> {
-    let should_treat_as_tag = matches!(
-        next_line_type,
-        LineType::BitmapLine { .. }
-            | LineType::KeyValue {
-                value_on_line: None,
-                ..
-            }
-    );
-
-    if should_treat_as_tag {
-        log::warn!(
-            "Deprecated unquoted tag format detected: '{single_element}'"
-        );
-        Some(Label::Tag(single_element.to_string()))
-    } else {
-        None
-    }
-}
-
-/// Parses a key string (content before the final ':' of a label line)
-/// into an `Option<Label>`.
-/// Returns `None` if the string does not conform to any known label syntax.
-pub fn parse_key_as_label(key_str: &str, next_line_type: &LineType) -> Option<Label> {
-    let trimmed_key = key_str.trim();
-
-    // Special case for a quoted comma so it doesn't get recognized as a sequence
-    if trimmed_key == "','" {
-        return Some(Label::Unicode(vec![0x2c]));
-    }
-
-    // Handle Anonymous Label (original line was just ":")
-    if trimmed_key.is_empty() {
-        return Some(Label::Anonymous);
-    }
-
-    // Handle double-quoted strings
-    if let Some(caps) = get_re_tag_label().captures(trimmed_key) {
-        let inner_content = caps.get(1).unwrap().as_str();
-
-        return Some(Label::Tag(inner_content.to_string()));
-    }
-
-    // Handle potential multi-element labels (Unicode or Codepoint)
-    let elements: Vec<&str> = trimmed_key.split(',').map(str::trim).collect();
-    if elements.is_empty() || elements.iter().any(|e| e.is_empty()) {
-        // This can happen if key_str was just "," or "elem1, ,elem2"
-        return None;
-    }
-
-    // Determine label type from first element and parse accordingly
-    let first_element = elements[0];
-
-    // Try Unicode (Character) Label first
-    if get_re_unicode_uplus_element().is_match(first_element)
-        || get_re_unicode_char_seq_element().is_match(first_element)
-    {
-        return parse_unicode_label_sequence(&elements);
-    }
-
-    // Try Codepoint Label
-    if get_re_codepoint_hex_element().is_match(first_element)
-        || get_re_codepoint_oct_element().is_match(first_element)
-        || get_re_codepoint_dec_element().is_match(first_element)
-    {
-        return parse_codepoint_label_sequence(&elements);
-    }
-
-    // Handle deprecated unquoted tags (single element only)
-    if elements.len() == 1 && get_re_unquoted_tag().is_match(first_element) {
-        return handle_deprecated_unquoted_tag(first_element, next_line_type);
-    }
-
-    None // Does not match any known label format (including previously matched unquoted tags)
-}
-
 fn parse_int_val(s: &str, line_num: usize, key_for_error: &str) -> Result<i32, ParseError> {
     s.trim()
         .parse::<i32>()
@@ -427,12 +340,12 @@ fn parse_kerning_map_val(
         let mut parts_iter = trimmed_line.rsplitn(2, char::is_whitespace);
         let val_str = parts_iter.next();
         let label_str = parts_iter.next();
-        if let (Some(label_s), Some(val_s)) = (label_str, val_str) {
-            if let Some(label) = parse_key_as_label(label_s, &LineType::default()) {
-                let val = parse_float_val(val_s, current_line_num, key_for_error)?;
-                map.insert(label, val);
-                continue;
-            }
+        if let (Some(label_s), Some(val_s)) = (label_str, val_str)
+            && let Some(label) = parse_key_as_label(label_s, &LineType::default())
+        {
+            let val = parse_float_val(val_s, current_line_num, key_for_error)?;
+            map.insert(label, val);
+            continue;
         }
         return Err(ParseError::InvalidPropertyValue {
             line: current_line_num,
@@ -453,10 +366,10 @@ fn parse_kerning_map_val(
 }
 
 /// Reads a property value from the lines iterator, handling both single-line and multi-line values.
-fn read_property_value_from_iter<'s, 'a: 's>(
-    lines_iter: &'s mut LineIterator<'a>,
+fn read_property_value_from_iter<R: BufRead>(
+    lines_iter: &mut LineIterator<R>,
     initial_key_line_num: usize,
-    initial_line_str: &'a str,
+    initial_line_str: &str,
     key_for_error: &str,
     colon_pos_on_key_line: usize,
 ) -> Result<String, ParseError> {
@@ -516,12 +429,48 @@ fn read_property_value_from_iter<'s, 'a: 's>(
     }
 }
 
+// --- Diagnostics ---
+struct Reporter {
+    mode: ParseMode,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Reporter {
+    fn new(mode: ParseMode) -> Self {
+        Reporter {
+            mode,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Records a problem, or fails if strict mode doesn't allow it.
+    fn report(
+        &mut self,
+        line: usize,
+        kind: DiagnosticKind,
+        message: String,
+    ) -> Result<(), ParseError> {
+        let mut diagnostic = Diagnostic::new(Severity::Warning, kind, line, message);
+        let refused = matches!(
+            kind,
+            DiagnosticKind::Deprecated | DiagnosticKind::UnknownProperty
+        );
+        if self.mode == ParseMode::Strict && refused {
+            diagnostic.severity = Severity::Error;
+            return Err(ParseError::Strict(diagnostic));
+        }
+        self.diagnostics.push(diagnostic);
+        Ok(())
+    }
+}
+
 // --- Setter Functions ---
 fn set_global_property(
     font: &mut YaffFont,
     key: String,
     value_str: String, // Renamed from value to value_str for clarity
     line_num: usize,
+    reporter: &mut Reporter,
 ) -> Result<(), ParseError> {
     match key.as_str() {
         "yaff" => font.yaff_version = Some(value_str),
@@ -637,9 +586,11 @@ fn set_global_property(
         "shift-up" => font.global_shift_up = Some(parse_int_val(&value_str, line_num, &key)?),
 
         "line-width" => {
-            log::warn!(
-                "Unsupported vertical metric property '{key}' on line {line_num}. This property is ignored."
-            );
+            reporter.report(
+                line_num,
+                DiagnosticKind::UnsupportedProperty,
+                format!("Unsupported vertical metric property '{key}' is ignored."),
+            )?;
         }
         "left-extent" | "right-extent" => {
             return Err(ParseError::UnsupportedFeature {
@@ -656,7 +607,11 @@ fn set_global_property(
             });
         }
         _ => {
-            log::warn!("Unknown global property: {key}");
+            reporter.report(
+                line_num,
+                DiagnosticKind::UnknownProperty,
+                format!("Unknown global property '{key}', discarding."),
+            )?;
         }
     }
     Ok(())
@@ -667,7 +622,22 @@ fn set_glyph_property(
     key: String,
     value: String, // Using `value` as per the snippet provided in the query
     line_num: usize,
+    reporter: &mut Reporter,
 ) -> Result<(), ParseError> {
+    let replacement = match key.as_str() {
+        "offset" => Some("left-bearing and shift-up"),
+        "tracking" => Some("right-bearing"),
+        "kern-to" => Some("right-kerning"),
+        _ => None,
+    };
+    if let Some(replacement) = replacement {
+        reporter.report(
+            line_num,
+            DiagnosticKind::Deprecated,
+            format!("Deprecated glyph property '{key}', use {replacement} instead."),
+        )?;
+    }
+
     match key.as_str() {
         // Standard per-glyph properties
         "left-bearing" => glyph.left_bearing = Some(parse_int_val(&value, line_num, &key)?),
@@ -716,44 +686,151 @@ fn set_glyph_property(
             });
         }
         _ => {
-            log::warn!(
-                "Line {line_num}: Unknown per-glyph property '{key}' with value '{value}', discarding."
-            );
+            reporter.report(
+                line_num,
+                DiagnosticKind::UnknownProperty,
+                format!("Unknown per-glyph property '{key}' with value '{value}', discarding."),
+            )?;
         }
     }
     Ok(())
 }
 
 // --- Line Iterator with Tracking ---
-struct LineIterator<'a> {
-    inner: std::iter::Peekable<std::str::Lines<'a>>,
+
+/// Number of consumed lines kept for locating diagnostics in the source.
+const LINE_HISTORY: usize = 1024;
+
+struct LineIterator<R: BufRead> {
+    reader: R,
+    peeked: Option<(usize, String)>, // (byte offset, content)
+    bytes_read: usize,
+    history: VecDeque<(usize, usize, String)>, // (line number, byte offset, content)
     current_line_number: usize,
+    at_eof: bool,
+    error: Option<std::io::Error>,
 }
 
-impl<'a> LineIterator<'a> {
-    fn new(s: &'a str) -> Self {
+impl<R: BufRead> LineIterator<R> {
+    fn new(reader: R) -> Self {
         Self {
-            inner: s.lines().peekable(),
+            reader,
+            peeked: None,
+            bytes_read: 0,
+            history: VecDeque::new(),
             current_line_number: 0,
+            at_eof: false,
+            error: None,
+        }
+    }
+
+    /// Reads the next line into the peek slot, stripping the line terminator
+    /// (and a leading byte order mark on the first line).
+    fn fill_peeked(&mut self) {
+        if self.peeked.is_some() || self.at_eof {
+            return;
+        }
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => self.at_eof = true,
+            Ok(len) => {
+                let mut offset = self.bytes_read;
+                self.bytes_read += len;
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                if self.current_line_number == 0
+                    && let Some(stripped) = line.strip_prefix('\u{FEFF}')
+                {
+                    line = stripped.to_string();
+                    offset += '\u{FEFF}'.len_utf8();
+                }
+                self.peeked = Some((offset, line));
+            }
+            Err(err) => {
+                self.at_eof = true;
+                self.error = Some(err);
+            }
         }
     }
 
-    fn next(&mut self) -> Option<(usize, &'a str)> {
-        self.inner.next().map(|line| {
+    fn next(&mut self) -> Option<(usize, String)> {
+        self.fill_peeked();
+        self.peeked.take().map(|(offset, line)| {
             self.current_line_number += 1;
+            if self.history.len() == LINE_HISTORY {
+                self.history.pop_front();
+            }
+            self.history
+                .push_back((self.current_line_number, offset, line.clone()));
             (self.current_line_number, line)
         })
     }
 
-    fn peek(&mut self) -> Option<(usize, &'a str)> {
-        self.inner
-            .peek()
-            .map(|&line| (self.current_line_number + 1, line))
+    fn peek(&mut self) -> Option<(usize, &str)> {
+        self.fill_peeked();
+        self.peeked
+            .as_ref()
+            .map(|(_, line)| (self.current_line_number + 1, line.as_str()))
+    }
+
+    /// Returns the byte offset and content of a recently read line.
+    fn source_line(&self, line_num: usize) -> Option<(usize, &str)> {
+        if line_num == self.current_line_number + 1 {
+            return self
+                .peeked
+                .as_ref()
+                .map(|(offset, line)| (*offset, line.as_str()));
+        }
+        self.history
+            .iter()
+            .rev()
+            .find(|(num, _, _)| *num == line_num)
+            .map(|(_, offset, line)| (*offset, line.as_str()))
+    }
+
+    /// Fills in the column, span and source line of a diagnostic. The span covers
+    /// `needle` if it occurs on the line, the property key for problems with a key,
+    /// and the whole line content otherwise.
+    fn locate(&self, diagnostic: &mut Diagnostic, needle: Option<&str>) {
+        let Some((offset, text)) = self.source_line(diagnostic.line) else {
+            return;
+        };
+        let indent = text.len() - text.trim_start().len();
+        let content = text[indent..].trim_end();
+        let key_kinds = matches!(
+            diagnostic.kind,
+            DiagnosticKind::Deprecated
+                | DiagnosticKind::UnknownProperty
+                | DiagnosticKind::UnsupportedProperty
+        );
+        let found: Option<Range<usize>> = match needle {
+            Some(needle) if !needle.is_empty() => content
+                .find(needle)
+                .map(|start| start..start + needle.len()),
+            _ if key_kinds => content
+                .find(':')
+                .map(|end| 0..content[..end].trim_end().len()),
+            _ => None,
+        };
+        let range = found.unwrap_or(0..content.len());
+        let range = indent + range.start..indent + range.end;
+        diagnostic.column = text[..range.start].chars().count() + 1;
+        diagnostic.span = offset + range.start..offset + range.end;
+        diagnostic.source_line = text.to_string();
     }
 
     fn current_line_number(&self) -> usize {
         self.current_line_number
     }
+
+    /// Returns the I/O error that ended the input early, if any.
+    fn take_error(&mut self) -> Option<std::io::Error> {
+        self.error.take()
+    }
 }
 
 // --- Parser State Machine ---
@@ -783,25 +860,39 @@ enum ParserState {
 // D (ParsingGlyphProps) → B (AccumulatingLabels):
 //   - When new glyph labels start at indent 0 (commits current glyph)
 
-struct Decoder<'a> {
-    lines_iter: LineIterator<'a>,
+struct Decoder<R: BufRead> {
+    lines_iter: LineIterator<R>,
     font: YaffFont,
     state: ParserState,
+    finished: bool,
+    ready_glyphs: VecDeque<GlyphDefinition>,
+    reporter: Reporter,
+    recovery: Recovery,
+    located_diagnostics: usize,
+    // When set, errors are recorded and parsing resumes after the broken part
+    collect_errors: bool,
+    errors: Vec<(ParseError, Diagnostic)>,
 
     // State-specific data
     pending_labels: Vec<Label>,
     current_glyph_being_built: Option<GlyphDefinition>, // Holds glyph during C and D states
-    current_glyph_bitmap_lines: Vec<String>,
+    current_glyph_bitmap_lines: Vec<(usize, String)>,   // (line number, content)
     current_glyph_expected_indent: Option<usize>,
 }
 
-impl<'a> Decoder<'a> {
-    fn new(s: &'a str) -> Self {
-        let content = s.strip_prefix('\u{FEFF}').unwrap_or(s);
+impl<R: BufRead> Decoder<R> {
+    fn new(reader: R, options: &ParseOptions) -> Self {
         Decoder {
-            lines_iter: LineIterator::new(content),
+            lines_iter: LineIterator::new(reader),
             font: YaffFont::new(),
             state: ParserState::GlobalProps,
+            finished: false,
+            ready_glyphs: VecDeque::new(),
+            reporter: Reporter::new(options.mode),
+            recovery: options.recovery,
+            located_diagnostics: 0,
+            collect_errors: false,
+            errors: Vec::new(),
             pending_labels: Vec::new(),
             current_glyph_being_built: None,
             current_glyph_bitmap_lines: Vec::new(),
@@ -809,7 +900,7 @@ impl<'a> Decoder<'a> {
         }
     }
 
-    fn next_significant_line(&mut self) -> Option<(usize, LineType, usize, &'a str)> {
+    fn next_significant_line(&mut self) -> Option<(usize, LineType, usize, String)> {
         while let Some((line_num, line_str)) = self.lines_iter.peek() {
             let (line_type, indent) = classify_line(line_str);
             match line_type {
@@ -819,7 +910,7 @@ impl<'a> Decoder<'a> {
                 }
                 _ => {
                     // Found a significant line
-                    return Some((line_num, line_type, indent, line_str));
+                    return Some((line_num, line_type, indent, line_str.to_string()));
                 }
             }
         }
@@ -830,100 +921,295 @@ impl<'a> Decoder<'a> {
         self.lines_iter.next();
     }
 
+    fn emit_glyph(&mut self, glyph: GlyphDefinition) {
+        self.ready_glyphs.push_back(glyph);
+    }
+
     fn commit_current_glyph(&mut self) {
         if let Some(glyph) = self.current_glyph_being_built.take() {
-            self.font.glyphs.push(glyph);
+            self.emit_glyph(glyph);
         }
         self.current_glyph_bitmap_lines.clear();
         self.current_glyph_expected_indent = None;
         // pending_labels should have been cleared when glyph construction started
     }
 
-    fn parse_internal(mut self) -> Result<YaffFont, ParseError> {
+    fn parse_internal(mut self) -> Result<(YaffFont, Vec<Diagnostic>), ParseError> {
+        self.collect_errors = true;
+        while let Some(glyph) = self.next_glyph()? {
+            self.font.glyphs.push(glyph);
+        }
+        if self.errors.len() > 1 {
+            let diagnostics = self.errors.into_iter().map(|(_, d)| d).collect();
+            return Err(ParseError::Multiple(diagnostics));
+        }
+        if let Some((err, _)) = self.errors.pop() {
+            return Err(err);
+        }
+        Ok((self.font, self.reporter.diagnostics))
+    }
+
+    /// Reports a label that was only recognised through the deprecated unquoted tag form.
+    fn check_unquoted_tag(
+        &mut self,
+        key_str: &str,
+        label: &Option<Label>,
+        line_num: usize,
+    ) -> Result<(), ParseError> {
+        if let Some(Label::Tag(tag)) = label
+            && !key_str.trim_start().starts_with('"')
+        {
+            self.reporter.report(
+                line_num,
+                DiagnosticKind::Deprecated,
+                format!("Deprecated unquoted tag '{tag}', write \"{tag}\" instead."),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Processes lines until the first glyph label is reached.
+    fn parse_global_properties(&mut self) -> Result<(), ParseError> {
+        while self.state == ParserState::GlobalProps && !self.finished {
+            if let Err(err) = self.advance() {
+                self.handle_error(err)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Processes lines until the next glyph is complete. Returns `None` at the end
+    /// of the input. After an error, no further glyphs are produced unless errors
+    /// are being collected or recovered from.
+    fn next_glyph(&mut self) -> Result<Option<GlyphDefinition>, ParseError> {
+        loop {
+            if let Some(glyph) = self.ready_glyphs.pop_front() {
+                return Ok(Some(glyph));
+            }
+            if self.finished {
+                return Ok(None);
+            }
+            if let Err(err) = self.advance() {
+                self.handle_error(err)?;
+            }
+        }
+    }
+
+    /// Records a recoverable error and skips past it, or stops parsing.
+    fn handle_error(&mut self, err: ParseError) -> Result<(), ParseError> {
+        if matches!(err, ParseError::Io(_)) {
+            self.finished = true;
+            self.ready_glyphs.clear();
+            return Err(err);
+        }
+        let diagnostic = self.error_diagnostic(&err);
+        self.resync(diagnostic.line);
+        if self.recovery != Recovery::Fail {
+            self.reporter.diagnostics.push(diagnostic);
+            self.located_diagnostics = self.reporter.diagnostics.len();
+        } else if self.collect_errors {
+            self.errors.push((err, diagnostic));
+        } else {
+            self.finished = true;
+            self.ready_glyphs.clear();
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Handles the next line, or finishes at the end of the input.
+    fn advance(&mut self) -> Result<(), ParseError> {
+        let result = match self.step() {
+            Ok(true) => Ok(()),
+            Ok(false) => self.finish(),
+            Err(err) => Err(err),
+        };
+        // Locate new diagnostics while their lines are still in the history
+        for diagnostic in &mut self.reporter.diagnostics[self.located_diagnostics..] {
+            self.lines_iter.locate(diagnostic, None);
+        }
+        self.located_diagnostics = self.reporter.diagnostics.len();
+        result
+    }
+
+    /// Skips the rest of a broken property or glyph: the lines up to the one the
+    /// error was reported on and any indented lines after it. Parsing resumes at
+    /// the next top-level line, which is a property or a label.
+    fn resync(&mut self, error_line: usize) {
+        while let Some((line_num, line_str)) = self.lines_iter.peek() {
+            let (line_type, indent) = classify_line(line_str);
+            if line_num <= error_line || line_type == LineType::Empty || indent > 0 {
+                self.lines_iter.next();
+            } else {
+                break;
+            }
+        }
+        self.pending_labels.clear();
+        self.current_glyph_being_built = None;
+        self.current_glyph_bitmap_lines.clear();
+        self.current_glyph_expected_indent = None;
+        if self.state != ParserState::GlobalProps {
+            self.state = ParserState::AccumulatingLabels;
+        }
+    }
+
+    /// Describes a parse error as a located diagnostic.
+    fn error_diagnostic(&self, err: &ParseError) -> Diagnostic {
+        let (kind, line, message, needle) = match err {
+            ParseError::Strict(diagnostic) => {
+                let mut diagnostic = diagnostic.clone();
+                self.lines_iter.locate(&mut diagnostic, None);
+                return diagnostic;
+            }
+            ParseError::Io(err) => (DiagnosticKind::Io, 0, err.to_string(), None),
+            ParseError::InvalidSyntax { line, message } => {
+                (DiagnosticKind::InvalidSyntax, *line, message.clone(), None)
+            }
+            ParseError::UnexpectedEndOfInput => (
+                DiagnosticKind::UnexpectedEndOfInput,
+                self.lines_iter.current_line_number(),
+                "Unexpected end of input.".to_string(),
+                None,
+            ),
+            ParseError::SemanticError { line, message } => {
+                (DiagnosticKind::SemanticError, *line, message.clone(), None)
+            }
+            ParseError::InvalidPropertyValue {
+                line,
+                property_key,
+                value,
+                expected_format,
+            } => (
+                DiagnosticKind::InvalidPropertyValue,
+                *line,
+                format!(
+                    "Invalid value '{value}' for property '{property_key}'. Expected: {expected_format}"
+                ),
+                Some(value.trim().to_string()),
+            ),
+            ParseError::UnsupportedFeature {
+                line,
+                feature_name,
+                message,
+            } => (
+                DiagnosticKind::UnsupportedFeature,
+                *line,
+                format!("Unsupported feature '{feature_name}': {message}"),
+                Some(feature_name.clone()),
+            ),
+            ParseError::InconsistentGlyphLineLength { line } => (
+                DiagnosticKind::InconsistentGlyphLineLength,
+                *line,
+                "Bitmap line is longer than the first line of the glyph.".to_string(),
+                None,
+            ),
+            ParseError::InvalidGlyphCharacter { line, char_found } => (
+                DiagnosticKind::InvalidGlyphCharacter,
+                *line,
+                format!("Invalid glyph character '{char_found}'."),
+                Some(char_found.to_string()),
+            ),
+            ParseError::Multiple(_) => (DiagnosticKind::SemanticError, 0, err.to_string(), None),
+        };
+        let mut diagnostic = Diagnostic::new(Severity::Error, kind, line, message);
+        self.lines_iter.locate(&mut diagnostic, needle.as_deref());
+        diagnostic
+    }
+
+    /// Handles the next significant line. Returns `false` at the end of the input.
+    fn step(&mut self) -> Result<bool, ParseError> {
         // This is way more complicated than it should be. The spec requires this complexity. :(
-        while let Some((line_num, line_type, indent, line_str_raw)) = self.next_significant_line() {
-            // The line_str_raw is the peeked line. We consume it only if the current state handles it.
-            // If a state transitions without consuming, the next state will re-evaluate this same line.
-
-            match self.state {
-                ParserState::GlobalProps => {
-                    match line_type {
-                        LineType::KeyValue {
-                            key: key_str,
-                            value_on_line,
-                        } if indent == 0 => {
-                            self.consume_line();
-                            let value_on_line2 = value_on_line.clone();
+        let Some((line_num, line_type, indent, line_str_raw)) = self.next_significant_line() else {
+            return Ok(false);
+        };
+        // The line_str_raw is the peeked line. We consume it only if the current state handles it.
+        // If a state transitions without consuming, the next state will re-evaluate this same line.
+        let line_str_raw = line_str_raw.as_str();
+        match self.state {
+            ParserState::GlobalProps => {
+                match line_type {
+                    LineType::KeyValue {
+                        key: key_str,
+                        value_on_line,
+                    } if indent == 0 => {
+                        self.consume_line();
+                        let value_on_line2 = value_on_line.clone();
+                        if value_on_line2.is_none() {
+                            // Only a key without a value can be a label
                             let next_line_type = self
                                 .lines_iter
                                 .peek()
                                 .map(|(_, s)| classify_line(s).0)
                                 .unwrap_or_default();
                             let label = parse_key_as_label(&key_str, &next_line_type);
-                            if value_on_line2.is_none() {
-                                if let Some(new_label) = label {
-                                    // It's a label
-                                    if !self.pending_labels.contains(&new_label) {
-                                        self.pending_labels.push(new_label);
-                                    }
-                                    self.state = ParserState::AccumulatingLabels;
-                                } else {
-                                    // It's a global property with no value on the same line
-                                    let value_to_set = read_property_value_from_iter(
-                                        &mut self.lines_iter,
-                                        line_num,
-                                        line_str_raw,
-                                        &key_str,
-                                        line_str_raw.find(':').unwrap_or(line_str_raw.len()),
-                                    )?;
-                                    if value_to_set.is_empty() {
-                                        return Err(ParseError::InvalidSyntax {
-                                            line: line_num,
-                                            message: format!(
-                                                "Multi-line property '{key_str}' expects at least one continuation line."
-                                            ),
-                                        });
-                                    }
-                                    set_global_property(
-                                        &mut self.font,
-                                        key_str,
-                                        value_to_set,
-                                        line_num,
-                                    )?;
+                            self.check_unquoted_tag(&key_str, &label, line_num)?;
+                            if let Some(new_label) = label {
+                                // It's a label
+                                if !self.pending_labels.contains(&new_label) {
+                                    self.pending_labels.push(new_label);
                                 }
+                                self.state = ParserState::AccumulatingLabels;
                             } else {
-                                // It's a global property with value on the same line
-                                let value_to_set = value_on_line.unwrap();
+                                // It's a global property with no value on the same line
+                                let value_to_set = read_property_value_from_iter(
+                                    &mut self.lines_iter,
+                                    line_num,
+                                    line_str_raw,
+                                    &key_str,
+                                    line_str_raw.find(':').unwrap_or(line_str_raw.len()),
+                                )?;
+                                if value_to_set.is_empty() {
+                                    return Err(ParseError::InvalidSyntax {
+                                        line: line_num,
+                                        message: format!(
+                                            "Multi-line property '{key_str}' expects at least one continuation line."
+                                        ),
+                                    });
+                                }
                                 set_global_property(
                                     &mut self.font,
                                     key_str,
                                     value_to_set,
                                     line_num,
+                                    &mut self.reporter,
                                 )?;
                             }
+                        } else {
+                            // It's a global property with value on the same line
+                            let value_to_set = value_on_line.unwrap();
+                            set_global_property(
+                                &mut self.font,
+                                key_str,
+                                value_to_set,
+                                line_num,
+                                &mut self.reporter,
+                            )?;
                         }
-                        _ => {
-                            return Err(ParseError::InvalidSyntax {
-                                line: line_num,
-                                message: "Expected unindented global property or first label."
-                                    .to_string(),
-                            });
-                        }
+                    }
+                    _ => {
+                        return Err(ParseError::InvalidSyntax {
+                            line: line_num,
+                            message: "Expected unindented global property or first label."
+                                .to_string(),
+                        });
                     }
                 }
-                ParserState::AccumulatingLabels => match line_type {
+            }
+            ParserState::AccumulatingLabels => {
+                match line_type {
                     LineType::KeyValue {
                         key: key_str,
                         value_on_line,
                     } if indent == 0 => {
                         self.consume_line();
-                        let next_line_type = self
-                            .lines_iter
-                            .peek()
-                            .map(|(_, s)| classify_line(s).0)
-                            .unwrap_or_default();
-                        let label = parse_key_as_label(&key_str, &next_line_type);
                         if value_on_line.is_none() {
+                            let next_line_type = self
+                                .lines_iter
+                                .peek()
+                                .map(|(_, s)| classify_line(s).0)
+                                .unwrap_or_default();
+                            let label = parse_key_as_label(&key_str, &next_line_type);
+                            self.check_unquoted_tag(&key_str, &label, line_num)?;
                             if let Some(new_label) = label {
                                 if !self.pending_labels.contains(&new_label) {
                                     self.pending_labels.push(new_label);
@@ -953,7 +1239,7 @@ impl<'a> Decoder<'a> {
                             self.current_glyph_being_built = Some(glyph);
                             self.state = ParserState::ParsingGlyphProps;
                         } else {
-                            self.current_glyph_bitmap_lines.push(content);
+                            self.current_glyph_bitmap_lines.push((line_num, content));
                             self.state = ParserState::ParsingBitmap;
                         }
                     }
@@ -963,136 +1249,153 @@ impl<'a> Decoder<'a> {
                             message: "Expected more labels or bitmap data.".to_string(),
                         });
                     }
-                },
-                ParserState::ParsingBitmap => {
-                    let expected_indent = self.current_glyph_expected_indent.unwrap();
-                    match line_type {
-                        LineType::BitmapLine { content } if indent == expected_indent => {
-                            self.consume_line();
-                            self.current_glyph_bitmap_lines.push(content);
+                }
+            }
+            ParserState::ParsingBitmap => {
+                let expected_indent = self.current_glyph_expected_indent.unwrap();
+                match line_type {
+                    LineType::BitmapLine { content } if indent == expected_indent => {
+                        self.consume_line();
+                        self.current_glyph_bitmap_lines.push((line_num, content));
+                    }
+                    LineType::KeyValue {
+                        key: key_str,
+                        value_on_line,
+                    } if indent == expected_indent => {
+                        // End of bitmap, start of per-glyph properties
+                        let bitmap = self.finalize_bitmap(line_num)?;
+                        let mut glyph = GlyphDefinition {
+                            labels: std::mem::take(&mut self.pending_labels),
+                            bitmap,
+                            ..Default::default()
+                        };
+                        // Apply this first property
+                        self.consume_line();
+                        let value_on_line2 = value_on_line.clone();
+                        let value_to_set = if let Some(val_on_line) = value_on_line {
+                            val_on_line
+                        } else {
+                            read_property_value_from_iter(
+                                &mut self.lines_iter,
+                                line_num,
+                                line_str_raw,
+                                &key_str,
+                                line_str_raw.find(':').unwrap_or(line_str_raw.len()),
+                            )?
+                        };
+                        if value_to_set.is_empty() && value_on_line2.is_none() {
+                            return Err(ParseError::InvalidSyntax {
+                                line: line_num,
+                                message: format!(
+                                    "Multi-line glyph property '{key_str}' expects at least one continuation line."
+                                ),
+                            });
                         }
-                        LineType::KeyValue {
-                            key: key_str,
-                            value_on_line,
-                        } if indent == expected_indent => {
-                            // End of bitmap, start of per-glyph properties
-                            let bitmap = self.finalize_bitmap(line_num)?;
-                            let mut glyph = GlyphDefinition {
-                                labels: std::mem::take(&mut self.pending_labels),
-                                bitmap,
-                                ..Default::default()
-                            };
-                            // Apply this first property
-                            self.consume_line();
-                            let value_on_line2 = value_on_line.clone();
-                            let value_to_set = if let Some(val_on_line) = value_on_line {
-                                val_on_line
-                            } else {
-                                read_property_value_from_iter(
-                                    &mut self.lines_iter,
-                                    line_num,
-                                    line_str_raw,
-                                    &key_str,
-                                    line_str_raw.find(':').unwrap_or(line_str_raw.len()),
-                                )?
-                            };
-                            if value_to_set.is_empty() && value_on_line2.is_none() {
-                                return Err(ParseError::InvalidSyntax {
-                                    line: line_num,
-                                    message: format!(
-                                        "Multi-line glyph property '{key_str}' expects at least one continuation line."
-                                    ),
-                                });
-                            }
-                            set_glyph_property(&mut glyph, key_str, value_to_set, line_num)?;
-                            self.current_glyph_being_built = Some(glyph);
-                            self.state = ParserState::ParsingGlyphProps;
+                        set_glyph_property(
+                            &mut glyph,
+                            key_str,
+                            value_to_set,
+                            line_num,
+                            &mut self.reporter,
+                        )?;
+                        self.current_glyph_being_built = Some(glyph);
+                        self.state = ParserState::ParsingGlyphProps;
+                    }
+                    LineType::KeyValue { key: _, .. } if indent == 0 => {
+                        // New label
+                        let bitmap = self.finalize_bitmap(line_num)?;
+                        let bitmap2 = bitmap.clone();
+                        let glyph_to_commit = GlyphDefinition {
+                            labels: std::mem::take(&mut self.pending_labels),
+                            bitmap,
+                            ..Default::default()
+                        };
+                        if let Some(mut existing_glyph) = self.current_glyph_being_built.take() {
+                            existing_glyph.bitmap = bitmap2;
+                            self.emit_glyph(existing_glyph);
+                        } else {
+                            self.emit_glyph(glyph_to_commit);
                         }
-                        LineType::KeyValue { key: _, .. } if indent == 0 => {
-                            // New label
-                            let bitmap = self.finalize_bitmap(line_num)?;
-                            let bitmap2 = bitmap.clone();
-                            let glyph_to_commit = GlyphDefinition {
-                                labels: std::mem::take(&mut self.pending_labels),
-                                bitmap,
-                                ..Default::default()
-                            };
-                            if let Some(mut existing_glyph) = self.current_glyph_being_built.take()
-                            {
-                                existing_glyph.bitmap = bitmap2;
-                                self.font.glyphs.push(existing_glyph);
-                            } else {
-                                self.font.glyphs.push(glyph_to_commit);
-                            }
 
-                            self.state = ParserState::AccumulatingLabels;
-                        }
-                        _ => {
-                            // Any other line type or indent mismatch ends bitmap
-                            let bitmap = self.finalize_bitmap(line_num)?;
-                            let glyph_to_commit = GlyphDefinition {
-                                labels: std::mem::take(&mut self.pending_labels),
-                                bitmap,
-                                ..Default::default()
-                            };
-                            self.current_glyph_being_built = Some(glyph_to_commit);
-                            self.state = ParserState::ParsingGlyphProps;
-                        }
+                        self.state = ParserState::AccumulatingLabels;
+                    }
+                    _ => {
+                        // Any other line type or indent mismatch ends bitmap
+                        let bitmap = self.finalize_bitmap(line_num)?;
+                        let glyph_to_commit = GlyphDefinition {
+                            labels: std::mem::take(&mut self.pending_labels),
+                            bitmap,
+                            ..Default::default()
+                        };
+                        self.current_glyph_being_built = Some(glyph_to_commit);
+                        self.state = ParserState::ParsingGlyphProps;
                     }
                 }
-                ParserState::ParsingGlyphProps => {
-                    let expected_indent = self.current_glyph_expected_indent.unwrap();
-                    match line_type {
-                        LineType::KeyValue {
-                            key: key_str,
-                            value_on_line,
-                        } if indent == expected_indent => {
-                            self.consume_line();
-                            let glyph =
-                                self.current_glyph_being_built.as_mut().ok_or_else(|| {
-                                    ParseError::SemanticError {
-                                        line: line_num,
-                                        message:
-                                            "Internal error: no glyph being built for properties"
-                                                .to_string(),
-                                    }
-                                })?;
-                            let value_on_line2 = value_on_line.clone();
-                            let value_to_set = if let Some(val_on_line) = value_on_line {
-                                val_on_line
-                            } else {
-                                read_property_value_from_iter(
-                                    &mut self.lines_iter,
-                                    line_num,
-                                    line_str_raw,
-                                    &key_str,
-                                    line_str_raw.find(':').unwrap_or(line_str_raw.len()),
-                                )?
-                            };
-                            if value_to_set.is_empty() && value_on_line2.is_none() {
-                                return Err(ParseError::InvalidSyntax {
-                                    line: line_num,
-                                    message: format!(
-                                        "Multi-line glyph property '{key_str}' expects at least one continuation line."
-                                    ),
-                                });
+            }
+            ParserState::ParsingGlyphProps => {
+                let expected_indent = self.current_glyph_expected_indent.unwrap();
+                match line_type {
+                    LineType::KeyValue {
+                        key: key_str,
+                        value_on_line,
+                    } if indent == expected_indent => {
+                        self.consume_line();
+                        let glyph = self.current_glyph_being_built.as_mut().ok_or_else(|| {
+                            ParseError::SemanticError {
+                                line: line_num,
+                                message: "Internal error: no glyph being built for properties"
+                                    .to_string(),
                             }
-                            set_glyph_property(glyph, key_str, value_to_set, line_num)?;
-                        }
-                        LineType::KeyValue { key: _, .. } if indent == 0 => {
-                            // New label
-                            self.commit_current_glyph();
-                            self.state = ParserState::AccumulatingLabels;
-                        }
-                        _ => {
-                            self.commit_current_glyph();
-                            self.state = ParserState::AccumulatingLabels;
+                        })?;
+                        let value_on_line2 = value_on_line.clone();
+                        let value_to_set = if let Some(val_on_line) = value_on_line {
+                            val_on_line
+                        } else {
+                            read_property_value_from_iter(
+                                &mut self.lines_iter,
+                                line_num,
+                                line_str_raw,
+                                &key_str,
+                                line_str_raw.find(':').unwrap_or(line_str_raw.len()),
+                            )?
+                        };
+                        if value_to_set.is_empty() && value_on_line2.is_none() {
+                            return Err(ParseError::InvalidSyntax {
+                                line: line_num,
+                                message: format!(
+                                    "Multi-line glyph property '{key_str}' expects at least one continuation line."
+                                ),
+                            });
                         }
+                        set_glyph_property(
+                            glyph,
+                            key_str,
+                            value_to_set,
+                            line_num,
+                            &mut self.reporter,
+                        )?;
+                    }
+                    LineType::KeyValue { key: _, .. } if indent == 0 => {
+                        // New label
+                        self.commit_current_glyph();
+                        self.state = ParserState::AccumulatingLabels;
+                    }
+                    _ => {
+                        self.commit_current_glyph();
+                        self.state = ParserState::AccumulatingLabels;
                     }
                 }
             }
         }
+        Ok(true)
+    }
 
+    /// Commits the glyph in progress at the end of the input.
+    fn finish(&mut self) -> Result<(), ParseError> {
+        self.finished = true;
+        if let Some(err) = self.lines_iter.take_error() {
+            return Err(ParseError::Io(err));
+        }
         match self.state {
             ParserState::GlobalProps => {}
             ParserState::AccumulatingLabels => {
@@ -1108,18 +1411,18 @@ impl<'a> Decoder<'a> {
                         bitmap,
                         ..Default::default()
                     };
-                    self.font.glyphs.push(glyph_to_commit);
+                    self.emit_glyph(glyph_to_commit);
                 } else if !self.pending_labels.is_empty() {
                     return Err(ParseError::UnexpectedEndOfInput);
                 }
             }
             ParserState::ParsingGlyphProps => {
                 if let Some(glyph) = self.current_glyph_being_built.take() {
-                    self.font.glyphs.push(glyph);
+                    self.emit_glyph(glyph);
                 }
             }
         }
-        Ok(self.font)
+        Ok(())
     }
 
     fn finalize_bitmap(&mut self, error_line_num: usize) -> Result<Bitmap, ParseError> {
@@ -1138,27 +1441,32 @@ impl<'a> Decoder<'a> {
             return Ok(Bitmap::default());
         }
 
-        let expected_width = if lines_data[0].is_empty() {
-            0
-        } else {
-            lines_data[0].len()
-        };
+        let expected_width = lines_data[0].1.len();
         let mut pixels_matrix: Vec<Vec<bool>> = Vec::new();
 
-        for (i, line_data) in lines_data.iter().enumerate() {
+        for (line_num, line_data) in &lines_data {
+            let line_num = *line_num;
             let line_data = if line_data.len() < expected_width {
                 // We are okay with shorter lines, we pad with '.'
-                let line_num = error_line_num - lines_data.len() + i;
-                log::warn!(
-                    "Line {line_num}: Bitmap line is shorter than expected width."
-                );
+                self.reporter.report(
+                    line_num,
+                    DiagnosticKind::ShortBitmapLine,
+                    "Bitmap line is shorter than expected width.".to_string(),
+                )?;
                 let mut line_data = line_data.to_string();
                 line_data.push_str(&".".repeat(expected_width - line_data.len()));
                 line_data
             } else if line_data.len() > expected_width {
-                return Err(ParseError::InconsistentGlyphLineLength {
-                    line: error_line_num - lines_data.len() + i,
-                });
+                if self.recovery != Recovery::Repair {
+                    return Err(ParseError::InconsistentGlyphLineLength { line: line_num });
+                }
+                self.reporter.report(
+                    line_num,
+                    DiagnosticKind::InconsistentGlyphLineLength,
+                    "Bitmap line is longer than the first line of the glyph, truncating."
+                        .to_string(),
+                )?;
+                line_data.chars().take(expected_width).collect()
             } else {
                 line_data.to_string()
             };
@@ -1169,7 +1477,7 @@ impl<'a> Decoder<'a> {
                     '@' => pixel_row.push(true),
                     _ => {
                         return Err(ParseError::InvalidGlyphCharacter {
-                            line: error_line_num - lines_data.len() + i,
+                            line: line_num,
                             char_found: char_c,
                         });
                     }
@@ -1544,6 +1852,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_codepoint_label_multi_byte() {
+        assert_eq!(
+            parse_key_as_label("0x8140", &LineType::default()),
+            Some(Label::Codepoint(vec![0x81, 0x40]))
+        );
+        assert_eq!(
+            parse_key_as_label("0x81, 0x40", &LineType::default()),
+            Some(Label::Codepoint(vec![0x81, 0x40]))
+        );
+        assert_eq!(
+            parse_key_as_label("33088", &LineType::default()),
+            Some(Label::Codepoint(vec![0x81, 0x40]))
+        );
+        assert_eq!(
+            parse_key_as_label("0x0041", &LineType::default()),
+            Some(Label::Codepoint(vec![0x41]))
+        );
+    }
+
     #[test]
     fn test_quotes() {
         assert_eq!(
@@ -1753,4 +2081,226 @@ mod tests {
         assert_eq!(kerning_map.len(), 1);
         assert_eq!(kerning_map[&Label::Tag("space".to_string())], -1.0);
     }
+
+    #[test]
+    fn test_streaming_reader() {
+        let yaff = "name: Stream\r\nascent: 2\r\n\r\n'A':\r\n  .@\r\n  @@\r\n\r\n'B':\r\n  -\r\n";
+        let mut reader =
+            YaffReader::new(std::io::BufReader::with_capacity(4, yaff.as_bytes())).unwrap();
+        assert_eq!(reader.properties().name.as_deref(), Some("Stream"));
+        assert_eq!(reader.properties().ascent, Some(2));
+        assert!(reader.properties().glyphs.is_empty());
+
+        let first = reader.next().unwrap().unwrap();
+        assert_eq!(first.labels, vec![Label::Unicode(vec!['A' as u32])]);
+        assert_eq!(
+            first.bitmap.pixels,
+            vec![vec![false, true], vec![true, true]]
+        );
+        let second = reader.next().unwrap().unwrap();
+        assert_eq!(second.labels, vec![Label::Unicode(vec!['B' as u32])]);
+        assert!(second.bitmap.is_empty());
+        assert!(reader.next().is_none());
+
+        let font = from_str(yaff).unwrap();
+        assert_eq!(font.glyphs, vec![first, second]);
+    }
+
+    #[test]
+    fn test_streaming_reader_reports_line_and_stops() {
+        let yaff = "\u{FEFF}name: Broken\n\n'A':\n  @@\n  @@@\n\n'B':\n  @\n";
+        let mut reader = YaffReader::new(yaff.as_bytes()).unwrap();
+        assert_eq!(reader.properties().name.as_deref(), Some("Broken"));
+        let streamed_line = match reader.next() {
+            Some(Err(ParseError::InconsistentGlyphLineLength { line })) => line,
+            other => panic!("Expected InconsistentGlyphLineLength, got {other:?}"),
+        };
+        assert_eq!(streamed_line, 5);
+        assert!(reader.next().is_none());
+
+        match from_str(yaff) {
+            Err(ParseError::InconsistentGlyphLineLength { line }) => {
+                assert_eq!(line, streamed_line)
+            }
+            other => panic!("Expected InconsistentGlyphLineLength, got {other:?}"),
+        }
+    }
+
+    const DIAGNOSTICS_INPUT: &str = "name: Test
+unknown-key: value
+line-width: 8
+
+space:
+  ...
+  @
+
+  tracking: 1
+  mystery: 2
+";
+
+    #[test]
+    fn test_lenient_mode_collects_diagnostics() {
+        let (font, diagnostics) =
+            from_str_with_options(DIAGNOSTICS_INPUT, &ParseOptions::default()).unwrap();
+        assert_eq!(font.glyphs[0].labels, vec![Label::Tag("space".to_string())]);
+        assert_eq!(font.glyphs[0].right_bearing, Some(1));
+
+        let summary: Vec<(usize, DiagnosticKind)> =
+            diagnostics.iter().map(|d| (d.line, d.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, DiagnosticKind::UnknownProperty),
+                (3, DiagnosticKind::UnsupportedProperty),
+                (5, DiagnosticKind::Deprecated),
+                (7, DiagnosticKind::ShortBitmapLine),
+                (9, DiagnosticKind::Deprecated),
+                (10, DiagnosticKind::UnknownProperty),
+            ]
+        );
+        assert_eq!(from_str(DIAGNOSTICS_INPUT).unwrap(), font);
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_deprecations_and_unknown_keys() {
+        let strict = ParseOptions {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+        match from_str_with_options(DIAGNOSTICS_INPUT, &strict) {
+            Err(ParseError::Multiple(diagnostics)) => {
+                let summary: Vec<(usize, DiagnosticKind)> =
+                    diagnostics.iter().map(|d| (d.line, d.kind)).collect();
+                assert_eq!(
+                    summary,
+                    vec![
+                        (2, DiagnosticKind::UnknownProperty),
+                        (5, DiagnosticKind::Deprecated),
+                    ]
+                );
+                assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+            }
+            other => panic!("Expected strict mode errors, got {other:?}"),
+        }
+
+        let unquoted_tag = "space:\n  -\n";
+        assert!(matches!(
+            from_str_with_options(unquoted_tag, &strict),
+            Err(ParseError::Strict(Diagnostic {
+                line: 1,
+                kind: DiagnosticKind::Deprecated,
+                ..
+            }))
+        ));
+
+        // Problems that aren't deprecations or unknown keys are still only recorded
+        let short_line = "name: Test\n\n'A':\n  @@\n  @\n";
+        let (_, diagnostics) = from_str_with_options(short_line, &strict).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::ShortBitmapLine);
+
+        // A property directly followed by the first label isn't an unquoted tag
+        for input in ["name: x\n0x41:\n    @.\n", "ascent: 7\n'A':\n    @.\n"] {
+            let (font, diagnostics) = from_str_with_options(input, &strict).unwrap();
+            assert!(diagnostics.is_empty());
+            assert_eq!(font.glyphs.len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_reports_all_errors_with_locations() {
+        let input = "name: Test\nascent: big\n\nu+0041:\n  @x\n  @@\n\nu+0042:\n  @@\n  @@@\n\nu+0043:\n  @\n";
+        let err = from_str(input).unwrap_err();
+        let ParseError::Multiple(diagnostics) = err else {
+            panic!("Expected multiple errors, got {err:?}");
+        };
+        let summary: Vec<(usize, usize, DiagnosticKind)> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.column, d.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, 9, DiagnosticKind::InvalidPropertyValue),
+                (5, 3, DiagnosticKind::InvalidSyntax),
+                (10, 3, DiagnosticKind::InconsistentGlyphLineLength),
+            ]
+        );
+
+        let invalid_value = &diagnostics[0];
+        assert_eq!(invalid_value.source_line, "ascent: big");
+        assert_eq!(&input[invalid_value.span.clone()], "big");
+        assert_eq!(invalid_value.code(), "invalid-property-value");
+        assert_eq!(&input[diagnostics[1].span.clone()], "@x");
+
+        assert_eq!(
+            diagnostics[1].render(Some("font.yaff")),
+            "error[invalid-syntax]: Expected more labels or bitmap data.\n --> font.yaff:5:3\n  |\n5 |   @x\n  |   ^^\n"
+        );
+    }
+
+    #[test]
+    fn test_single_error_is_returned_unchanged() {
+        let input = "name: Test\n\nu+0041:\n  @@\n  @@@\n\nu+0042:\n  @\n";
+        assert!(matches!(
+            from_str(input),
+            Err(ParseError::InconsistentGlyphLineLength { line: 5 })
+        ));
+
+        // The streaming reader still stops at the first error
+        let mut reader = YaffReader::new(input.as_bytes()).unwrap();
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_recovery_skips_or_repairs_broken_glyphs() {
+        let input = "name: Test\nascent: big\n\nu+0041:\n  @.\n  @@@\n    indented: 1\n\nu+0042:\n  @x\n\nu+0043:\n  .@\n  right-bearing: 2\n";
+
+        let discard = ParseOptions {
+            recovery: Recovery::Discard,
+            ..Default::default()
+        };
+        let (font, diagnostics) = from_str_with_options(input, &discard).unwrap();
+        assert_eq!(font.name.as_deref(), Some("Test"));
+        assert_eq!(font.ascent, None);
+        assert_eq!(font.glyphs.len(), 1);
+        assert_eq!(font.glyphs[0].labels, vec![Label::Unicode(vec![0x43])]);
+        assert_eq!(font.glyphs[0].right_bearing, Some(2));
+        let summary: Vec<(usize, Severity, DiagnosticKind)> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.severity, d.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, Severity::Error, DiagnosticKind::InvalidPropertyValue),
+                (
+                    6,
+                    Severity::Error,
+                    DiagnosticKind::InconsistentGlyphLineLength
+                ),
+                (10, Severity::Error, DiagnosticKind::InvalidSyntax),
+            ]
+        );
+        assert_eq!(diagnostics[0].source_line, "ascent: big");
+
+        let repair = ParseOptions {
+            recovery: Recovery::Repair,
+            ..Default::default()
+        };
+        let (font, diagnostics) = from_str_with_options(input, &repair).unwrap();
+        assert_eq!(font.glyphs.len(), 2);
+        assert_eq!(
+            font.glyphs[0].bitmap.pixels,
+            vec![vec![true, false], vec![true, true]]
+        );
+        assert_eq!(diagnostics[1].line, 6);
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+
+        // The streaming reader skips the broken glyphs too
+        let reader = YaffReader::with_options(input.as_bytes(), &discard).unwrap();
+        let glyphs: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(glyphs.len(), 1);
+    }
 }
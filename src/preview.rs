@@ -0,0 +1,240 @@
+//! Showing bitmaps in a terminal.
+//!
+//! A [`Bitmap`] or a line of text is turned into a string that a terminal draws
+//! as the glyphs: ANSI half-block characters, two pixels to a cell; Unicode
+//! braille patterns, 2×4 pixels to a cell; or sixel graphics for terminals that
+//! show images. Half-blocks and braille use the terminal's colours unless
+//! colours are given, in which case they are set with 24-bit SGR sequences.
+
+use crate::image::Color;
+use crate::models::*;
+use crate::render::Renderer;
+use std::fmt::Write;
+
+/// How a preview is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreviewStyle {
+    /// Upper and lower half blocks, one character for two rows of pixels.
+    #[default]
+    HalfBlock,
+    /// Braille patterns, one character for two columns and four rows of pixels.
+    Braille,
+    /// Sixel graphics, at `scale` screen pixels per font pixel.
+    Sixel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreviewOptions {
+    pub style: PreviewStyle,
+    /// Colour of set pixels. Sixel previews default to white.
+    pub foreground: Option<Color>,
+    /// Colour of unset pixels. Sixel previews leave them transparent by default.
+    pub background: Option<Color>,
+    /// Size of a font pixel in screen pixels, for sixel previews.
+    pub scale: u32,
+}
+
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        PreviewOptions {
+            style: PreviewStyle::default(),
+            foreground: None,
+            background: None,
+            scale: 1,
+        }
+    }
+}
+
+/// Returns whether a pixel is set, treating pixels outside the bitmap as unset.
+fn ink(bitmap: &Bitmap, x: usize, y: usize) -> bool {
+    bitmap
+        .pixels
+        .get(y)
+        .and_then(|row| row.get(x))
+        .copied()
+        .unwrap_or(false)
+}
+
+/// SGR sequence that sets the given colours, empty for none.
+fn sgr(foreground: Option<Color>, background: Option<Color>) -> String {
+    let mut sequence = String::new();
+    if let Some(c) = foreground {
+        let _ = write!(sequence, "\x1b[38;2;{};{};{}m", c.r, c.g, c.b);
+    }
+    if let Some(c) = background {
+        let _ = write!(sequence, "\x1b[48;2;{};{};{}m", c.r, c.g, c.b);
+    }
+    sequence
+}
+
+/// Lines of characters, each for a `cell_width` by `cell_height` block of pixels.
+fn cells(
+    bitmap: &Bitmap,
+    options: &PreviewOptions,
+    (cell_width, cell_height): (usize, usize),
+    cell: impl Fn(usize, usize) -> char,
+) -> String {
+    let colours = sgr(options.foreground, options.background);
+    let mut out = String::new();
+    for y in (0..bitmap.height).step_by(cell_height) {
+        out.push_str(&colours);
+        for x in (0..bitmap.width).step_by(cell_width) {
+            out.push(cell(x, y));
+        }
+        if !colours.is_empty() {
+            out.push_str("\x1b[0m");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn half_blocks(bitmap: &Bitmap, options: &PreviewOptions) -> String {
+    cells(bitmap, options, (1, 2), |x, y| {
+        match (ink(bitmap, x, y), ink(bitmap, x, y + 1)) {
+            (true, true) => '█',
+            (true, false) => '▀',
+            (false, true) => '▄',
+            (false, false) => ' ',
+        }
+    })
+}
+
+fn braille(bitmap: &Bitmap, options: &PreviewOptions) -> String {
+    // Dot bits of the pattern by row, for the left and right column
+    const DOTS: [(u32, u32); 4] = [(0x01, 0x08), (0x02, 0x10), (0x04, 0x20), (0x40, 0x80)];
+    cells(bitmap, options, (2, 4), |x, y| {
+        let mut bits = 0;
+        for (row, (left, right)) in DOTS.iter().enumerate() {
+            if ink(bitmap, x, y + row) {
+                bits |= left;
+            }
+            if ink(bitmap, x + 1, y + row) {
+                bits |= right;
+            }
+        }
+        char::from_u32(0x2800 + bits).unwrap_or(' ')
+    })
+}
+
+fn sixel(bitmap: &Bitmap, options: &PreviewOptions) -> String {
+    let scale = options.scale.max(1) as usize;
+    let width = bitmap.width * scale;
+    let height = bitmap.height * scale;
+    let percent = |v: u8| (v as u32 * 100 + 127) / 255;
+
+    // Unset pixels are left transparent unless there is a background
+    let transparent = options.background.is_none() as u8;
+    let mut out = format!("\x1bP0;{transparent};0q\"1;1;{width};{height}");
+    let foreground = options.foreground.unwrap_or(Color::WHITE);
+    let mut palette = vec![(1, foreground, true)];
+    if let Some(background) = options.background {
+        palette.push((0, background, false));
+    }
+    for (register, c, _) in &palette {
+        let _ = write!(
+            out,
+            "#{register};2;{};{};{}",
+            percent(c.r),
+            percent(c.g),
+            percent(c.b)
+        );
+    }
+
+    for band in (0..height).step_by(6) {
+        for (i, (register, _, set)) in palette.iter().enumerate() {
+            if i > 0 {
+                // Back to the start of the band for the next colour
+                out.push('$');
+            }
+            let _ = write!(out, "#{register}");
+            let columns = (0..width).map(|x| {
+                let rows = (band..height.min(band + 6)).map(|y| ink(bitmap, x / scale, y / scale));
+                let bits = rows
+                    .enumerate()
+                    .filter(|&(_, ink)| ink == *set)
+                    .fold(0u8, |bits, (row, _)| bits | 1 << row);
+                (b'?' + bits) as char
+            });
+            push_runs(&mut out, columns);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Appends sixel characters, with runs of more than three as `!count`.
+fn push_runs(out: &mut String, columns: impl Iterator<Item = char>) {
+    let mut columns = columns.peekable();
+    while let Some(c) = columns.next() {
+        let mut count = 1;
+        while columns.next_if_eq(&c).is_some() {
+            count += 1;
+        }
+        if count > 3 {
+            let _ = write!(out, "!{count}{c}");
+        } else {
+            out.extend(std::iter::repeat_n(c, count));
+        }
+    }
+}
+
+/// Turns a bitmap into text for a terminal to show.
+pub fn preview(bitmap: &Bitmap, options: &PreviewOptions) -> String {
+    match options.style {
+        PreviewStyle::HalfBlock => half_blocks(bitmap, options),
+        PreviewStyle::Braille => braille(bitmap, options),
+        PreviewStyle::Sixel => sixel(bitmap, options),
+    }
+}
+
+/// Turns a line of text into text for a terminal to show. See [`Renderer::render`].
+pub fn preview_text(renderer: &Renderer, text: &str, options: &PreviewOptions) -> String {
+    preview(&renderer.render(text), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::bitmap;
+
+    #[test]
+    fn test_half_blocks_and_braille() {
+        let bitmap = bitmap(&["@.@", "@@.", ".@."]);
+        let options = PreviewOptions::default();
+        assert_eq!(preview(&bitmap, &options), "█▄▀\n ▀ \n");
+
+        let options = PreviewOptions {
+            style: PreviewStyle::Braille,
+            ..Default::default()
+        };
+        // Dots 1, 2, 5 and 6 in the first cell, dot 1 in the second
+        assert_eq!(preview(&bitmap, &options), "\u{2833}\u{2801}\n");
+
+        let options = PreviewOptions {
+            foreground: Some(Color::rgb(255, 0, 0)),
+            ..Default::default()
+        };
+        assert_eq!(
+            preview(&bitmap, &options),
+            "\x1b[38;2;255;0;0m█▄▀\x1b[0m\n\x1b[38;2;255;0;0m ▀ \x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_sixel() {
+        let bitmap = bitmap(&["@.", "@@"]);
+        let options = PreviewOptions {
+            style: PreviewStyle::Sixel,
+            background: Some(Color::BLACK),
+            scale: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            preview(&bitmap, &options),
+            "\x1bP0;0;0q\"1;1;4;4#1;2;100;100;100#0;2;0;0;0\
+             #1NNKK$#0??BB-\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,489 @@
+//! Drawing text with a font.
+//!
+//! Characters are mapped to glyphs by their `Label::Unicode` labels, matching
+//! multi-character labels such as ligatures first (see [`Renderer::place`]).
+//! Glyphs are placed with their resolved metrics (see
+//! [`glyph_metrics`](crate::glyph_metrics)), kerning between neighbours is applied
+//! from both glyphs' `right-kerning` and `left-kerning`, spaces advance by the
+//! font's `word-space` if it has one, and characters without a glyph are drawn
+//! with the `default-char` glyph, or skipped if the font has none. Right-to-left
+//! text is reordered for display according to the font's `direction`, or the
+//! Unicode Bidirectional Algorithm with the `bidi` feature.
+
+use crate::bidi;
+use crate::index::GlyphIndex;
+use crate::label::parse_key_as_label;
+use crate::metrics::{GlyphMetrics, bearing_anchors, glyph_metrics_all};
+use crate::models::*;
+use crate::segment;
+
+/// A glyph placed on a line of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlacedGlyph {
+    /// Index into `font.glyphs`.
+    pub glyph: usize,
+    /// Horizontal position of the glyph origin from the start of the line.
+    pub x: i32,
+}
+
+/// Part of a line of text that is drawn as one glyph.
+struct Unit {
+    glyph: Option<usize>,
+    /// Combining marks drawn over the glyph.
+    marks: Vec<usize>,
+    /// Embedding level, for bidirectional reordering.
+    level: u8,
+    /// A space that advances by the font's `word-space`.
+    space: bool,
+}
+
+impl Unit {
+    fn glyph(glyph: Option<usize>, level: u8) -> Self {
+        Unit {
+            glyph,
+            marks: Vec::new(),
+            level,
+            space: false,
+        }
+    }
+
+    fn space(level: u8) -> Self {
+        Unit {
+            space: true,
+            ..Unit::glyph(None, level)
+        }
+    }
+}
+
+/// A line of text turned into positioned glyphs.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GlyphRun {
+    pub glyphs: Vec<PlacedGlyph>,
+    /// Total advance of the line, where the next glyph would be placed.
+    pub advance: i32,
+}
+
+/// Lays out and draws text with one font.
+///
+/// Creating a renderer resolves the metrics and character map of the font once,
+/// so reuse it when drawing more than one string.
+pub struct Renderer<'a> {
+    font: &'a YaffFont,
+    metrics: Vec<GlyphMetrics>,
+    index: GlyphIndex<'a>,
+    default_glyph: Option<usize>,
+    ascent: i32,
+    descent: i32,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(font: &'a YaffFont) -> Self {
+        let index = GlyphIndex::new(font);
+        let default_glyph = font
+            .default_char_label_raw
+            .as_deref()
+            .and_then(|raw| parse_key_as_label(raw, &LineType::Empty))
+            .and_then(|label| index.get(&label));
+        let (ascent, descent) = bearing_anchors(font);
+        Renderer {
+            font,
+            metrics: glyph_metrics_all(font),
+            index,
+            default_glyph,
+            ascent,
+            descent,
+        }
+    }
+
+    pub fn font(&self) -> &'a YaffFont {
+        self.font
+    }
+
+    /// The glyph lookup tables of the font.
+    pub fn index(&self) -> &GlyphIndex<'a> {
+        &self.index
+    }
+
+    /// Height of the ascent line above the baseline.
+    pub fn ascent(&self) -> i32 {
+        self.ascent
+    }
+
+    /// Depth of the descent line below the baseline, as a positive number.
+    pub fn descent(&self) -> i32 {
+        self.descent
+    }
+
+    /// Returns the glyph drawn for `c`, which is the default glyph if the font
+    /// has no glyph for it.
+    pub fn glyph_for(&self, c: char) -> Option<usize> {
+        self.index.char(c).or(self.default_glyph)
+    }
+
+    /// Whether the font has a glyph of its own for `c`, or a word space for ' '.
+    pub fn covers(&self, c: char) -> bool {
+        self.index.char(c).is_some() || (c == ' ' && self.font.word_space.is_some())
+    }
+
+    /// Resolved metrics of a glyph.
+    pub(crate) fn glyph_metrics(&self, glyph: usize) -> &GlyphMetrics {
+        &self.metrics[glyph]
+    }
+
+    /// Advance width of a glyph.
+    pub fn advance(&self, glyph: usize) -> i32 {
+        self.metrics[glyph].advance_width
+    }
+
+    /// Kerning between two neighbouring glyphs, from the right kerning of the first
+    /// and the left kerning of the second.
+    pub fn kerning(&self, left: usize, right: usize) -> f32 {
+        self.index.kerning(left, right)
+    }
+
+    /// Splits text into the units that are drawn as one glyph, in logical order.
+    ///
+    /// At each grapheme cluster, the longest multi-character label that matches
+    /// and ends on a cluster boundary is used, such as a ligature or a precomposed
+    /// base and mark. Otherwise each character is a unit, with any combining
+    /// marks in its cluster drawn over it. Characters at right-to-left levels use
+    /// the glyph of their mirror image where the font has one.
+    fn segment(&self, text: &str, levels: &[u8]) -> Vec<Unit> {
+        let chars: Vec<char> = text.chars().collect();
+        let starts = segment::cluster_starts(text);
+        let is_boundary = |i: usize| i == chars.len() || starts[i];
+
+        let mut units = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let level = levels[i];
+            let longest = self.index.longest_sequence().min(chars.len() - i);
+            let sequence = (2..=longest).rev().find_map(|len| {
+                if !is_boundary(i + len) {
+                    return None;
+                }
+                let key: Vec<u32> = chars[i..i + len].iter().map(|&c| c as u32).collect();
+                self.index.unicode(&key).map(|glyph| (glyph, len))
+            });
+            if let Some((glyph, len)) = sequence {
+                units.push(Unit::glyph(Some(glyph), level));
+                i += len;
+                continue;
+            }
+
+            let c = chars[i];
+            let end = (i + 1..=chars.len())
+                .find(|&j| is_boundary(j))
+                .unwrap_or(chars.len());
+            if c == ' ' && self.font.word_space.is_some() && end == i + 1 {
+                units.push(Unit::space(level));
+                i = end;
+                continue;
+            }
+            let mirrored = (level % 2 == 1)
+                .then(|| bidi::mirror(c))
+                .flatten()
+                .and_then(|m| self.index.char(m));
+            let mut unit = Unit::glyph(mirrored.or_else(|| self.glyph_for(c)), level);
+            // Draw the marks of the cluster that the font has glyphs for
+            unit.marks = chars[i + 1..end]
+                .iter()
+                .filter(|&&m| segment::is_combining(m))
+                .filter_map(|&m| self.index.char(m))
+                .collect();
+            units.push(unit);
+            i = end;
+        }
+        units
+    }
+
+    /// Places the glyphs of a line of text in visual order, so kerning applies
+    /// between glyphs that end up next to each other. Combining marks are placed
+    /// at the origin of their base glyph.
+    pub fn place(&self, text: &str) -> GlyphRun {
+        let (levels, _) = bidi::levels(self.font, text);
+        self.place_with_levels(text, &levels)
+    }
+
+    /// Places the glyphs of text whose characters have the given embedding levels.
+    pub(crate) fn place_with_levels(&self, text: &str, levels: &[u8]) -> GlyphRun {
+        let units = self.segment(text, levels);
+        let levels: Vec<u8> = units.iter().map(|u| u.level).collect();
+        let mut run = GlyphRun::default();
+        let mut pen = 0.0f32;
+        let mut previous = None;
+        for index in bidi::visual_order(&levels) {
+            let unit = &units[index];
+            if unit.space {
+                pen += self.font.word_space.unwrap_or(0) as f32;
+                previous = None;
+                continue;
+            }
+            if let Some(glyph) = unit.glyph {
+                if let Some(previous) = previous {
+                    pen += self.kerning(previous, glyph);
+                }
+                run.glyphs.push(PlacedGlyph {
+                    glyph,
+                    x: pen.round() as i32,
+                });
+            }
+            run.glyphs
+                .extend(unit.marks.iter().map(|&glyph| PlacedGlyph {
+                    glyph,
+                    x: pen.round() as i32,
+                }));
+            if let Some(glyph) = unit.glyph {
+                pen += self.metrics[glyph].advance_width as f32;
+                previous = Some(glyph);
+            }
+        }
+        run.advance = pen.round() as i32;
+        run
+    }
+
+    /// Width of a line of text.
+    pub fn measure(&self, text: &str) -> i32 {
+        self.place(text).advance
+    }
+
+    /// Bounds of a run as `(left, bottom, right, top)`: the advance and the
+    /// ascent and descent lines, extended to hold any ink beyond them. A negative
+    /// advance counts as none, so `right` is never left of `left`.
+    pub fn run_bounds(&self, run: &GlyphRun) -> (i32, i32, i32, i32) {
+        let glyphs = run.glyphs.iter().map(|p| (p.x, &self.metrics[p.glyph]));
+        placed_bounds(run.advance, self.ascent, self.descent, glyphs)
+    }
+
+    /// Draws a glyph onto a bitmap with its origin at `(x, baseline)` in bitmap
+    /// coordinates, where rows grow downwards. Pixels outside the bitmap are clipped.
+    pub fn draw_glyph(&self, target: &mut Bitmap, glyph: usize, x: i32, baseline: i32) {
+        let (left, _, _, top) = self.metrics[glyph].raster_bounds;
+        for (row_index, row) in self.font.glyphs[glyph].bitmap.pixels.iter().enumerate() {
+            let y = baseline - top + row_index as i32;
+            let Some(target_row) = usize::try_from(y)
+                .ok()
+                .and_then(|y| target.pixels.get_mut(y))
+            else {
+                continue;
+            };
+            for (column, _) in row.iter().enumerate().filter(|(_, set)| **set) {
+                if let Some(pixel) = usize::try_from(x + left + column as i32)
+                    .ok()
+                    .and_then(|x| target_row.get_mut(x))
+                {
+                    *pixel = true;
+                }
+            }
+        }
+    }
+
+    /// Draws a line of text onto a new bitmap that is as wide as the text and as
+    /// tall as the font, grown where ink extends beyond either.
+    pub fn render(&self, text: &str) -> Bitmap {
+        let run = self.place(text);
+        let bounds = self.run_bounds(&run);
+        let (left, _, _, top) = bounds;
+        let mut bitmap = canvas(bounds);
+        for placed in &run.glyphs {
+            self.draw_glyph(&mut bitmap, placed.glyph, placed.x - left, top);
+        }
+        bitmap
+    }
+}
+
+/// Bounds as `(left, bottom, right, top)` of glyphs placed at `x` on a line of
+/// `advance` between the ascent and descent lines, shared by [`Renderer`] and
+/// font stacks.
+pub(crate) fn placed_bounds<'m>(
+    advance: i32,
+    ascent: i32,
+    descent: i32,
+    glyphs: impl IntoIterator<Item = (i32, &'m GlyphMetrics)>,
+) -> (i32, i32, i32, i32) {
+    let mut bounds = (0, -descent, advance.max(0), ascent);
+    for (x, metrics) in glyphs {
+        if let Some((left, bottom, right, top)) = metrics.ink_bounds {
+            bounds = (
+                bounds.0.min(x + left),
+                bounds.1.min(bottom),
+                bounds.2.max(x + right),
+                bounds.3.max(top),
+            );
+        }
+    }
+    bounds
+}
+
+/// A blank bitmap the size of `(left, bottom, right, top)` bounds.
+pub(crate) fn canvas((left, bottom, right, top): (i32, i32, i32, i32)) -> Bitmap {
+    let width = (right - left).max(0) as usize;
+    let height = (top - bottom).max(0) as usize;
+    Bitmap {
+        pixels: vec![vec![false; width]; height],
+        width,
+        height,
+    }
+}
+
+/// Draws a line of text with `font`. See [`Renderer::render`].
+pub fn render(font: &YaffFont, text: &str) -> Bitmap {
+    Renderer::new(font).render(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rows;
+
+    fn font() -> YaffFont {
+        let yaff = "\
+ascent: 3
+descent: 1
+default-char: u+003F
+
+u+0041:
+    .@.
+    @@@
+    @.@
+    right-bearing: 1
+    right-kerning:
+        u+0056 -1
+
+u+0056:
+    @.@
+    @.@
+    .@.
+    right-bearing: 1
+
+u+003F:
+    @@
+    .@
+    @.
+    right-bearing: 1
+
+u+0067:
+    @@
+    @@
+    shift-up: -1
+";
+        yaff.parse().unwrap()
+    }
+
+    #[test]
+    fn test_render_with_kerning_and_default_char() {
+        let font = font();
+        let bitmap = render(&font, "AVx");
+        assert_eq!(bitmap.width, 10);
+        assert_eq!(bitmap.height, 4);
+        assert_eq!(
+            rows(&bitmap),
+            vec![".@.@.@.@@.", "@@@@.@..@.", "@.@.@..@..", ".........."]
+        );
+    }
+
+    #[test]
+    fn test_word_space_and_descenders() {
+        let mut font = font();
+        font.word_space = Some(2);
+        let renderer = Renderer::new(&font);
+        assert_eq!(renderer.measure("A A"), 10);
+        assert_eq!(rows(&renderer.render("g")), vec!["..", "..", "@@", "@@"]);
+
+        font.default_char_label_raw = None;
+        assert_eq!(Renderer::new(&font).measure("xA"), 4);
+    }
+
+    #[test]
+    fn test_negative_advance_without_ink() {
+        let font: YaffFont = "ascent: 2\n\n'a':\n    -\n    right-bearing: -3\n"
+            .parse()
+            .unwrap();
+        let renderer = Renderer::new(&font);
+        assert_eq!(renderer.measure("a"), -3);
+        assert_eq!(renderer.run_bounds(&renderer.place("a")), (0, 0, 0, 2));
+        let bitmap = renderer.render("a");
+        assert_eq!((bitmap.width, bitmap.height), (0, 2));
+    }
+
+    #[test]
+    fn test_right_to_left_with_mirroring() {
+        let yaff = "\
+direction: right-to-left
+
+u+05D0:
+    @.
+    right-kerning:
+        u+05D1 1
+
+u+05D1:
+    .@
+    right-kerning:
+        u+05D0 -1
+
+u+0028:
+    @@
+    @.
+
+u+0029:
+    @@
+    .@
+";
+        let font: YaffFont = yaff.parse().unwrap();
+        let renderer = Renderer::new(&font);
+        // Glyphs run right to left, kerning in visual order, with "(" drawn as ")"
+        let run = renderer.place("(אב");
+        let order: Vec<(usize, i32)> = run.glyphs.iter().map(|p| (p.glyph, p.x)).collect();
+        assert_eq!(order, vec![(1, 0), (0, 1), (3, 3)]);
+        assert_eq!(rows(&renderer.render("(אב")), vec!["...@@", ".@..@"]);
+    }
+
+    #[test]
+    fn test_sequence_labels_and_combining_marks() {
+        let yaff = "\
+u+0066:
+    @.
+    @.
+
+u+0069:
+    .@
+    .@
+
+u+0066, u+0069:
+    @@@
+    @.@
+
+u+0065:
+    ..
+    @@
+
+u+0301:
+    .@
+    ..
+    right-bearing: -2
+
+u+0061, u+0301:
+    @@@
+    .@.
+";
+        let font: YaffFont = yaff.parse().unwrap();
+        let renderer = Renderer::new(&font);
+        let glyphs = |text| -> Vec<(usize, i32)> {
+            renderer
+                .place(text)
+                .glyphs
+                .iter()
+                .map(|p| (p.glyph, p.x))
+                .collect()
+        };
+        // The longest label wins
+        assert_eq!(glyphs("fif"), vec![(2, 0), (0, 3)]);
+        // No precomposed glyph, so the mark is drawn over its base
+        assert_eq!(glyphs("e\u{301}i"), vec![(3, 0), (4, 0), (1, 2)]);
+        assert_eq!(rows(&renderer.render("e\u{301}")), vec![".@", "@@"]);
+        // A precomposed glyph is used where there is one
+        assert_eq!(glyphs("a\u{301}"), vec![(5, 0)]);
+        // A label does not match part of a cluster
+        assert_eq!(glyphs("fi\u{301}"), vec![(0, 0), (1, 2), (4, 2)]);
+    }
+}
@@ -0,0 +1,87 @@
+//! Grapheme clusters for matching text against glyph labels.
+//!
+//! With the `graphemes` feature, clusters are the extended grapheme clusters of
+//! Unicode text segmentation. Without it, clusters are an approximation: a
+//! character followed by any combining marks from the common combining blocks,
+//! variation selectors and zero-width-joined characters. That covers accented
+//! letters, Hebrew and Arabic points and emoji sequences, but not Hangul jamo,
+//! regional indicator pairs, prepended characters or spacing marks of Indic
+//! scripts, which are separate clusters.
+
+/// Whether `c` is a combining mark that is drawn over the preceding character.
+pub(crate) fn is_combining(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F // Combining Diacritical Marks
+        | 0x0483..=0x0489 // Cyrillic
+        | 0x0591..=0x05BD | 0x05BF | 0x05C1..=0x05C2 | 0x05C4..=0x05C5 | 0x05C7 // Hebrew
+        | 0x0610..=0x061A | 0x064B..=0x065F | 0x0670 | 0x06D6..=0x06DC | 0x06DF..=0x06E4
+        | 0x06E7..=0x06E8 | 0x06EA..=0x06ED // Arabic
+        | 0x0E31 | 0x0E34..=0x0E3A | 0x0E47..=0x0E4E // Thai
+        | 0x1AB0..=0x1AFF // Combining Diacritical Marks Extended
+        | 0x1DC0..=0x1DFF // Combining Diacritical Marks Supplement
+        | 0x20D0..=0x20FF // Combining Diacritical Marks for Symbols
+        | 0x3099..=0x309A // Kana voicing marks
+        | 0xFE20..=0xFE2F // Combining Half Marks
+    )
+}
+
+/// Whether each character of `text` starts a grapheme cluster.
+#[cfg(feature = "graphemes")]
+pub(crate) fn cluster_starts(text: &str) -> Vec<bool> {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    let mut boundaries = text.grapheme_indices(true).map(|(i, _)| i).peekable();
+    text.char_indices()
+        .map(|(i, _)| boundaries.next_if_eq(&i).is_some())
+        .collect()
+}
+
+/// Whether each character of `text` starts a grapheme cluster.
+#[cfg(not(feature = "graphemes"))]
+pub(crate) fn cluster_starts(text: &str) -> Vec<bool> {
+    const ZWJ: char = '\u{200D}';
+    let mut previous = None;
+    text.chars()
+        .map(|c| {
+            let extends = is_combining(c)
+                || matches!(c as u32, 0xFE00..=0xFE0F | 0xE0100..=0xE01EF)
+                || c == ZWJ
+                || previous == Some(ZWJ);
+            let start = previous.is_none() || !extends;
+            previous = Some(c);
+            start
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cluster_starts() {
+        assert_eq!(cluster_starts("ab"), vec![true, true]);
+        assert_eq!(
+            cluster_starts("e\u{301}\u{302}x"),
+            vec![true, false, false, true]
+        );
+        assert_eq!(cluster_starts("\u{301}a"), vec![true, true]);
+        // Emoji with a variation selector, and joined into a family
+        assert_eq!(cluster_starts("\u{2764}\u{FE0F}"), vec![true, false]);
+        assert_eq!(
+            cluster_starts("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}!"),
+            vec![true, false, false, false, false, true]
+        );
+        // Hebrew points stay with their letter
+        assert_eq!(
+            cluster_starts("\u{5E9}\u{5C1}\u{5D1}"),
+            vec![true, false, true]
+        );
+        // Hangul jamo form one cluster only with the `graphemes` feature
+        let jamo = cluster_starts("\u{1100}\u{1161}\u{11A8}");
+        assert!(jamo[0]);
+        assert_eq!(jamo[1..], [!cfg!(feature = "graphemes"); 2]);
+        assert!(is_combining('\u{301}'));
+        assert!(!is_combining('a'));
+    }
+}
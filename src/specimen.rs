@@ -0,0 +1,961 @@
+//! Font specimens and character charts.
+//!
+//! A specimen sheet shows a font's metadata, a chart of its characters in rows
+//! of sixteen code points labelled in hex, sample texts set in the font, and its
+//! kerning pairs with and without kerning. A font labelled only by codepoint is
+//! charted by the characters its `encoding` decodes, or else by the codes.
+//!
+//! Sheets are written as 1-bit PNG images, SVG drawings or HTML pages with
+//! inline SVG. Labels in PNG images are set in a small built-in font, so they
+//! show for fonts without Latin glyphs. PNG images are held packed at one bit
+//! per pixel while drawn, and compressed with fixed Huffman codes.
+
+use crate::codepage::Codepage;
+use crate::image::Color;
+use crate::layout::{LayoutOptions, layout};
+use crate::metrics::calculate_metrics;
+use crate::models::*;
+use crate::render::Renderer;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// File format of a specimen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecimenFormat {
+    Png,
+    Svg,
+    Html,
+}
+
+/// Pangrams shown when no other samples are given.
+pub const PANGRAMS: &[&str] = &[
+    "The quick brown fox jumps over the lazy dog.",
+    "Pack my box with five dozen liquor jugs!",
+    "0123456789 ({[<&@#%*+=>]})",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecimenOptions {
+    /// Texts set in the font, one block each; newlines break lines.
+    pub samples: Vec<String>,
+    /// Most kerning pairs to show.
+    pub kerning_pairs: usize,
+    /// Size of a font pixel in image pixels.
+    pub scale: u32,
+    pub foreground: Color,
+    pub background: Color,
+}
+
+impl Default for SpecimenOptions {
+    fn default() -> Self {
+        SpecimenOptions {
+            samples: PANGRAMS.iter().map(|s| s.to_string()).collect(),
+            kerning_pairs: 12,
+            scale: 2,
+            foreground: Color::BLACK,
+            background: Color::WHITE,
+        }
+    }
+}
+
+/// A kerning pair drawn without and with its kerning.
+struct Pair {
+    text: String,
+    amount: f32,
+    plain: Bitmap,
+    kerned: Bitmap,
+}
+
+/// What a specimen shows, independent of the file format.
+struct Sheet {
+    title: String,
+    metadata: Vec<(&'static str, String)>,
+    /// Rows of the chart by their first code point.
+    chart: BTreeMap<u32, [Option<Bitmap>; 16]>,
+    /// Whether the chart is of codepoints rather than Unicode characters.
+    chart_codes: bool,
+    samples: Vec<Bitmap>,
+    kerning: Vec<Pair>,
+}
+
+/// The character of a single-character label, or of a codepoint the codepage
+/// decodes.
+fn label_char(label: &Label, codepage: Option<&Codepage>) -> Option<char> {
+    match label {
+        Label::Unicode(values) if values.len() == 1 => char::from_u32(values[0]),
+        Label::Codepoint(bytes) => codepage?.decode(bytes),
+        _ => None,
+    }
+}
+
+/// The character a glyph is labelled with, if any.
+fn glyph_char(glyph: &GlyphDefinition, codepage: Option<&Codepage>) -> Option<char> {
+    glyph
+        .labels
+        .iter()
+        .find_map(|label| label_char(label, codepage))
+}
+
+/// The value of a codepoint label read as a big-endian number.
+fn codepoint_value(label: &Label) -> Option<u32> {
+    match label {
+        Label::Codepoint(bytes) if (1..=4).contains(&bytes.len()) => {
+            Some(bytes.iter().fold(0, |code, &b| code << 8 | b as u32))
+        }
+        _ => None,
+    }
+}
+
+/// Glyphs drawn side by side on a shared baseline, each `advance` after the last.
+fn draw_glyphs(renderer: &Renderer, glyphs: &[(usize, i32)], width: i32) -> Bitmap {
+    let width = width.max(1) as usize;
+    let height = (renderer.ascent() + renderer.descent()).max(1) as usize;
+    let mut bitmap = Bitmap {
+        pixels: vec![vec![false; width]; height],
+        width,
+        height,
+    };
+    for &(glyph, x) in glyphs {
+        renderer.draw_glyph(&mut bitmap, glyph, x, renderer.ascent());
+    }
+    bitmap
+}
+
+impl Sheet {
+    fn new(font: &YaffFont, options: &SpecimenOptions) -> Self {
+        let renderer = Renderer::new(font);
+        let metrics = calculate_metrics(font);
+        let spacing = match font.spacing.clone().unwrap_or(metrics.spacing) {
+            FontSpacing::Proportional => "proportional",
+            FontSpacing::Monospace => "monospace",
+            FontSpacing::CharacterCell => "character-cell",
+            FontSpacing::MultiCell => "multi-cell",
+        };
+        let metadata = [
+            ("name", font.name.clone()),
+            ("family", font.family.clone()),
+            (
+                "pixel-size",
+                Some(font.pixel_size.unwrap_or(metrics.pixel_size).to_string()),
+            ),
+            ("spacing", Some(spacing.to_string())),
+            ("author", font.author.clone()),
+            ("copyright", font.copyright.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect();
+
+        // Chart by character where the labels or encoding tell, or else by code
+        let codepage = renderer.index().codepage();
+        let chart_by = |code: &dyn Fn(&Label) -> Option<u32>| {
+            let mut chart: BTreeMap<u32, [Option<Bitmap>; 16]> = BTreeMap::new();
+            for (index, glyph) in font.glyphs.iter().enumerate() {
+                for value in glyph.labels.iter().filter_map(code) {
+                    let row = chart.entry(value & !0xF).or_default();
+                    let width = renderer.advance(index);
+                    row[(value & 0xF) as usize]
+                        .get_or_insert_with(|| draw_glyphs(&renderer, &[(index, 0)], width));
+                }
+            }
+            chart
+        };
+        let mut chart = chart_by(&|label| label_char(label, codepage).map(u32::from));
+        let chart_codes = chart.is_empty();
+        if chart_codes {
+            chart = chart_by(&codepoint_value);
+        }
+
+        let samples = options
+            .samples
+            .iter()
+            .map(|text| layout(&renderer, text, &LayoutOptions::default()).render(&renderer))
+            .collect();
+
+        let mut pairs: Vec<((usize, usize), f32)> = renderer
+            .index()
+            .kerning_pairs()
+            .filter(|&(_, amount)| amount != 0.0)
+            .collect();
+        pairs.sort_by_key(|&(pair, _)| pair);
+        let kerning = pairs
+            .into_iter()
+            .take(options.kerning_pairs)
+            .map(|((left, right), amount)| {
+                let name = |glyph: usize| {
+                    glyph_char(&font.glyphs[glyph], codepage)
+                        .map_or(format!("#{glyph}"), String::from)
+                };
+                let advance = renderer.advance(left);
+                let kerned = advance + amount.round() as i32;
+                let width = advance.max(kerned) + renderer.advance(right);
+                Pair {
+                    text: name(left) + &name(right),
+                    amount,
+                    plain: draw_glyphs(&renderer, &[(left, 0), (right, advance)], width),
+                    kerned: draw_glyphs(&renderer, &[(left, 0), (right, kerned)], width),
+                }
+            })
+            .collect();
+
+        Sheet {
+            title: font.name.clone().unwrap_or_else(|| "Untitled".to_string()),
+            metadata,
+            chart,
+            chart_codes,
+            samples,
+            kerning,
+        }
+    }
+
+    /// How the chart writes a code point.
+    fn code_label(&self, code: u32) -> String {
+        if self.chart_codes {
+            format!("0x{code:02X}")
+        } else {
+            format!("U+{code:04X}")
+        }
+    }
+}
+
+/// Something placed on a PNG or SVG sheet, at a position in font pixels.
+enum Item<'s> {
+    Label { x: i32, y: i32, text: String },
+    Bitmap { x: i32, y: i32, bitmap: &'s Bitmap },
+}
+
+const MARGIN: i32 = 4;
+/// Advance of a label character.
+const LABEL_ADVANCE: i32 = 4;
+/// Height of a label line, including the space below it.
+const LABEL_LINE: i32 = 7;
+
+/// Lays out a sheet top to bottom, and returns its items, width and height.
+fn arrange(sheet: &Sheet) -> (Vec<Item<'_>>, i32, i32) {
+    let mut items = Vec::new();
+    let mut y = MARGIN;
+    let label = |items: &mut Vec<Item>, x, y, text: String| {
+        items.push(Item::Label { x, y, text });
+    };
+
+    label(&mut items, MARGIN, y, sheet.title.clone());
+    y += LABEL_LINE * 2;
+    for (key, value) in &sheet.metadata {
+        label(&mut items, MARGIN, y, format!("{key}: {value}"));
+        y += LABEL_LINE;
+    }
+
+    if !sheet.chart.is_empty() {
+        y += LABEL_LINE;
+        label(&mut items, MARGIN, y, "Character chart".to_string());
+        y += LABEL_LINE * 2;
+        let cells = sheet.chart.values().flatten().flatten();
+        let cell_width = cells.clone().map(|b| b.width as i32).max().unwrap_or(0);
+        let cell_height = cells.map(|b| b.height as i32).max().unwrap_or(0);
+        let pitch = (cell_width.max(LABEL_ADVANCE * 2), cell_height.max(5));
+        let pitch = (pitch.0 + 4, pitch.1 + 4);
+        let label_width = sheet.chart.keys().map(|&s| sheet.code_label(s).len()).max();
+        let left = MARGIN + label_width.unwrap_or(0) as i32 * LABEL_ADVANCE + 4;
+        for column in 0..16 {
+            label(
+                &mut items,
+                left + column * pitch.0,
+                y,
+                format!("{column:X}"),
+            );
+        }
+        y += LABEL_LINE;
+        for (&start, row) in &sheet.chart {
+            label(
+                &mut items,
+                MARGIN,
+                y + (pitch.1 - 5) / 2,
+                sheet.code_label(start),
+            );
+            for (column, cell) in row.iter().enumerate() {
+                if let Some(bitmap) = cell {
+                    let x = left + column as i32 * pitch.0;
+                    items.push(Item::Bitmap { x, y, bitmap });
+                }
+            }
+            y += pitch.1;
+        }
+    }
+
+    if !sheet.samples.is_empty() {
+        y += LABEL_LINE;
+        label(&mut items, MARGIN, y, "Samples".to_string());
+        y += LABEL_LINE * 2;
+        for bitmap in &sheet.samples {
+            items.push(Item::Bitmap {
+                x: MARGIN,
+                y,
+                bitmap,
+            });
+            y += bitmap.height as i32 + 4;
+        }
+    }
+
+    if !sheet.kerning.is_empty() {
+        y += LABEL_LINE;
+        label(&mut items, MARGIN, y, "Kerning".to_string());
+        y += LABEL_LINE * 2;
+        let label_width = sheet.kerning.iter().map(|p| pair_label(p).chars().count());
+        let left = MARGIN + label_width.max().unwrap_or(0) as i32 * LABEL_ADVANCE + 4;
+        let pair_width = sheet.kerning.iter().map(|p| p.plain.width as i32).max();
+        let right = left + pair_width.unwrap_or(0) + 8;
+        for pair in &sheet.kerning {
+            label(&mut items, MARGIN, y, pair_label(pair));
+            items.push(Item::Bitmap {
+                x: left,
+                y,
+                bitmap: &pair.plain,
+            });
+            items.push(Item::Bitmap {
+                x: right,
+                y,
+                bitmap: &pair.kerned,
+            });
+            y += (pair.plain.height as i32).max(5) + 4;
+        }
+    }
+
+    let width = items
+        .iter()
+        .map(|item| match item {
+            Item::Label { x, text, .. } => x + text.chars().count() as i32 * LABEL_ADVANCE,
+            Item::Bitmap { x, bitmap, .. } => x + bitmap.width as i32,
+        })
+        .max()
+        .unwrap_or(0);
+    (items, width + MARGIN, y + MARGIN)
+}
+
+fn pair_label(pair: &Pair) -> String {
+    format!("{} {}", pair.text, pair.amount)
+}
+
+/// Rows of the built-in 3×5 label font, three bits to a row.
+fn label_glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [7, 5, 5, 5, 7],
+        '1' => [2, 6, 2, 2, 7],
+        '2' => [7, 1, 7, 4, 7],
+        '3' => [7, 1, 7, 1, 7],
+        '4' => [5, 5, 7, 1, 1],
+        '5' => [7, 4, 7, 1, 7],
+        '6' => [7, 4, 7, 5, 7],
+        '7' => [7, 1, 2, 2, 2],
+        '8' => [7, 5, 7, 5, 7],
+        '9' => [7, 5, 7, 1, 7],
+        'A' => [2, 5, 7, 5, 5],
+        'B' => [6, 5, 6, 5, 6],
+        'C' => [3, 4, 4, 4, 3],
+        'D' => [6, 5, 5, 5, 6],
+        'E' => [7, 4, 6, 4, 7],
+        'F' => [7, 4, 6, 4, 4],
+        'G' => [3, 4, 5, 5, 3],
+        'H' => [5, 5, 7, 5, 5],
+        'I' => [7, 2, 2, 2, 7],
+        'J' => [1, 1, 1, 5, 2],
+        'K' => [5, 5, 6, 5, 5],
+        'L' => [4, 4, 4, 4, 7],
+        'M' => [5, 7, 7, 5, 5],
+        'N' => [6, 5, 5, 5, 5],
+        'O' => [2, 5, 5, 5, 2],
+        'P' => [6, 5, 6, 4, 4],
+        'Q' => [2, 5, 5, 6, 3],
+        'R' => [6, 5, 6, 5, 5],
+        'S' => [3, 4, 2, 1, 6],
+        'T' => [7, 2, 2, 2, 2],
+        'U' => [5, 5, 5, 5, 7],
+        'V' => [5, 5, 5, 5, 2],
+        'W' => [5, 5, 7, 7, 5],
+        'X' => [5, 5, 2, 5, 5],
+        'Y' => [5, 5, 2, 2, 2],
+        'Z' => [7, 1, 2, 4, 7],
+        ' ' => [0, 0, 0, 0, 0],
+        ':' => [0, 2, 0, 2, 0],
+        '+' => [0, 2, 7, 2, 0],
+        '-' => [0, 0, 7, 0, 0],
+        '=' => [0, 7, 0, 7, 0],
+        '.' => [0, 0, 0, 0, 2],
+        ',' => [0, 0, 0, 2, 4],
+        '(' => [1, 2, 2, 2, 1],
+        ')' => [4, 2, 2, 2, 4],
+        '/' => [1, 1, 2, 4, 4],
+        '\'' => [2, 2, 0, 0, 0],
+        '"' => [5, 5, 0, 0, 0],
+        '!' => [2, 2, 2, 0, 2],
+        '&' => [2, 5, 2, 5, 3],
+        '@' => [2, 5, 7, 4, 3],
+        '#' => [5, 7, 5, 7, 5],
+        '%' => [5, 1, 2, 4, 5],
+        '_' => [0, 0, 0, 0, 7],
+        '©' => [7, 4, 4, 4, 7],
+        _ => [6, 1, 2, 0, 2],
+    }
+}
+
+/// A label set in the built-in font.
+fn label_bitmap(text: &str) -> Bitmap {
+    let chars: Vec<char> = text.chars().collect();
+    let width = chars.len() * LABEL_ADVANCE as usize;
+    let pixels = (0..5)
+        .map(|row| {
+            let mut line = vec![false; width];
+            for (i, &c) in chars.iter().enumerate() {
+                let bits = label_glyph(c)[row];
+                for column in 0..3 {
+                    line[i * LABEL_ADVANCE as usize + column] = bits & (4 >> column) != 0;
+                }
+            }
+            line
+        })
+        .collect();
+    Bitmap {
+        pixels,
+        width,
+        height: 5,
+    }
+}
+
+/// A 1-bit image held as PNG scanlines: a filter byte, then eight pixels to a
+/// byte with the leftmost in the high bit.
+struct Scanlines {
+    width: usize,
+    height: usize,
+    stride: usize,
+    data: Vec<u8>,
+}
+
+impl Scanlines {
+    fn new(width: usize, height: usize) -> Self {
+        let stride = width.div_ceil(8) + 1;
+        Scanlines {
+            width,
+            height,
+            stride,
+            data: vec![0; stride * height],
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize) {
+        if x < self.width && y < self.height {
+            self.data[y * self.stride + 1 + x / 8] |= 0x80 >> (x % 8);
+        }
+    }
+}
+
+/// Sets the pixels of `source` on `target`, scaled, with the top left at `(x, y)`
+/// in unscaled pixels.
+fn blit(target: &mut Scanlines, source: &Bitmap, x: i32, y: i32, scale: usize) {
+    for (row, line) in source.pixels.iter().enumerate() {
+        for (column, _) in line.iter().enumerate().filter(|(_, set)| **set) {
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let tx = (x as usize + column) * scale + dx;
+                    target.set(tx, (y as usize + row) * scale + dy);
+                }
+            }
+        }
+    }
+}
+
+fn png(sheet: &Sheet, options: &SpecimenOptions) -> Vec<u8> {
+    let (items, width, height) = arrange(sheet);
+    let scale = options.scale.max(1) as usize;
+    let mut image = Scanlines::new(width as usize * scale, height as usize * scale);
+    for item in &items {
+        match item {
+            Item::Label { x, y, text } => blit(&mut image, &label_bitmap(text), *x, *y, scale),
+            Item::Bitmap { x, y, bitmap } => blit(&mut image, bitmap, *x, *y, scale),
+        }
+    }
+    encode_png(&image, options.foreground, options.background)
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Writes bits least significant first, as deflate packs them.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: u32) {
+        self.bits |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Writes a Huffman code, which deflate stores most significant bit first.
+    fn write_code(&mut self, code: u32, length: u32) {
+        self.write(code.reverse_bits() >> (32 - length), length);
+    }
+
+    /// Writes a literal, length or end-of-block symbol in the fixed Huffman code.
+    fn write_symbol(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xC0 + symbol - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Compresses PNG scanlines into a single fixed-Huffman deflate block.
+///
+/// Only repeats of the previous byte or of the scanline above are matched, which
+/// is what blank space and repeated rows in a sheet are made of.
+fn deflate(data: &[u8], stride: usize) -> Vec<u8> {
+    let mut out = BitWriter::default();
+    // Final block, fixed Huffman codes
+    out.write(1, 1);
+    out.write(1, 2);
+    let match_length = |i: usize, distance: usize| {
+        data[i..]
+            .iter()
+            .zip(&data[i - distance..])
+            .take(258)
+            .take_while(|(a, b)| a == b)
+            .count()
+    };
+    let mut i = 0;
+    while i < data.len() {
+        let best = [1, stride]
+            .into_iter()
+            .filter(|&distance| distance <= i && distance <= 32768)
+            .map(|distance| (match_length(i, distance), distance))
+            .max();
+        match best {
+            Some((length, distance)) if length >= 3 => {
+                let code = LENGTH_BASE
+                    .iter()
+                    .rposition(|&b| b as usize <= length)
+                    .unwrap();
+                out.write_symbol(257 + code as u32);
+                out.write(
+                    (length - LENGTH_BASE[code] as usize) as u32,
+                    LENGTH_EXTRA[code] as u32,
+                );
+                let code = DISTANCE_BASE
+                    .iter()
+                    .rposition(|&b| b as usize <= distance)
+                    .unwrap();
+                out.write_code(code as u32, 5);
+                out.write(
+                    (distance - DISTANCE_BASE[code] as usize) as u32,
+                    DISTANCE_EXTRA[code] as u32,
+                );
+                i += length;
+            }
+            _ => {
+                out.write_symbol(data[i] as u32);
+                i += 1;
+            }
+        }
+    }
+    out.write_symbol(256);
+    out.finish()
+}
+
+/// Encodes scanlines as a 1-bit palette PNG.
+fn encode_png(image: &Scanlines, foreground: Color, background: Color) -> Vec<u8> {
+    let mut zlib = vec![0x78, 0x01];
+    zlib.extend(deflate(&image.data, image.stride));
+    zlib.extend_from_slice(&adler32(&image.data).to_be_bytes());
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut header = Vec::new();
+    header.extend_from_slice(&(image.width as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height as u32).to_be_bytes());
+    // Bit depth 1, palette colour, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[1, 3, 0, 0, 0]);
+    push_chunk(&mut out, b"IHDR", &header);
+    let palette = [background, foreground];
+    let colours: Vec<u8> = palette.iter().flat_map(|c| [c.r, c.g, c.b]).collect();
+    push_chunk(&mut out, b"PLTE", &colours);
+    if palette.iter().any(|c| c.a != 255) {
+        push_chunk(&mut out, b"tRNS", &[background.a, foreground.a]);
+    }
+    push_chunk(&mut out, b"IDAT", &zlib);
+    push_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn css(color: Color) -> String {
+    if color.a == 255 {
+        format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+    } else {
+        let alpha = color.a as f32 / 255.0;
+        format!("rgba({},{},{},{alpha:.3})", color.r, color.g, color.b)
+    }
+}
+
+/// Path data for the set pixels of a bitmap, one rectangle per run.
+fn path_data(bitmap: &Bitmap, x: i32, y: i32) -> String {
+    let mut data = String::new();
+    for (row, line) in bitmap.pixels.iter().enumerate() {
+        let mut column = 0;
+        while column < line.len() {
+            if !line[column] {
+                column += 1;
+                continue;
+            }
+            let start = column;
+            while column < line.len() && line[column] {
+                column += 1;
+            }
+            let length = column - start;
+            let _ = write!(
+                data,
+                "M{} {}h{length}v1h-{length}z",
+                x + start as i32,
+                y + row as i32
+            );
+        }
+    }
+    data
+}
+
+/// A standalone SVG element showing a bitmap.
+fn inline_svg(bitmap: &Bitmap, options: &SpecimenOptions) -> String {
+    let scale = options.scale.max(1) as usize;
+    format!(
+        "<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">\
+         <path fill=\"{}\" d=\"{}\"/></svg>",
+        bitmap.width * scale,
+        bitmap.height * scale,
+        bitmap.width,
+        bitmap.height,
+        css(options.foreground),
+        path_data(bitmap, 0, 0)
+    )
+}
+
+fn svg(sheet: &Sheet, options: &SpecimenOptions) -> String {
+    let (items, width, height) = arrange(sheet);
+    let scale = options.scale.max(1) as i32;
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         viewBox=\"0 0 {width} {height}\" shape-rendering=\"crispEdges\">\n\
+         <title>{}</title>\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n\
+         <g fill=\"{}\" font-family=\"monospace\" font-size=\"6\">\n",
+        width * scale,
+        height * scale,
+        escape(&sheet.title),
+        css(options.background),
+        css(options.foreground),
+    );
+    for item in &items {
+        match item {
+            Item::Label { x, y, text } => {
+                let baseline = y + 5;
+                let _ = writeln!(
+                    out,
+                    "<text x=\"{x}\" y=\"{baseline}\">{}</text>",
+                    escape(text)
+                );
+            }
+            Item::Bitmap { x, y, bitmap } => {
+                let _ = writeln!(out, "<path d=\"{}\"/>", path_data(bitmap, *x, *y));
+            }
+        }
+    }
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+fn html(sheet: &Sheet, options: &SpecimenOptions) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>\n\
+         body {{ font-family: sans-serif; color: {fg}; background: {bg}; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         td, th {{ padding: 4px 8px; text-align: left; vertical-align: middle; }}\n\
+         .chart td, .chart th {{ border: 1px solid #ccc; text-align: center; }}\n\
+         th, code {{ font-family: monospace; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>\n<table class=\"metadata\">\n",
+        title = escape(&sheet.title),
+        fg = css(options.foreground),
+        bg = css(options.background),
+    );
+    for (key, value) in &sheet.metadata {
+        let _ = writeln!(
+            out,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            escape(key),
+            escape(value)
+        );
+    }
+    out.push_str("</table>\n");
+
+    if !sheet.chart.is_empty() {
+        out.push_str("<h2>Character chart</h2>\n<table class=\"chart\">\n<tr><th></th>");
+        for column in 0..16 {
+            let _ = write!(out, "<th>{column:X}</th>");
+        }
+        out.push_str("</tr>\n");
+        for (&start, row) in &sheet.chart {
+            let _ = write!(out, "<tr><th>{}</th>", sheet.code_label(start));
+            for (column, cell) in row.iter().enumerate() {
+                match cell {
+                    Some(bitmap) => {
+                        let _ = write!(
+                            out,
+                            "<td title=\"{}\">{}</td>",
+                            sheet.code_label(start + column as u32),
+                            inline_svg(bitmap, options)
+                        );
+                    }
+                    None => out.push_str("<td></td>"),
+                }
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+
+    if !sheet.samples.is_empty() {
+        out.push_str("<h2>Samples</h2>\n");
+        for bitmap in &sheet.samples {
+            let _ = writeln!(out, "<p>{}</p>", inline_svg(bitmap, options));
+        }
+    }
+
+    if !sheet.kerning.is_empty() {
+        out.push_str("<h2>Kerning</h2>\n<table class=\"kerning\">\n");
+        out.push_str("<tr><th>Pair</th><th>Kerning</th><th>Without</th><th>With</th></tr>\n");
+        for pair in &sheet.kerning {
+            let _ = writeln!(
+                out,
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&pair.text),
+                pair.amount,
+                inline_svg(&pair.plain, options),
+                inline_svg(&pair.kerned, options)
+            );
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Generates a specimen sheet of a font in a file format.
+pub fn specimen(font: &YaffFont, format: SpecimenFormat, options: &SpecimenOptions) -> Vec<u8> {
+    let sheet = Sheet::new(font, options);
+    match format {
+        SpecimenFormat::Png => png(&sheet, options),
+        SpecimenFormat::Svg => svg(&sheet, options).into_bytes(),
+        SpecimenFormat::Html => html(&sheet, options).into_bytes(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> YaffFont {
+        "\
+name: Tiny <Test>
+author: A. Person
+ascent: 2
+descent: 0
+
+u+0041:
+    @@
+    @@
+    right-bearing: 1
+    right-kerning:
+        u+0042 -1
+
+u+0042:
+    @.
+    @@
+    right-bearing: 1
+
+u+00E9:
+    .@
+    @@
+"
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sheet_contents() {
+        let options = SpecimenOptions {
+            samples: vec!["AB".to_string()],
+            ..Default::default()
+        };
+        let sheet = Sheet::new(&font(), &options);
+        assert_eq!(
+            sheet.metadata,
+            vec![
+                ("name", "Tiny <Test>".to_string()),
+                ("pixel-size", "2".to_string()),
+                ("spacing", "proportional".to_string()),
+                ("author", "A. Person".to_string()),
+            ]
+        );
+        assert_eq!(
+            sheet.chart.keys().copied().collect::<Vec<_>>(),
+            [0x40, 0xE0]
+        );
+        assert!(sheet.chart[&0x40][1].is_some() && sheet.chart[&0x40][0].is_none());
+        assert_eq!(sheet.kerning.len(), 1);
+        assert_eq!(sheet.kerning[0].text, "AB");
+        assert_eq!(sheet.kerning[0].plain.width, 6);
+        assert_eq!(
+            sheet.kerning[0].kerned.pixels[0],
+            vec![true, true, true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn test_codepoint_labelled_fonts() {
+        let options = SpecimenOptions {
+            samples: vec!["éA".to_string()],
+            ..Default::default()
+        };
+        let mut font: YaffFont = "\
+encoding: cp437
+ascent: 2
+
+0x82:
+    @@
+    @@
+
+0x41:
+    @@
+    @@
+"
+        .parse()
+        .unwrap();
+        let sheet = Sheet::new(&font, &options);
+        assert_eq!(sheet.code_label(0xE0), "U+00E0");
+        assert_eq!(
+            sheet.chart.keys().copied().collect::<Vec<_>>(),
+            [0x40, 0xE0]
+        );
+        assert_eq!(sheet.samples[0].width, 4);
+        assert!(sheet.samples[0].pixels[0].iter().all(|&set| set));
+
+        // Without a known encoding, the chart is of the codes themselves
+        font.encoding = None;
+        let sheet = Sheet::new(&font, &options);
+        assert_eq!(sheet.code_label(0x80), "0x80");
+        assert_eq!(
+            sheet.chart.keys().copied().collect::<Vec<_>>(),
+            [0x40, 0x80]
+        );
+        assert!(sheet.chart[&0x80][2].is_some());
+    }
+
+    #[test]
+    fn test_formats() {
+        let font = font();
+        let options = SpecimenOptions::default();
+
+        let png = specimen(&font, SpecimenFormat::Png, &options);
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR"));
+        assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+
+        let svg = String::from_utf8(specimen(&font, SpecimenFormat::Svg, &options)).unwrap();
+        assert!(svg.contains("<title>Tiny &lt;Test&gt;</title>"));
+        assert!(svg.contains(">U+0040</text>"));
+
+        let html = String::from_utf8(specimen(&font, SpecimenFormat::Html, &options)).unwrap();
+        assert!(html.contains("<tr><th>author</th><td>A. Person</td></tr>"));
+        assert!(html.contains("<td title=\"U+00E9\"><svg"));
+        assert!(html.contains("<code>AB</code></td><td>-1</td>"));
+    }
+
+    #[test]
+    fn test_png_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn test_deflate() {
+        // Fixed codes: literal 'a', then 'a' repeated 3 more times at distance 1
+        assert_eq!(deflate(b"aaaa", 2), [0x4B, 0x04, 0x02, 0x00]);
+        let mut image = Scanlines::new(64, 64);
+        image.set(3, 3);
+        assert_eq!(image.data[image.stride * 3 + 1], 0x10);
+        assert!(deflate(&image.data, image.stride).len() < 32);
+    }
+}
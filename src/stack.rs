@@ -0,0 +1,271 @@
+//! Drawing text with a stack of fallback fonts.
+//!
+//! A [`FontStack`] takes each character from the first font that has a glyph
+//! for it, so a UI font can be composed from a Latin font, a symbols font and a
+//! last-resort font such as Unifont. Runs of text from the same font are placed
+//! with that font's kerning and ligatures. All fonts share one baseline, placed
+//! below the tallest `ascent` of the stack.
+
+use crate::bidi;
+use crate::models::*;
+use crate::render::{GlyphRun, Renderer, canvas, placed_bounds};
+use crate::segment;
+
+/// A glyph of one of the fonts of a stack, placed on a line of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackedGlyph {
+    /// Index of the font in the stack.
+    pub font: usize,
+    /// Index into the font's `glyphs`.
+    pub glyph: usize,
+    /// Horizontal position of the glyph origin from the start of the line.
+    pub x: i32,
+}
+
+/// A line of text turned into positioned glyphs from a font stack.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StackRun {
+    /// Glyphs in visual order, left to right.
+    pub glyphs: Vec<StackedGlyph>,
+    /// Total advance of the line.
+    pub advance: i32,
+    /// Characters of the text that no font of the stack has, in the order they
+    /// first appear. They are drawn with the `default-char` of the font of the
+    /// text around them, if it has one.
+    pub missing: Vec<char>,
+}
+
+/// Fonts that are searched in order for the glyph of each character.
+pub struct FontStack<'a> {
+    renderers: Vec<Renderer<'a>>,
+    ascent: i32,
+    descent: i32,
+}
+
+/// Text of one font at one embedding level.
+struct Segment {
+    font: usize,
+    level: u8,
+    chars: std::ops::Range<usize>,
+}
+
+impl<'a> FontStack<'a> {
+    /// Creates a stack from fonts in order of preference.
+    pub fn new(fonts: impl IntoIterator<Item = &'a YaffFont>) -> Self {
+        let renderers: Vec<Renderer> = fonts.into_iter().map(Renderer::new).collect();
+        let ascent = renderers.iter().map(Renderer::ascent).max().unwrap_or(0);
+        let descent = renderers.iter().map(Renderer::descent).max().unwrap_or(0);
+        FontStack {
+            renderers,
+            ascent,
+            descent,
+        }
+    }
+
+    /// Renderers of the fonts, in order of preference.
+    pub fn renderers(&self) -> &[Renderer<'a>] {
+        &self.renderers
+    }
+
+    /// Height of the shared ascent line above the baseline.
+    pub fn ascent(&self) -> i32 {
+        self.ascent
+    }
+
+    /// Depth of the shared descent line below the baseline, as a positive number.
+    pub fn descent(&self) -> i32 {
+        self.descent
+    }
+
+    /// Index of the first font that has a glyph for `c`.
+    pub fn font_for(&self, c: char) -> Option<usize> {
+        self.renderers.iter().position(|r| r.covers(c))
+    }
+
+    /// Characters of `text` that no font of the stack has, in the order they
+    /// first appear.
+    pub fn missing(&self, text: &str) -> Vec<char> {
+        let mut missing = Vec::new();
+        let starts = segment::cluster_starts(text);
+        for (c, start) in text.chars().zip(starts) {
+            // Joiners and selectors inside a cluster need no glyph
+            let drawn = start || segment::is_combining(c);
+            if drawn && self.font_for(c).is_none() && !missing.contains(&c) {
+                missing.push(c);
+            }
+        }
+        missing
+    }
+
+    /// Splits text into runs of one font and embedding level, in logical order.
+    /// Each cluster takes the font of its first character, and clusters that no
+    /// font has stay with the font before them.
+    fn segments(&self, text: &str, levels: &[u8]) -> Vec<Segment> {
+        let chars: Vec<char> = text.chars().collect();
+        let starts = segment::cluster_starts(text);
+        let mut segments: Vec<Segment> = Vec::new();
+        for i in 0..chars.len() {
+            let current = segments.last_mut();
+            if !starts[i]
+                && let Some(current) = current
+            {
+                current.chars.end = i + 1;
+                continue;
+            }
+            let font = self
+                .font_for(chars[i])
+                .or(current.as_ref().map(|s| s.font))
+                .unwrap_or(0);
+            match current {
+                Some(current) if current.font == font && current.level == levels[i] => {
+                    current.chars.end = i + 1;
+                }
+                _ => segments.push(Segment {
+                    font,
+                    level: levels[i],
+                    chars: i..i + 1,
+                }),
+            }
+        }
+        segments
+    }
+
+    /// Places the glyphs of a line of text in visual order. Runs of text from
+    /// one font are placed by that font's [`Renderer`]; there is no kerning
+    /// between glyphs of different fonts.
+    pub fn place(&self, text: &str) -> StackRun {
+        let Some(primary) = self.renderers.first() else {
+            return StackRun::default();
+        };
+        let (levels, _) = bidi::levels(primary.font(), text);
+        let offsets: Vec<usize> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([text.len()])
+            .collect();
+        let segments = self.segments(text, &levels);
+        let runs: Vec<GlyphRun> = segments
+            .iter()
+            .map(|segment| {
+                let chars = segment.chars.clone();
+                let text = &text[offsets[chars.start]..offsets[chars.end]];
+                self.renderers[segment.font].place_with_levels(text, &levels[chars])
+            })
+            .collect();
+
+        let mut run = StackRun {
+            missing: self.missing(text),
+            ..Default::default()
+        };
+        let segment_levels: Vec<u8> = segments.iter().map(|s| s.level).collect();
+        for index in bidi::visual_order(&segment_levels) {
+            let font = segments[index].font;
+            run.glyphs
+                .extend(runs[index].glyphs.iter().map(|placed| StackedGlyph {
+                    font,
+                    glyph: placed.glyph,
+                    x: run.advance + placed.x,
+                }));
+            run.advance += runs[index].advance;
+        }
+        run
+    }
+
+    /// Width of a line of text.
+    pub fn measure(&self, text: &str) -> i32 {
+        self.place(text).advance
+    }
+
+    /// Bounds of a run as `(left, bottom, right, top)`: the advance and the
+    /// shared ascent and descent lines, extended to hold any ink beyond them.
+    pub fn run_bounds(&self, run: &StackRun) -> (i32, i32, i32, i32) {
+        let glyphs = run.glyphs.iter().map(|placed| {
+            let metrics = self.renderers[placed.font].glyph_metrics(placed.glyph);
+            (placed.x, metrics)
+        });
+        placed_bounds(run.advance, self.ascent, self.descent, glyphs)
+    }
+
+    /// Draws a line of text onto a new bitmap that is as wide as the text and as
+    /// tall as the tallest font, grown where ink extends beyond either.
+    pub fn render(&self, text: &str) -> Bitmap {
+        let run = self.place(text);
+        let bounds = self.run_bounds(&run);
+        let (left, _, _, top) = bounds;
+        let mut bitmap = canvas(bounds);
+        for placed in &run.glyphs {
+            self.renderers[placed.font].draw_glyph(&mut bitmap, placed.glyph, placed.x - left, top);
+        }
+        bitmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rows;
+
+    #[test]
+    fn test_fallback_and_baselines() {
+        let latin: YaffFont = "\
+ascent: 2
+descent: 0
+default-char: u+0041
+
+u+0041:
+    @@
+    @@
+    right-kerning:
+        u+0041 -1
+"
+        .parse()
+        .unwrap();
+        let symbols: YaffFont = "\
+ascent: 3
+descent: 1
+
+u+0041:
+    @@@
+    @@@
+    @@@
+    @@@
+
+u+2665:
+    @.@
+    .@.
+    shift-up: -1
+"
+        .parse()
+        .unwrap();
+        let stack = FontStack::new([&latin, &symbols]);
+        assert_eq!(stack.font_for('A'), Some(0));
+        assert_eq!(stack.font_for('♥'), Some(1));
+        assert_eq!((stack.ascent(), stack.descent()), (3, 1));
+
+        let run = stack.place("AA♥x");
+        let placed: Vec<(usize, usize, i32)> =
+            run.glyphs.iter().map(|p| (p.font, p.glyph, p.x)).collect();
+        // Kerning within the Latin run; the unknown "x" stays with the symbols
+        // font before it, which has no default glyph
+        assert_eq!(placed, vec![(0, 0, 0), (0, 0, 1), (1, 1, 3)]);
+        assert_eq!(run.missing, vec!['x']);
+        assert_eq!(stack.missing("x♥yx"), vec!['x', 'y']);
+
+        // Both fonts sit on the same baseline, three rows from the top
+        assert_eq!(
+            rows(&stack.render("A♥")),
+            vec![".....", "@@...", "@@@.@", "...@."]
+        );
+    }
+
+    #[test]
+    fn test_negative_advance_without_ink() {
+        let font: YaffFont = "ascent: 2\n\n'a':\n    -\n    right-bearing: -3\n"
+            .parse()
+            .unwrap();
+        let stack = FontStack::new([&font]);
+        assert_eq!(stack.measure("a"), -3);
+        let bitmap = stack.render("a");
+        assert_eq!((bitmap.width, bitmap.height), (0, 2));
+    }
+}
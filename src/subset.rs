@@ -0,0 +1,191 @@
+//! Cutting a font down to a set of characters.
+//!
+//! A [`CharSet`] describes the glyphs to keep by Unicode ranges, codepoint
+//! ranges, tags and codepages. [`subset`] removes every other glyph from a font,
+//! drops kerning entries that named the removed glyphs and recalculates the
+//! derived metrics the font stores.
+
+use crate::codepage::Codepage;
+use crate::label::parse_key_as_label;
+use crate::metrics::update_metrics;
+use crate::models::{Label, LineType, YaffFont};
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+/// The characters, codes and tags of the glyphs to keep.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CharSet {
+    /// Unicode characters. A label with a sequence of characters matches when all
+    /// of them are in the set.
+    pub ranges: Vec<RangeInclusive<char>>,
+    /// Codepoints, compared with the bytes of a codepoint label read as a
+    /// big-endian number.
+    pub codepoints: Vec<RangeInclusive<u32>>,
+    pub tags: Vec<String>,
+    /// Characters a codepage can encode, and codepoints it can decode.
+    pub codepages: Vec<Codepage>,
+}
+
+impl CharSet {
+    /// The characters of a string.
+    pub fn from_chars(text: &str) -> Self {
+        CharSet {
+            ranges: text.chars().map(|c| c..=c).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Looks up a character set by name: `ascii` for printable ASCII, `latin-1`
+    /// for printable ISO-8859-1, or the name of a codepage such as `cp437`.
+    pub fn named(name: &str) -> Option<Self> {
+        let ranges = match crate::codepage::normalize_name(name).as_str() {
+            "ascii" | "usascii" => vec![' '..='~'],
+            "latin1" => vec![' '..='~', '\u{a0}'..='\u{ff}'],
+            _ => {
+                return Codepage::from_name(name).map(|codepage| CharSet {
+                    codepages: vec![codepage],
+                    ..Default::default()
+                });
+            }
+        };
+        Some(CharSet {
+            ranges,
+            ..Default::default()
+        })
+    }
+
+    /// Adds the contents of another set.
+    pub fn extend(&mut self, other: CharSet) {
+        self.ranges.extend(other.ranges);
+        self.codepoints.extend(other.codepoints);
+        self.tags.extend(other.tags);
+        self.codepages.extend(other.codepages);
+    }
+
+    pub fn contains_char(&self, c: char) -> bool {
+        self.ranges.iter().any(|range| range.contains(&c))
+            || self.codepages.iter().any(|cp| cp.encode(c).is_some())
+    }
+
+    pub fn contains(&self, label: &Label) -> bool {
+        match label {
+            Label::Unicode(values) => {
+                !values.is_empty()
+                    && values
+                        .iter()
+                        .all(|&v| char::from_u32(v).is_some_and(|c| self.contains_char(c)))
+            }
+            Label::Codepoint(bytes) => {
+                let code = bytes.iter().fold(0u32, |code, &b| code << 8 | b as u32);
+                self.codepoints.iter().any(|range| range.contains(&code))
+                    || self.codepages.iter().any(|cp| cp.decode(bytes).is_some())
+            }
+            Label::Tag(tag) => self.tags.contains(tag),
+            Label::Anonymous => false,
+        }
+    }
+}
+
+/// Labels of the glyphs `default-char` and `word-boundary` name.
+fn special_labels(font: &YaffFont) -> Vec<Label> {
+    [&font.default_char_label_raw, &font.word_boundary_label_raw]
+        .into_iter()
+        .flatten()
+        .filter_map(|raw| parse_key_as_label(raw, &LineType::Empty))
+        .collect()
+}
+
+/// Keep only the glyphs with a label in `charset`
+///
+/// The glyphs named by `default-char` and `word-boundary` are kept as well.
+/// Kerning entries naming a removed glyph are dropped, and derived metrics the
+/// font has are recalculated with [`update_metrics`].
+pub fn subset(font: &mut YaffFont, charset: &CharSet) {
+    let special = special_labels(font);
+    font.glyphs.retain(|glyph| {
+        glyph
+            .labels
+            .iter()
+            .any(|label| charset.contains(label) || special.contains(label))
+    });
+
+    let kept: HashSet<Label> = font
+        .glyphs
+        .iter()
+        .flat_map(|glyph| glyph.labels.iter().cloned())
+        .collect();
+    for glyph in &mut font.glyphs {
+        for kerning in [&mut glyph.right_kerning, &mut glyph.left_kerning] {
+            if let Some(map) = kerning {
+                map.retain(|label, _| kept.contains(label));
+                if map.is_empty() {
+                    *kerning = None;
+                }
+            }
+        }
+    }
+    update_metrics(font);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{char, filled, glyph};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_charset_matching() {
+        let ascii = CharSet::named("ASCII").unwrap();
+        assert!(ascii.contains(&char('A')));
+        assert!(ascii.contains(&Label::Unicode(vec!['f' as u32, 'i' as u32])));
+        assert!(!ascii.contains(&char('é')));
+        assert!(!ascii.contains(&Label::Codepoint(vec![0x41])));
+
+        let cp437 = CharSet::named("cp437").unwrap();
+        assert!(cp437.contains(&char('░')));
+        assert!(cp437.contains(&Label::Codepoint(vec![0xb0])));
+        assert!(!cp437.contains(&char('€')));
+        assert_eq!(CharSet::named("no-such-charset"), None);
+
+        let mut set = CharSet {
+            codepoints: vec![0x8140..=0x81ff],
+            tags: vec!["logo".into()],
+            ..Default::default()
+        };
+        set.extend(CharSet::from_chars("xy"));
+        assert!(set.contains(&Label::Codepoint(vec![0x81, 0x40])));
+        assert!(!set.contains(&Label::Codepoint(vec![0x40])));
+        assert!(set.contains(&Label::Tag("logo".into())));
+        assert!(set.contains(&char('y')));
+        assert!(!set.contains(&char('z')));
+    }
+
+    #[test]
+    fn test_subset_prunes_glyphs_and_kerning() {
+        let mut a = glyph(vec![char('A')], filled(1, 1));
+        a.right_kerning = Some(HashMap::from([(char('V'), -1.0), (char('é'), -1.0)]));
+        let mut e = glyph(vec![char('é')], filled(3, 1));
+        e.left_kerning = Some(HashMap::from([(char('A'), -1.0)]));
+        let mut font = YaffFont {
+            default_char_label_raw: Some("u+FFFD".into()),
+            max_width: Some(3),
+            glyphs: vec![
+                a,
+                glyph(vec![char('V')], filled(1, 1)),
+                e,
+                glyph(vec![char('\u{fffd}')], filled(2, 1)),
+            ],
+            ..Default::default()
+        };
+
+        subset(&mut font, &CharSet::named("ascii").unwrap());
+        let labels: Vec<&Label> = font.glyphs.iter().map(|g| &g.labels[0]).collect();
+        assert_eq!(
+            font.glyphs[0].right_kerning,
+            Some(HashMap::from([(char('V'), -1.0)]))
+        );
+        // The default glyph stays, and is now the widest
+        assert_eq!(labels, [&char('A'), &char('V'), &char('\u{fffd}')]);
+        assert_eq!(font.max_width, Some(2));
+    }
+}
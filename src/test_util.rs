@@ -0,0 +1,53 @@
+//! Fixtures shared by the unit tests.
+
+use crate::models::{Bitmap, GlyphDefinition, Label};
+
+/// A bitmap drawn as rows of `@` for set pixels and `.` for unset ones.
+pub fn bitmap(rows: &[&str]) -> Bitmap {
+    let pixels: Vec<Vec<bool>> = rows
+        .iter()
+        .map(|row| row.chars().map(|c| c == '@').collect())
+        .collect();
+    Bitmap {
+        width: pixels.first().map_or(0, Vec::len),
+        height: pixels.len(),
+        pixels,
+    }
+}
+
+/// A bitmap with every pixel set.
+pub fn filled(width: usize, height: usize) -> Bitmap {
+    Bitmap {
+        pixels: vec![vec![true; width]; height],
+        width,
+        height,
+    }
+}
+
+/// The rows of a bitmap drawn with `@` and `.`, the reverse of [`bitmap`].
+pub fn rows(bitmap: &Bitmap) -> Vec<String> {
+    bitmap
+        .pixels
+        .iter()
+        .map(|row| row.iter().map(|&p| if p { '@' } else { '.' }).collect())
+        .collect()
+}
+
+/// The label of a single character.
+pub fn char(c: char) -> Label {
+    Label::Unicode(vec![c as u32])
+}
+
+/// A glyph with labels and a bitmap, and no properties of its own.
+pub fn glyph(labels: Vec<Label>, bitmap: Bitmap) -> GlyphDefinition {
+    GlyphDefinition {
+        labels,
+        bitmap,
+        ..Default::default()
+    }
+}
+
+/// A glyph with labels and no pixels.
+pub fn labelled(labels: Vec<Label>) -> GlyphDefinition {
+    glyph(labels, Bitmap::default())
+}
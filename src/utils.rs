@@ -1,4 +1,7 @@
+use crate::codepage::{Codepage, normalize_name};
+use crate::metrics::{bearing_anchors, calculate_metrics, glyph_metrics_all};
 use crate::models::*;
+use std::collections::HashMap;
 
 /// Calculate the ascent value for a font based on its glyphs
 pub fn calculate_ascent(font: &YaffFont) -> i32 {
@@ -116,43 +119,478 @@ pub fn minimize_glyph_bounding_box(glyph_def: &mut GlyphDefinition, ascent: i32)
     glyph_def.bitmap.height = new_bitmap_height;
 }
 
-/// Convert Codepoint labels to Unicode labels for ASCII range
+/// Pad every glyph to a common character cell, the inverse of minimizing
+///
+/// Each bitmap is grown to its full advance and to the height from the descent
+/// line to the ascent line (extended to hold any ink beyond them), and per-glyph
+/// bearings and shifts are removed; the baseline is kept with a global `shift-up`.
+/// Glyphs twice the cell width are kept as such in multi-cell fonts. Sets
+/// `spacing`, `cell-size`, `ascent` and `descent` to match.
+///
+/// Fails without changing the font if the advances vary, or if any ink sticks
+/// out to the left or right of its advance.
+pub fn expand_to_character_cell(font: &mut YaffFont) -> Result<(), CellError> {
+    let spacing = calculate_metrics(font).spacing;
+    let metrics = glyph_metrics_all(font);
+    let Some(cell_width) = metrics.iter().map(|m| m.advance_width).min() else {
+        return Ok(());
+    };
+    if spacing == FontSpacing::Proportional || cell_width <= 0 {
+        return Err(CellError::Proportional);
+    }
+
+    let (ascent, descent) = bearing_anchors(font);
+    let mut top = ascent;
+    let mut bottom = -descent;
+    for (index, metrics) in metrics.iter().enumerate() {
+        if let Some((left, ink_bottom, right, ink_top)) = metrics.ink_bounds {
+            if left < 0 || right > metrics.advance_width {
+                return Err(CellError::InkOutsideCell { glyph: index });
+            }
+            top = top.max(ink_top);
+            bottom = bottom.min(ink_bottom);
+        }
+    }
+    let height = (top - bottom) as usize;
+
+    for (glyph, metrics) in font.glyphs.iter_mut().zip(&metrics) {
+        let width = metrics.advance_width as usize;
+        let mut pixels = vec![vec![false; width]; height];
+        let (left, _, _, raster_top) = metrics.raster_bounds;
+        for (y, row) in glyph.bitmap.pixels.iter().enumerate() {
+            for (x, _) in row.iter().enumerate().filter(|(_, set)| **set) {
+                let cell_x = (left + x as i32) as usize;
+                let cell_y = (top - raster_top + y as i32) as usize;
+                pixels[cell_y][cell_x] = true;
+            }
+        }
+        glyph.bitmap = Bitmap {
+            pixels,
+            width,
+            height,
+        };
+        glyph.left_bearing = None;
+        glyph.right_bearing = None;
+        glyph.shift_up = None;
+        glyph.top_bearing = None;
+        glyph.bottom_bearing = None;
+    }
+
+    font.global_left_bearing = None;
+    font.global_right_bearing = None;
+    font.global_shift_up = (bottom != 0).then_some(bottom);
+    font.ascent = Some(top);
+    font.descent = Some(-bottom);
+    font.cell_size = Some((cell_width as u32, height as u32));
+    if spacing == FontSpacing::Monospace {
+        font.spacing = Some(FontSpacing::CharacterCell);
+    } else {
+        font.spacing = Some(spacing);
+    }
+    Ok(())
+}
+
+/// Convert Codepoint labels to Unicode labels using the font's encoding
 ///
 /// For all glyphs that don't have a Unicode label, but do have a Codepoint label,
-/// create a Unicode label from the Codepoint label. If there is no encoding,
-/// assume ASCII, i.e. convert codepoints 0x20 to 0x7E, ignore the rest.
-/// For other encodings, do nothing.
+/// create a Unicode label from the Codepoint label by looking up its byte sequence
+/// in the codepage named by `font.encoding`. If there is no encoding, assume ASCII,
+/// i.e. convert codepoints 0x20 to 0x7E, ignore the rest.
+/// For encodings without a known codepage, do nothing.
 pub fn convert_codepoint_to_unicode_labels(font: &mut YaffFont) {
-    match font.encoding.as_deref() {
-        None | Some("ascii") => {
-            for glyph in &mut font.glyphs {
-                if glyph.labels.iter().any(|l| matches!(l, Label::Unicode(_))) {
-                    // Already has a Unicode label, skip
-                    continue;
-                }
-                if let Some(Label::Codepoint(codepoints)) = glyph
-                    .labels
-                    .iter()
-                    .find(|l| matches!(l, Label::Codepoint(_)))
-                {
-                    let unicode_labels: Vec<Label> = codepoints
-                        .iter()
-                        .filter_map(|&cp| {
-                            if (0x20..=0x7E).contains(&cp) {
-                                Some(Label::Unicode(vec![cp as u32]))
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-                    if !unicode_labels.is_empty() {
-                        glyph.labels.extend(unicode_labels);
-                    }
-                }
+    let encoding = font.encoding.as_deref().unwrap_or("ascii");
+    let Some(codepage) = Codepage::from_name(encoding) else {
+        log::warn!("Unsupported encoding '{encoding}'. Codepoint labels are not converted.");
+        return;
+    };
+    for glyph in &mut font.glyphs {
+        if glyph.labels.iter().any(|l| matches!(l, Label::Unicode(_))) {
+            // Already has a Unicode label, skip
+            continue;
+        }
+        let decoded = glyph.labels.iter().find_map(|l| match l {
+            Label::Codepoint(bytes) => Some(codepage.decode(bytes)),
+            _ => None,
+        });
+        if let Some(Some(c)) = decoded {
+            glyph.labels.push(Label::Unicode(vec![c as u32]));
+        }
+    }
+}
+
+/// Convert Unicode labels to Codepoint labels for the given encoding
+///
+/// For all glyphs that don't have a Codepoint label, but do have a single-character
+/// Unicode label that exists in the target codepage, add the corresponding
+/// Codepoint label and set `font.encoding` if it was unset. If the font already
+/// declares a different encoding, or the encoding is unknown, do nothing.
+pub fn convert_unicode_to_codepoint_labels(font: &mut YaffFont, encoding: &str) {
+    if let Some(existing) = font.encoding.as_deref()
+        && normalize_name(existing) != normalize_name(encoding)
+    {
+        log::warn!(
+            "Font already uses encoding '{existing}'. Not adding codepoint labels for '{encoding}'."
+        );
+        return;
+    }
+    let Some(codepage) = Codepage::from_name(encoding) else {
+        log::warn!("Unsupported encoding '{encoding}'. Unicode labels are not converted.");
+        return;
+    };
+    for glyph in &mut font.glyphs {
+        if glyph
+            .labels
+            .iter()
+            .any(|l| matches!(l, Label::Codepoint(_)))
+        {
+            // Already has a Codepoint label, skip
+            continue;
+        }
+        let codepoint = glyph.labels.iter().find_map(|l| match l {
+            Label::Unicode(values) if values.len() == 1 => {
+                std::char::from_u32(values[0]).and_then(|c| codepage.encode(c))
             }
+            _ => None,
+        });
+        if let Some(bytes) = codepoint {
+            glyph.labels.push(Label::Codepoint(bytes));
         }
-        Some(_) => {
-            log::warn!("Unsupported encoding. Only ASCII is processed.");
+    }
+    if font.encoding.is_none() {
+        font.encoding = Some(encoding.to_string());
+    }
+}
+
+/// Fix the ascent and descent of a font whose glyphs are positioned with
+/// `top-bearing` or `bottom-bearing`, so that they don't move when other glyphs change
+pub(crate) fn pin_bearing_anchors(font: &mut YaffFont, force: bool) {
+    let uses_bearings = font
+        .glyphs
+        .iter()
+        .any(|g| g.top_bearing.is_some() || g.bottom_bearing.is_some());
+    if force || uses_bearings {
+        let (ascent, descent) = bearing_anchors(font);
+        font.ascent.get_or_insert(ascent);
+        font.descent.get_or_insert(descent);
+    }
+}
+
+/// Move the global bearings and shift into every glyph
+///
+/// Each glyph that inherits `left-bearing`, `right-bearing` or `shift-up` from the
+/// global properties gets its own copy, and the global properties are removed.
+/// The rendered font is unchanged.
+pub fn expand_global_metrics(font: &mut YaffFont) {
+    pin_bearing_anchors(font, false);
+    let metrics = glyph_metrics_all(font);
+    for (glyph, metrics) in font.glyphs.iter_mut().zip(metrics) {
+        if font.global_left_bearing.is_some() {
+            glyph.left_bearing = Some(metrics.left_bearing);
+        }
+        if font.global_right_bearing.is_some() {
+            glyph.right_bearing = Some(metrics.right_bearing);
+        }
+        if font.global_shift_up.is_some()
+            && glyph.top_bearing.is_none()
+            && glyph.bottom_bearing.is_none()
+        {
+            glyph.shift_up = Some(metrics.shift_up);
         }
     }
+    font.global_left_bearing = None;
+    font.global_right_bearing = None;
+    font.global_shift_up = None;
+}
+
+/// Factor the most common bearings and shift out into the global properties
+///
+/// Glyphs are first converted to the `shift-up` form with
+/// [`convert_bearings_to_shift_up`]. The most common value of each of `left-bearing`,
+/// `right-bearing` and `shift-up` then becomes the global value, and glyphs that
+/// have it no longer store their own. The rendered font is unchanged.
+pub fn factor_global_metrics(font: &mut YaffFont) {
+    convert_bearings_to_shift_up(font);
+    let metrics = glyph_metrics_all(font);
+
+    // Most common value, preferring the smaller one on ties so the result is stable
+    fn most_common(values: impl Iterator<Item = i32>) -> Option<i32> {
+        let mut counts: HashMap<i32, usize> = HashMap::new();
+        for value in values {
+            *counts.entry(value).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .max_by_key(|&(value, count)| (count, std::cmp::Reverse(value)))
+            .map(|(value, _)| value)
+            .filter(|&value| value != 0)
+    }
+    let left = most_common(metrics.iter().map(|m| m.left_bearing));
+    let right = most_common(metrics.iter().map(|m| m.right_bearing));
+    let shift_up = most_common(metrics.iter().map(|m| m.shift_up));
+
+    let own_value =
+        |value: i32, global: Option<i32>| (value != global.unwrap_or(0)).then_some(value);
+    for (glyph, metrics) in font.glyphs.iter_mut().zip(metrics) {
+        glyph.left_bearing = own_value(metrics.left_bearing, left);
+        glyph.right_bearing = own_value(metrics.right_bearing, right);
+        glyph.shift_up = own_value(metrics.shift_up, shift_up);
+    }
+    font.global_left_bearing = left;
+    font.global_right_bearing = right;
+    font.global_shift_up = shift_up;
+}
+
+/// Position every glyph with `shift-up` instead of `top-bearing` or `bottom-bearing`
+///
+/// The rendered font is unchanged.
+pub fn convert_bearings_to_shift_up(font: &mut YaffFont) {
+    let metrics = glyph_metrics_all(font);
+    for (glyph, metrics) in font.glyphs.iter_mut().zip(metrics) {
+        if glyph.top_bearing.is_some() || glyph.bottom_bearing.is_some() {
+            glyph.shift_up = Some(metrics.shift_up);
+            glyph.top_bearing = None;
+            glyph.bottom_bearing = None;
+        }
+    }
+}
+
+/// Position every glyph with `top-bearing` instead of `shift-up`
+///
+/// The top bearing is measured from the ascent, so the font's `ascent` and
+/// `descent` are set if they were missing. The global shift is removed. The
+/// rendered font is unchanged.
+pub fn convert_shift_up_to_bearings(font: &mut YaffFont) {
+    pin_bearing_anchors(font, true);
+    let metrics = glyph_metrics_all(font);
+    for (glyph, metrics) in font.glyphs.iter_mut().zip(metrics) {
+        glyph.top_bearing = Some(metrics.top_bearing);
+        glyph.shift_up = None;
+        glyph.bottom_bearing = None;
+    }
+    font.global_shift_up = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{filled, labelled, rows};
+
+    #[test]
+    fn test_convert_codepoint_to_unicode_with_codepage() {
+        let mut font = YaffFont {
+            encoding: Some("cp437".to_string()),
+            glyphs: vec![
+                labelled(vec![Label::Codepoint(vec![0x03])]),
+                labelled(vec![Label::Codepoint(vec![0x41])]),
+                labelled(vec![
+                    Label::Codepoint(vec![0x42]),
+                    Label::Unicode(vec![0x42]),
+                ]),
+            ],
+            ..Default::default()
+        };
+        convert_codepoint_to_unicode_labels(&mut font);
+        assert_eq!(font.glyphs[0].labels[1], Label::Unicode(vec!['♥' as u32]));
+        assert_eq!(font.glyphs[1].labels[1], Label::Unicode(vec![0x41]));
+        assert_eq!(font.glyphs[2].labels.len(), 2);
+    }
+
+    #[test]
+    fn test_convert_codepoint_to_unicode_defaults_to_ascii() {
+        let mut font = YaffFont {
+            glyphs: vec![
+                labelled(vec![Label::Codepoint(vec![0x41])]),
+                labelled(vec![Label::Codepoint(vec![0x81])]),
+            ],
+            ..Default::default()
+        };
+        convert_codepoint_to_unicode_labels(&mut font);
+        assert_eq!(font.glyphs[0].labels[1], Label::Unicode(vec![0x41]));
+        assert_eq!(font.glyphs[1].labels.len(), 1);
+    }
+
+    #[test]
+    fn test_convert_unicode_to_codepoint() {
+        let mut font = YaffFont {
+            glyphs: vec![
+                labelled(vec![Label::Unicode(vec!['é' as u32])]),
+                labelled(vec![Label::Unicode(vec!['€' as u32])]),
+            ],
+            ..Default::default()
+        };
+        convert_unicode_to_codepoint_labels(&mut font, "windows-1252");
+        assert_eq!(font.encoding.as_deref(), Some("windows-1252"));
+        assert_eq!(font.glyphs[0].labels[1], Label::Codepoint(vec![0xE9]));
+        assert_eq!(font.glyphs[1].labels[1], Label::Codepoint(vec![0x80]));
+
+        // A font that already declares another encoding is left alone
+        let mut font = YaffFont {
+            encoding: Some("cp437".to_string()),
+            glyphs: vec![labelled(vec![Label::Unicode(vec!['é' as u32])])],
+            ..Default::default()
+        };
+        convert_unicode_to_codepoint_labels(&mut font, "latin-1");
+        assert_eq!(font.glyphs[0].labels.len(), 1);
+    }
+
+    #[cfg(feature = "cjk")]
+    #[test]
+    fn test_convert_double_byte_labels() {
+        let mut font = YaffFont {
+            encoding: Some("shift-jis".to_string()),
+            glyphs: vec![labelled(vec![Label::Codepoint(vec![0x82, 0xA0])])],
+            ..Default::default()
+        };
+        convert_codepoint_to_unicode_labels(&mut font);
+        assert_eq!(font.glyphs[0].labels[1], Label::Unicode(vec!['あ' as u32]));
+
+        let mut font = YaffFont {
+            glyphs: vec![labelled(vec![Label::Unicode(vec!['가' as u32])])],
+            ..Default::default()
+        };
+        convert_unicode_to_codepoint_labels(&mut font, "euc-kr");
+        assert_eq!(font.glyphs[0].labels[1], Label::Codepoint(vec![0xB0, 0xA1]));
+    }
+
+    fn bearings_font() -> YaffFont {
+        let glyph = |left, right, shift_up, top_bearing, bottom_bearing| GlyphDefinition {
+            bitmap: Bitmap {
+                pixels: vec![vec![true, false]; 3],
+                width: 2,
+                height: 3,
+            },
+            left_bearing: left,
+            right_bearing: right,
+            shift_up,
+            top_bearing,
+            bottom_bearing,
+            ..Default::default()
+        };
+        YaffFont {
+            global_left_bearing: Some(1),
+            global_shift_up: Some(-1),
+            glyphs: vec![
+                glyph(None, Some(1), None, None, None),
+                glyph(Some(0), Some(1), Some(2), None, None),
+                glyph(None, None, None, Some(1), None),
+                glyph(Some(-1), None, Some(5), None, None),
+                glyph(None, Some(1), None, None, Some(3)),
+                glyph(None, Some(1), None, None, None),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bearing_normalisation_keeps_rendering() {
+        type Operation = fn(&mut YaffFont);
+        let operations: [(&str, Operation); 4] = [
+            ("expand", expand_global_metrics),
+            ("factor", factor_global_metrics),
+            ("to shift-up", convert_bearings_to_shift_up),
+            ("to bearings", convert_shift_up_to_bearings),
+        ];
+        for (name, operation) in operations {
+            let mut font = bearings_font();
+            let before = glyph_metrics_all(&font);
+            operation(&mut font);
+            let after = glyph_metrics_all(&font);
+            for (before, after) in before.iter().zip(&after) {
+                assert_eq!(before.raster_bounds, after.raster_bounds, "{name}");
+                assert_eq!(before.advance_width, after.advance_width, "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_bearing_normalisation_forms() {
+        let mut font = bearings_font();
+        expand_global_metrics(&mut font);
+        assert_eq!(font.global_left_bearing, None);
+        assert_eq!(font.global_shift_up, None);
+        assert!(font.glyphs.iter().all(|g| g.left_bearing.is_some()));
+        assert_eq!(font.glyphs[2].shift_up, None);
+
+        factor_global_metrics(&mut font);
+        assert_eq!(font.global_left_bearing, Some(1));
+        assert_eq!(font.global_right_bearing, Some(1));
+        assert_eq!(font.global_shift_up, Some(-1));
+        assert_eq!(font.glyphs[0].left_bearing, None);
+        assert_eq!(font.glyphs[1].left_bearing, Some(0));
+        assert_eq!(font.glyphs[2].right_bearing, Some(0));
+        assert!(font.glyphs.iter().all(|g| g.top_bearing.is_none()));
+
+        convert_shift_up_to_bearings(&mut font);
+        assert_eq!(font.global_shift_up, None);
+        assert!(font.ascent.is_some() && font.descent.is_some());
+        assert!(font.glyphs.iter().all(|g| g.shift_up.is_none()));
+    }
+
+    #[test]
+    fn test_expand_to_character_cell() {
+        let mut font = YaffFont {
+            descent: Some(1),
+            glyphs: vec![
+                GlyphDefinition {
+                    bitmap: Bitmap {
+                        pixels: vec![vec![true]],
+                        width: 1,
+                        height: 1,
+                    },
+                    left_bearing: Some(1),
+                    right_bearing: Some(1),
+                    shift_up: Some(2),
+                    ..Default::default()
+                },
+                GlyphDefinition {
+                    bitmap: filled(2, 2),
+                    left_bearing: Some(1),
+                    shift_up: Some(-1),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        font.ascent = Some(3);
+        expand_to_character_cell(&mut font).unwrap();
+
+        assert_eq!(font.cell_size, Some((3, 4)));
+        assert_eq!(font.global_shift_up, Some(-1));
+        assert_eq!(font.spacing, Some(FontSpacing::CharacterCell));
+        assert_eq!(rows(&font.glyphs[0].bitmap), [".@.", "...", "...", "..."]);
+        assert_eq!(rows(&font.glyphs[1].bitmap), ["...", "...", ".@@", ".@@"]);
+        assert!(
+            font.glyphs
+                .iter()
+                .all(|g| g.left_bearing.is_none() && g.shift_up.is_none())
+        );
+    }
+
+    #[test]
+    fn test_expand_to_character_cell_errors() {
+        let glyph = |width: usize, left_bearing| GlyphDefinition {
+            bitmap: filled(width, 1),
+            left_bearing: Some(left_bearing),
+            ..Default::default()
+        };
+        let mut font = YaffFont {
+            glyphs: vec![glyph(2, 0), glyph(3, 0)],
+            ..Default::default()
+        };
+        let original = font.clone();
+        assert_eq!(
+            expand_to_character_cell(&mut font),
+            Err(CellError::Proportional)
+        );
+        assert_eq!(font, original);
+
+        let mut font = YaffFont {
+            glyphs: vec![glyph(2, 0), glyph(3, -1)],
+            ..Default::default()
+        };
+        assert_eq!(
+            expand_to_character_cell(&mut font),
+            Err(CellError::InkOutsideCell { glyph: 1 })
+        );
+    }
 }
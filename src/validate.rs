@@ -0,0 +1,362 @@
+//! Semantic checks on a loaded font.
+//!
+//! The parser only checks that a document is well-formed. [`validate`] looks at the
+//! font as a whole for data that is consistent syntactically but contradicts itself,
+//! such as kerning pairs naming glyphs the font doesn't have.
+
+use crate::diagnostic::Severity;
+use crate::metrics::glyph_metrics_all;
+use crate::models::*;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Category of a problem found by [`validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IssueKind {
+    /// A label used by more than one glyph.
+    DuplicateLabel,
+    /// A kerning entry naming a label that no glyph has.
+    UnknownKerningLabel,
+    /// `ascent + descent` differs from `pixel-size`.
+    InconsistentVerticalMetrics,
+    /// A glyph in a `monospace` font whose advance differs from the others.
+    InconsistentAdvance,
+    /// A glyph that doesn't fit in the font's `cell-size`.
+    CellSizeMismatch,
+    /// `default-char` or `word-boundary` naming a glyph the font doesn't have.
+    MissingSpecialGlyph,
+    /// Bitmap `pixels` that don't match its `width` and `height`.
+    InconsistentBitmap,
+}
+
+impl IssueKind {
+    /// Stable identifier for tools that filter or count issues.
+    pub fn code(self) -> &'static str {
+        match self {
+            IssueKind::DuplicateLabel => "duplicate-label",
+            IssueKind::UnknownKerningLabel => "unknown-kerning-label",
+            IssueKind::InconsistentVerticalMetrics => "inconsistent-vertical-metrics",
+            IssueKind::InconsistentAdvance => "inconsistent-advance",
+            IssueKind::CellSizeMismatch => "cell-size-mismatch",
+            IssueKind::MissingSpecialGlyph => "missing-special-glyph",
+            IssueKind::InconsistentBitmap => "inconsistent-bitmap",
+        }
+    }
+}
+
+/// A problem found by [`validate`]. `glyph` is the index into `font.glyphs`, or
+/// `None` for problems with the global properties.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+    pub glyph: Option<usize>,
+    pub message: String,
+}
+
+impl Issue {
+    fn new(severity: Severity, kind: IssueKind, glyph: Option<usize>, message: String) -> Self {
+        Issue {
+            severity,
+            kind,
+            glyph,
+            message,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.glyph {
+            Some(index) => write!(f, "Glyph {index}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Checks a font for semantic problems the parser doesn't catch.
+///
+/// Errors are data that can't be used as-is, like a bitmap whose rows don't match
+/// its size; warnings are contradictions a renderer can work around. Issues are
+/// returned per glyph in glyph order, after the font-wide ones.
+pub fn validate(font: &YaffFont) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    check_vertical_metrics(font, &mut issues);
+    check_special_glyphs(font, &mut issues);
+    check_bitmaps(font, &mut issues);
+    check_labels(font, &mut issues);
+    check_advances(font, &mut issues);
+    check_cell_size(font, &mut issues);
+    issues.sort_by_key(|issue| issue.glyph.map_or(0, |index| index + 1));
+    issues
+}
+
+fn check_vertical_metrics(font: &YaffFont, issues: &mut Vec<Issue>) {
+    // Summed in i64, as the parser accepts any i32
+    if let (Some(ascent), Some(descent), Some(pixel_size)) =
+        (font.ascent, font.descent, font.pixel_size)
+        && let height = i64::from(ascent) + i64::from(descent)
+        && height != i64::from(pixel_size)
+    {
+        issues.push(Issue::new(
+            Severity::Warning,
+            IssueKind::InconsistentVerticalMetrics,
+            None,
+            format!(
+                "ascent ({ascent}) + descent ({descent}) = {height} differs from pixel-size ({pixel_size})."
+            ),
+        ));
+    }
+}
+
+fn check_special_glyphs(font: &YaffFont, issues: &mut Vec<Issue>) {
+    for (key, raw) in [
+        ("default-char", &font.default_char_label_raw),
+        ("word-boundary", &font.word_boundary_label_raw),
+    ] {
+        let Some(raw) = raw else {
+            continue;
+        };
+        let found = crate::label::parse_key_as_label(raw, &LineType::Empty)
+            .is_some_and(|label| font.glyphs.iter().any(|g| g.labels.contains(&label)));
+        if !found {
+            issues.push(Issue::new(
+                Severity::Warning,
+                IssueKind::MissingSpecialGlyph,
+                None,
+                format!("{key} '{raw}' does not name a glyph in the font."),
+            ));
+        }
+    }
+}
+
+fn check_bitmaps(font: &YaffFont, issues: &mut Vec<Issue>) {
+    for (index, glyph) in font.glyphs.iter().enumerate() {
+        let bitmap = &glyph.bitmap;
+        let message = if bitmap.pixels.len() != bitmap.height {
+            format!(
+                "Bitmap has {} rows but a height of {}.",
+                bitmap.pixels.len(),
+                bitmap.height
+            )
+        } else if let Some(row) = bitmap.pixels.iter().position(|r| r.len() != bitmap.width) {
+            format!(
+                "Bitmap row {row} has {} pixels but the width is {}.",
+                bitmap.pixels[row].len(),
+                bitmap.width
+            )
+        } else {
+            continue;
+        };
+        issues.push(Issue::new(
+            Severity::Error,
+            IssueKind::InconsistentBitmap,
+            Some(index),
+            message,
+        ));
+    }
+}
+
+fn check_labels(font: &YaffFont, issues: &mut Vec<Issue>) {
+    let mut owners: HashMap<&Label, usize> = HashMap::new();
+    for (index, glyph) in font.glyphs.iter().enumerate() {
+        for label in &glyph.labels {
+            if *label == Label::Anonymous {
+                continue;
+            }
+            match owners.get(label) {
+                Some(&first) if first != index => issues.push(Issue::new(
+                    Severity::Warning,
+                    IssueKind::DuplicateLabel,
+                    Some(index),
+                    format!("Label {label} is already used by glyph {first}."),
+                )),
+                Some(_) => {}
+                None => {
+                    owners.insert(label, index);
+                }
+            }
+        }
+    }
+
+    for (index, glyph) in font.glyphs.iter().enumerate() {
+        for (side, kerning) in [
+            ("right-kerning", &glyph.right_kerning),
+            ("left-kerning", &glyph.left_kerning),
+        ] {
+            let Some(kerning) = kerning else {
+                continue;
+            };
+            let mut unknown: Vec<String> = kerning
+                .keys()
+                .filter(|label| !owners.contains_key(label))
+                .map(Label::to_string)
+                .collect();
+            unknown.sort();
+            for label in unknown {
+                issues.push(Issue::new(
+                    Severity::Warning,
+                    IssueKind::UnknownKerningLabel,
+                    Some(index),
+                    format!("{side} refers to label {label}, which no glyph has."),
+                ));
+            }
+        }
+    }
+}
+
+fn check_advances(font: &YaffFont, issues: &mut Vec<Issue>) {
+    if font.spacing != Some(FontSpacing::Monospace) {
+        return;
+    }
+    let advances: Vec<i32> = glyph_metrics_all(font)
+        .iter()
+        .map(|m| m.advance_width)
+        .collect();
+    let mut counts: HashMap<i32, usize> = HashMap::new();
+    for &advance in &advances {
+        *counts.entry(advance).or_default() += 1;
+    }
+    // The most common advance is taken to be the intended one; ties go to the smaller
+    let Some(expected) = counts
+        .iter()
+        .max_by_key(|&(&advance, &count)| (count, std::cmp::Reverse(advance)))
+        .map(|(&advance, _)| advance)
+    else {
+        return;
+    };
+    for (index, &advance) in advances.iter().enumerate() {
+        if advance != expected {
+            issues.push(Issue::new(
+                Severity::Warning,
+                IssueKind::InconsistentAdvance,
+                Some(index),
+                format!(
+                    "Advance is {advance} in a monospace font where most glyphs advance {expected}."
+                ),
+            ));
+        }
+    }
+}
+
+fn check_cell_size(font: &YaffFont, issues: &mut Vec<Issue>) {
+    let Some((cell_width, cell_height)) = font.cell_size else {
+        return;
+    };
+    // Multi-cell fonts may have glyphs two cells wide
+    let max_width = match font.spacing {
+        Some(FontSpacing::MultiCell) => 2 * i64::from(cell_width),
+        _ => i64::from(cell_width),
+    };
+    let metrics = glyph_metrics_all(font);
+    for (index, (glyph, metrics)) in font.glyphs.iter().zip(&metrics).enumerate() {
+        let advance = metrics.advance_width;
+        let message = if i64::from(advance) > max_width {
+            format!("Advance {advance} is wider than the cell width {cell_width}.")
+        } else if glyph.bitmap.height > cell_height as usize {
+            format!(
+                "Bitmap height {} is taller than the cell height {cell_height}.",
+                glyph.bitmap.height
+            )
+        } else {
+            continue;
+        };
+        issues.push(Issue::new(
+            Severity::Warning,
+            IssueKind::CellSizeMismatch,
+            Some(index),
+            message,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{char, filled, glyph};
+
+    #[test]
+    fn test_valid_font_has_no_issues() {
+        let mut font = YaffFont::new();
+        font.ascent = Some(6);
+        font.descent = Some(2);
+        font.pixel_size = Some(8);
+        font.spacing = Some(FontSpacing::Monospace);
+        font.cell_size = Some((4, 8));
+        font.default_char_label_raw = Some("u+0041".to_string());
+        let mut a = glyph(vec![char('A')], filled(3, 8));
+        a.right_bearing = Some(1);
+        a.right_kerning = Some(HashMap::from([(Label::Unicode(vec![0x42]), -1.0)]));
+        let b = glyph(vec![char('B')], filled(4, 8));
+        font.glyphs = vec![a, b];
+        assert_eq!(validate(&font), vec![]);
+    }
+
+    #[test]
+    fn test_reports_inconsistencies() {
+        let mut font = YaffFont::new();
+        font.ascent = Some(6);
+        font.descent = Some(1);
+        font.pixel_size = Some(8);
+        font.spacing = Some(FontSpacing::Monospace);
+        font.cell_size = Some((4, 4));
+        font.default_char_label_raw = Some("\"missing\"".to_string());
+
+        let mut a = glyph(vec![char('A')], filled(4, 4));
+        a.right_kerning = Some(HashMap::from([(Label::Tag("nowhere".to_string()), 1.0)]));
+        let b = glyph(vec![char('A')], filled(4, 4));
+        let c = glyph(vec![char('C')], filled(5, 4));
+        let mut d = glyph(vec![char('D')], filled(4, 4));
+        d.bitmap.pixels.pop();
+        let e = glyph(vec![char('E')], filled(4, 5));
+        font.glyphs = vec![a, b, c, d, e];
+
+        let summary: Vec<(Option<usize>, IssueKind)> = validate(&font)
+            .iter()
+            .map(|issue| (issue.glyph, issue.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (None, IssueKind::InconsistentVerticalMetrics),
+                (None, IssueKind::MissingSpecialGlyph),
+                (Some(0), IssueKind::UnknownKerningLabel),
+                (Some(1), IssueKind::DuplicateLabel),
+                (Some(2), IssueKind::InconsistentAdvance),
+                (Some(2), IssueKind::CellSizeMismatch),
+                (Some(3), IssueKind::InconsistentBitmap),
+                (Some(4), IssueKind::CellSizeMismatch),
+            ]
+        );
+
+        let issues = validate(&font);
+        assert_eq!(
+            issues[3].to_string(),
+            "Glyph 1: Label u+0041 is already used by glyph 0."
+        );
+        assert_eq!(issues[6].severity, Severity::Error);
+        assert_eq!(issues[6].code(), "inconsistent-bitmap");
+    }
+
+    #[test]
+    fn test_extreme_metrics_do_not_overflow() {
+        let mut font = YaffFont::new();
+        font.ascent = Some(i32::MAX);
+        font.descent = Some(1);
+        font.pixel_size = Some(i32::MIN);
+        font.spacing = Some(FontSpacing::MultiCell);
+        font.cell_size = Some((u32::MAX, 1));
+        font.glyphs = vec![glyph(vec![char('A')], filled(1, 1))];
+
+        let issues = validate(&font);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].message,
+            "ascent (2147483647) + descent (1) = 2147483648 differs from pixel-size (-2147483648)."
+        );
+    }
+}
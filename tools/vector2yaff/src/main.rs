@@ -1,10 +1,10 @@
 use freetype::face::LoadFlag;
 use freetype::Library;
-use libyaff::{to_yaff_string, Bitmap, GlyphDefinition, Label, YaffFont};
+use libyaff::{Bitmap, GlyphDefinition, Label, YaffFont, YaffWriter};
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufWriter, Write};
 use std::ops::RangeInclusive;
 use std::path::Path;
 
@@ -13,22 +13,25 @@ fn convert_metric(value: i64) -> i32 {
     (value >> 6) as i32
 }
 
-/// Converts a font file (e.g., TTF, OTF) to a YaffFont struct.
+/// Converts a font file (e.g., TTF, OTF) to YAFF, writing each glyph to `output`
+/// as soon as it has been rendered.
 ///
 /// # Arguments
 /// * `font_path`: Path to the font file.
 /// * `point_size`: Desired point size for rendering.
 /// * `dpi`: DPI (dots per inch) for rendering. Common values are 72 or 96.
 /// * `range`: Range of character codes to include in the font.
+/// * `output`: Destination for the YAFF data.
 ///
 /// # Returns
-/// * `YaffFont`: A YaffFont struct containing the font data.
-pub fn convert_font_to_yaff(
+/// * `W`: The output writer, flushed.
+pub fn convert_font_to_yaff<W: Write>(
     font_path: &str,
     point_size: f32,
     dpi: u32,
     range_vec: Vec<RangeInclusive<u32>>,
-) -> Result<YaffFont, Box<dyn std::error::Error>> {
+    output: W,
+) -> Result<W, Box<dyn std::error::Error>> {
     let mut font = YaffFont::default();
 
     // Initialize FreeType and load the font face.
@@ -104,6 +107,17 @@ pub fn convert_font_to_yaff(
     font.source_format = Some(format_str.to_string());
     font.dpi = Some((dpi, dpi));
 
+    let mut writer = YaffWriter::new(output);
+    writer.write_header(&font)?;
+
+    // Character codes present in the face, needed up front for kerning pairs
+    let char_codes: Vec<u32> = range_vec
+        .iter()
+        .cloned()
+        .flatten()
+        .filter(|&char_code| face.get_char_index(char_code as usize).is_some())
+        .collect();
+
     for char_code_val in range_vec.into_iter().flatten() {
         // Load glyph with monochrome rendering.
         if face
@@ -181,66 +195,34 @@ pub fn convert_font_to_yaff(
         let bitmap_bottom = bitmap_top - rows as i32;
         glyph_def.shift_up = Some(bitmap_bottom);
 
-        font.glyphs.push(glyph_def);
-    }
-
-    // Extract kerning information if available
-    if face.has_kerning() {
-        // Collect all character codes we've processed
-        let char_codes: Vec<u32> = font
-            .glyphs
-            .iter()
-            .filter_map(|g| {
-                // Extract character code from Unicode label
-                if let Some(Label::Unicode(codes)) = g.labels.first() {
-                    codes.first().copied()
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        // Build a map for right_kerning data
-        let mut kerning_map: HashMap<u32, HashMap<Label, f32>> = HashMap::new();
-
-        // Check kerning between all pairs
-        for &left_char in &char_codes {
+        // Right kerning against every other glyph in the requested ranges
+        if face.has_kerning()
+            && let Some(left_index) = face.get_char_index(char_code_val as usize)
+        {
+            let mut kern_pairs: HashMap<Label, f32> = HashMap::new();
             for &right_char in &char_codes {
-                if let Some(left_index) = face.get_char_index(left_char as usize) {
-                    if let Some(right_index) = face.get_char_index(right_char as usize) {
-                        if let Ok(kerning) = face.get_kerning(
-                            left_index,
-                            right_index,
-                            freetype::face::KerningMode::KerningDefault,
-                        ) {
-                            let kern_x = convert_metric(kerning.x);
-                            if kern_x != 0 {
-                                let right_label = Label::Unicode(vec![right_char]);
-
-                                kerning_map
-                                    .entry(left_char)
-                                    .or_default()
-                                    .insert(right_label, kern_x as f32);
-                            }
-                        }
+                if let Some(right_index) = face.get_char_index(right_char as usize)
+                    && let Ok(kerning) = face.get_kerning(
+                        left_index,
+                        right_index,
+                        freetype::face::KerningMode::KerningDefault,
+                    )
+                {
+                    let kern_x = convert_metric(kerning.x);
+                    if kern_x != 0 {
+                        kern_pairs.insert(Label::Unicode(vec![right_char]), kern_x as f32);
                     }
                 }
             }
-        }
-
-        // Apply kerning data to glyphs
-        for glyph in &mut font.glyphs {
-            if let Some(Label::Unicode(codes)) = glyph.labels.first() {
-                if let Some(&char_code) = codes.first() {
-                    if let Some(kern_pairs) = kerning_map.get(&char_code) {
-                        glyph.right_kerning = Some(kern_pairs.clone());
-                    }
-                }
+            if !kern_pairs.is_empty() {
+                glyph_def.right_kerning = Some(kern_pairs);
             }
         }
+
+        writer.write_glyph(&glyph_def)?;
     }
 
-    Ok(font)
+    Ok(writer.finish()?)
 }
 
 // Logic for parsing ranges from command line
@@ -317,11 +299,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     let ranges = parse_ranges(&positional[2])?;
     let output_file_path = &positional[3];
 
-    match convert_font_to_yaff(font_path, point_size, dpi_value, ranges) {
-        Ok(font) => {
-            let yaff_content = to_yaff_string(&font);
-            let mut file = File::create(output_file_path)?;
-            file.write_all(yaff_content.as_bytes())?;
+    let file = BufWriter::new(File::create(output_file_path)?);
+    match convert_font_to_yaff(font_path, point_size, dpi_value, ranges, file) {
+        Ok(_) => {
             println!("YAFF file '{output_file_path}' created successfully.");
         }
         Err(e) => {
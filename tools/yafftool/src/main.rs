@@ -0,0 +1,200 @@
+use libyaff::{
+    Align, Bitmap, Color, LayoutOptions, PreviewOptions, PreviewStyle, Renderer, SpecimenFormat,
+    SpecimenOptions, YaffFont, layout, preview, specimen,
+};
+use std::error::Error;
+
+const USAGE: &str = "\
+Usage: yafftool <COMMAND> [OPTIONS] <ARGS>
+
+Commands:
+  preview [OPTIONS] <FONT_YAFF> [TEXT]
+      Shows TEXT in the terminal, or every glyph of the font without it.
+      --style <half|braille|sixel>  Characters or graphics to draw with (default: half)
+      --fg <RRGGBB>                 Colour of set pixels
+      --bg <RRGGBB>                 Colour of unset pixels
+      --scale <N>                   Screen pixels per font pixel for sixel (default: 1)
+      --width <PIXELS>              Wrap TEXT at this width
+      --columns <N>                 Glyphs per row without TEXT (default: 16)
+  specimen [OPTIONS] <FONT_YAFF> <OUTPUT>
+      Writes a specimen sheet with metadata, a character chart, samples and kerning.
+      --format <png|svg|html>       File format (default: from the OUTPUT extension)
+      --scale <N>                   Image pixels per font pixel (default: 2)
+      --sample <TEXT>               Sample text instead of the pangrams, repeatable
+      --fg <RRGGBB>                 Ink colour (default: 000000)
+      --bg <RRGGBB>                 Paper colour (default: ffffff)";
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    std::process::exit(1);
+}
+
+/// Splits arguments into `--option value` pairs and positional arguments.
+fn parse_options<'a>(
+    args: &'a [String],
+    known: &[&str],
+) -> (Vec<(&'a str, &'a str)>, Vec<&'a str>) {
+    let mut options = vec![];
+    let mut positional = vec![];
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if arg.starts_with("--") {
+            if !known.contains(&arg) {
+                usage_error(&format!("Unknown option {arg}"));
+            }
+            let Some(value) = args.get(i + 1) else {
+                usage_error(&format!("Expected argument after {arg}"));
+            };
+            options.push((arg, value.as_str()));
+            i += 2;
+        } else {
+            positional.push(arg);
+            i += 1;
+        }
+    }
+    (options, positional)
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| usage_error(&format!("Invalid value '{value}' for {option}")))
+}
+
+fn parse_color(option: &str, value: &str) -> Color {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    match u32::from_str_radix(hex, 16) {
+        Ok(rgb) if hex.len() == 6 => Color::rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8),
+        _ => usage_error(&format!("Invalid colour '{value}' for {option}")),
+    }
+}
+
+/// Draws every glyph of a font in a grid of character cells, one pixel apart.
+fn glyph_sheet(renderer: &Renderer, columns: usize) -> Bitmap {
+    let font = renderer.font();
+    let count = font.glyphs.len();
+    let cell_width = (0..count)
+        .map(|glyph| renderer.advance(glyph))
+        .max()
+        .unwrap_or(0)
+        .max(1) as usize
+        + 1;
+    let cell_height = (renderer.ascent() + renderer.descent()).max(1) as usize + 1;
+    let columns = columns.clamp(1, count.max(1));
+    let rows = count.div_ceil(columns);
+    let width = columns * cell_width;
+    let height = rows * cell_height;
+    let mut sheet = Bitmap {
+        pixels: vec![vec![false; width]; height],
+        width,
+        height,
+    };
+    for glyph in 0..count {
+        let x = (glyph % columns * cell_width) as i32;
+        let y = (glyph / columns * cell_height) as i32;
+        renderer.draw_glyph(&mut sheet, glyph, x, y + renderer.ascent());
+    }
+    sheet
+}
+
+fn preview_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let known = ["--style", "--fg", "--bg", "--scale", "--width", "--columns"];
+    let (options, positional) = parse_options(args, &known);
+    let [font_path, text @ ..] = positional.as_slice() else {
+        usage_error("Expected a font file");
+    };
+
+    let mut preview_options = PreviewOptions::default();
+    let mut width = None;
+    let mut columns = 16;
+    for (option, value) in options {
+        match option {
+            "--style" => {
+                preview_options.style = match value {
+                    "half" => PreviewStyle::HalfBlock,
+                    "braille" => PreviewStyle::Braille,
+                    "sixel" => PreviewStyle::Sixel,
+                    _ => usage_error(&format!("Unknown style '{value}'")),
+                }
+            }
+            "--fg" => preview_options.foreground = Some(parse_color(option, value)),
+            "--bg" => preview_options.background = Some(parse_color(option, value)),
+            "--scale" => preview_options.scale = parse_number(option, value),
+            "--width" => width = Some(parse_number(option, value)),
+            "--columns" => columns = parse_number(option, value),
+            _ => unreachable!(),
+        }
+    }
+
+    let font = YaffFont::from_path(font_path)?;
+    let renderer = Renderer::new(&font);
+    let bitmap = if text.is_empty() {
+        glyph_sheet(&renderer, columns)
+    } else {
+        let layout_options = LayoutOptions {
+            width,
+            align: Align::Left,
+        };
+        layout(&renderer, &text.join(" "), &layout_options).render(&renderer)
+    };
+    print!("{}", preview(&bitmap, &preview_options));
+    Ok(())
+}
+
+fn specimen_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let known = ["--format", "--scale", "--sample", "--fg", "--bg"];
+    let (options, positional) = parse_options(args, &known);
+    let [font_path, output_path] = positional.as_slice() else {
+        usage_error("Expected a font file and an output file");
+    };
+
+    let mut specimen_options = SpecimenOptions::default();
+    let mut samples = vec![];
+    let mut format = None;
+    for (option, value) in options {
+        match option {
+            "--format" => format = Some(parse_format(value)),
+            "--scale" => specimen_options.scale = parse_number(option, value),
+            "--sample" => samples.push(value.to_string()),
+            "--fg" => specimen_options.foreground = parse_color(option, value),
+            "--bg" => specimen_options.background = parse_color(option, value),
+            _ => unreachable!(),
+        }
+    }
+    if !samples.is_empty() {
+        specimen_options.samples = samples;
+    }
+    let format = format.unwrap_or_else(|| {
+        let extension = output_path.rsplit_once('.').map_or("", |(_, ext)| ext);
+        parse_format(&extension.to_lowercase())
+    });
+
+    let font = YaffFont::from_path(font_path)?;
+    std::fs::write(output_path, specimen(&font, format, &specimen_options))?;
+    println!("Specimen '{output_path}' created successfully.");
+    Ok(())
+}
+
+fn parse_format(value: &str) -> SpecimenFormat {
+    match value {
+        "png" => SpecimenFormat::Png,
+        "svg" => SpecimenFormat::Svg,
+        "html" | "htm" => SpecimenFormat::Html,
+        _ => usage_error(&format!("Unknown specimen format '{value}'")),
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("preview") => preview_command(&args[2..]),
+        Some("specimen") => specimen_command(&args[2..]),
+        Some("--help" | "-h") => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        Some(command) => usage_error(&format!("Unknown command '{command}'")),
+        None => usage_error("Expected a command"),
+    }
+}